@@ -0,0 +1,135 @@
+// 通知帧转发路径对比：完整 Value 解析 + 克隆 + 重新序列化 vs RawValue 零拷贝透传
+// 运行：cargo bench --bench passthrough
+// 以计数分配器统计每帧的分配次数与字节数，以 Instant 统计每帧耗时。
+
+use serde_json::Value;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+#[path = "../src/frame.rs"]
+#[allow(dead_code, unused_imports)]
+mod frame;
+
+struct Counting;
+
+static ALLOCS: AtomicU64 = AtomicU64::new(0);
+static ALLOC_BYTES: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        ALLOC_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        ALLOC_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// 构造接近实际的大帧：多卷磁盘 IO、SMART、Wi-Fi BSS 列表与 netsh 原始输出
+fn sample_frame() -> Vec<u8> {
+    let volumes: Vec<Value> = (0..8)
+        .map(|i| {
+            serde_json::json!({
+                "volume_id": format!("C{}:", i), "free_percent": 41.5,
+                "read_bytes_per_sec": 1024.0 * i as f64, "write_bytes_per_sec": 2048.0,
+                "read_iops": 12.0, "write_iops": 30.0, "busy_percent": 3.2, "queue_length": 0.01
+            })
+        })
+        .collect();
+    let bss: Vec<Value> = (0..40)
+        .map(|i| {
+            serde_json::json!({
+                "ssid": format!("network-{}", i), "bssid": format!("aa:bb:cc:dd:ee:{:02x}", i),
+                "rssi_dbm": -40 - i, "channel": 1 + i % 13, "band": "2.4GHz", "security": "WPA2-Personal"
+            })
+        })
+        .collect();
+    let payload = serde_json::json!({
+        "ts": 1_700_000_000_000u64, "seq": 42,
+        "cpu": { "usage_percent": 12.5, "per_core": [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0] },
+        "memory": { "total_mb": 32768, "used_mb": 12000, "percent_used": 36.6 },
+        "disk": {
+            "read_bytes_per_sec": 1.0, "write_bytes_per_sec": 2.0, "queue_length": 0.0,
+            "per_volume_io": volumes,
+            "smart_health": [{ "disk_id": "0", "overall_health": "Good", "temperature_c": 38.0 }]
+        },
+        "network": { "wifi": { "wlan_bss": bss, "netsh_raw": "Interface name : Wi-Fi\r\n".repeat(120) } }
+    });
+    let frame = serde_json::json!({ "jsonrpc": "2.0", "method": "metrics", "params": [payload] });
+    serde_json::to_vec(&frame).unwrap()
+}
+
+/// 旧路径：Value 树 -> 克隆 params -> 解包 -> 序列化为事件负载
+fn legacy(body: &[u8]) -> usize {
+    let v: Value = serde_json::from_slice(body).unwrap();
+    let _event = v.get("method").and_then(|m| m.as_str()).unwrap();
+    let raw_params = v.get("params").cloned().unwrap_or(Value::Null);
+    let payload = match &raw_params {
+        Value::Array(arr) if arr.len() == 1 => arr[0].clone(),
+        _ => raw_params,
+    };
+    serde_json::to_string(&payload).unwrap().len()
+}
+
+/// 新路径：只读 method/id，params 原始切片直接序列化为事件负载
+fn fast(body: &[u8]) -> usize {
+    let env = frame::parse_envelope(body).unwrap();
+    let _event = env.notification().unwrap();
+    let payload = frame::unwrap_params(env.params.unwrap());
+    serde_json::to_string(payload).unwrap().len()
+}
+
+/// metrics 帧：仍需从切片解析一次 Value 供断档检测/类型校验，但省去克隆与重新序列化
+fn fast_inspect(body: &[u8]) -> usize {
+    let env = frame::parse_envelope(body).unwrap();
+    let payload = frame::unwrap_params(env.params.unwrap());
+    let v: Value = serde_json::from_str(payload.get()).unwrap();
+    black_box(&v);
+    serde_json::to_string(payload).unwrap().len()
+}
+
+fn run(name: &str, body: &[u8], iters: u32, f: fn(&[u8]) -> usize) {
+    // 预热
+    for _ in 0..iters / 10 {
+        black_box(f(black_box(body)));
+    }
+    let a0 = ALLOCS.load(Ordering::Relaxed);
+    let b0 = ALLOC_BYTES.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for _ in 0..iters {
+        black_box(f(black_box(body)));
+    }
+    let elapsed = started.elapsed();
+    let allocs = ALLOCS.load(Ordering::Relaxed) - a0;
+    let bytes = ALLOC_BYTES.load(Ordering::Relaxed) - b0;
+    println!(
+        "{:<16} {:>10.1} us/frame {:>10.1} allocs/frame {:>12.0} bytes/frame",
+        name,
+        elapsed.as_secs_f64() * 1e6 / iters as f64,
+        allocs as f64 / iters as f64,
+        bytes as f64 / iters as f64,
+    );
+}
+
+fn main() {
+    let iters = 2_000;
+    let body = sample_frame();
+    assert_eq!(legacy(&body), fast(&body));
+    println!("# frame {} bytes", body.len());
+    run("legacy", &body, iters, legacy);
+    run("raw", &body, iters, fast);
+    run("raw+inspect", &body, iters, fast_inspect);
+}
@@ -0,0 +1,365 @@
+// 告警日志（告警收件箱）
+// 记录每条告警的触发、解除、确认与静音区间，保存在应用数据目录的 alert_journal.json，重启后仍可查看。
+// 同一去重键（rule_id 优先，缺省为 metric）未解除前的重复告警合并到同一条目；
+// 条目已确认（直到解除）或该键处于静音期内时，不再向前端转发 alert 事件，只计入 suppressed。
+// 桥接侧规则的条目由规则解除；服务端推送的告警没有解除通知，最后一次告警后 SERVICE_QUIET_MS 内未再出现即视为解除。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const JOURNAL_FILE: &str = "alert_journal.json";
+/// 条目上限，超出时丢弃最早的
+const MAX_ENTRIES: usize = 500;
+/// 服务端告警的静默解除时间
+pub const SERVICE_QUIET_MS: u64 = 300_000;
+
+/// 告警来源：桥接侧规则（alerts.rs）或服务端推送
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    Bridge,
+    /// 旧日志中没有该字段的条目按服务端告警处理，以便静默解除
+    #[default]
+    Service,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snooze {
+    pub from: u64,
+    pub until: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub key: String,
+    #[serde(default)]
+    pub origin: Origin,
+    pub rule_id: Option<String>,
+    pub level: String,
+    pub metric: String,
+    pub message: String,
+    pub threshold: Option<f64>,
+    /// 最近一次告警的值
+    pub value: Option<f64>,
+    pub fired_ts: u64,
+    pub last_ts: u64,
+    pub count: u64,
+    pub resolved_ts: Option<u64>,
+    pub acked_ts: Option<u64>,
+    pub snoozes: Vec<Snooze>,
+    /// 因确认/静音未转发的次数
+    pub suppressed: u64,
+}
+
+fn str_field(v: &Value, key: &str) -> Option<String> {
+    v.get(key).and_then(|x| x.as_str()).map(|s| s.to_string())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    next_id: u64,
+    entries: Vec<JournalEntry>,
+    /// 去重键 -> 静音截止时间
+    snoozed: BTreeMap<String, u64>,
+}
+
+impl Journal {
+    pub const fn new() -> Self {
+        Self { next_id: 1, entries: Vec::new(), snoozed: BTreeMap::new() }
+    }
+
+    fn open_mut(&mut self, key: &str) -> Option<&mut JournalEntry> {
+        self.entries.iter_mut().rev().find(|e| e.key == key && e.resolved_ts.is_none())
+    }
+
+    fn find_mut(&mut self, id: u64) -> Result<&mut JournalEntry, String> {
+        self.entries.iter_mut().find(|e| e.id == id).ok_or_else(|| format!("alert not found: {}", id))
+    }
+
+    /// 解除静默超过 SERVICE_QUIET_MS 的服务端告警条目，返回被解除的条目
+    pub fn expire(&mut self, now: u64) -> Vec<JournalEntry> {
+        self.entries
+            .iter_mut()
+            .filter(|e| e.origin == Origin::Service && e.resolved_ts.is_none() && now.saturating_sub(e.last_ts) >= SERVICE_QUIET_MS)
+            .map(|e| {
+                e.resolved_ts = Some(e.last_ts + SERVICE_QUIET_MS);
+                e.clone()
+            })
+            .collect()
+    }
+
+    /// 记录一次告警；返回 (是否转发, 更新后的条目)
+    pub fn on_alert(&mut self, payload: &Value, origin: Origin, now: u64) -> (bool, JournalEntry) {
+        self.expire(now);
+        let metric = str_field(payload, "metric").unwrap_or_default();
+        let rule_id = str_field(payload, "rule_id");
+        let key = rule_id.clone().unwrap_or_else(|| metric.clone());
+        let ts = payload.get("ts").and_then(|t| t.as_u64()).unwrap_or(now);
+        self.snoozed.retain(|_, until| *until > now);
+        let snoozed = self.snoozed.contains_key(&key);
+        let level = str_field(payload, "level").unwrap_or_else(|| "info".to_string());
+        let message = str_field(payload, "message").unwrap_or_default();
+        let value = payload.get("value").and_then(|v| v.as_f64());
+        let threshold = payload.get("threshold").and_then(|v| v.as_f64());
+        let (notify, entry) = match self.open_mut(&key) {
+            Some(e) => {
+                e.level = level;
+                e.message = message;
+                e.value = value;
+                e.threshold = threshold.or(e.threshold);
+                e.last_ts = ts;
+                e.count += 1;
+                (e.acked_ts.is_none() && !snoozed, e)
+            }
+            None => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.entries.push(JournalEntry {
+                    id,
+                    key,
+                    origin,
+                    rule_id,
+                    level,
+                    metric,
+                    message,
+                    threshold,
+                    value,
+                    fired_ts: ts,
+                    last_ts: ts,
+                    count: 1,
+                    resolved_ts: None,
+                    acked_ts: None,
+                    snoozes: Vec::new(),
+                    suppressed: 0,
+                });
+                if self.entries.len() > MAX_ENTRIES {
+                    self.entries.remove(0);
+                }
+                (!snoozed, self.entries.last_mut().expect("just pushed"))
+            }
+        };
+        if !notify {
+            entry.suppressed += 1;
+        }
+        (notify, entry.clone())
+    }
+
+    pub fn on_resolved(&mut self, key: &str, value: Option<f64>, ts: u64) -> Option<JournalEntry> {
+        let e = self.open_mut(key)?;
+        e.resolved_ts = Some(ts);
+        if value.is_some() {
+            e.value = value;
+        }
+        Some(e.clone())
+    }
+
+    pub fn ack(&mut self, id: u64, now: u64) -> Result<JournalEntry, String> {
+        let e = self.find_mut(id)?;
+        e.acked_ts.get_or_insert(now);
+        Ok(e.clone())
+    }
+
+    /// 静音该条目的去重键 duration_ms；0 表示取消静音
+    pub fn snooze(&mut self, id: u64, duration_ms: u64, now: u64) -> Result<JournalEntry, String> {
+        let e = self.find_mut(id)?;
+        let key = e.key.clone();
+        if duration_ms == 0 {
+            if let Some(s) = e.snoozes.last_mut().filter(|s| s.until > now) {
+                s.until = now;
+            }
+        } else {
+            e.snoozes.push(Snooze { from: now, until: now + duration_ms });
+        }
+        let e = e.clone();
+        if duration_ms == 0 {
+            self.snoozed.remove(&key);
+        } else {
+            self.snoozed.insert(key, now + duration_ms);
+        }
+        Ok(e)
+    }
+
+    /// 按时间倒序
+    pub fn list(&self, open_only: bool, limit: usize) -> Vec<JournalEntry> {
+        self.entries.iter().rev().filter(|e| !open_only || e.resolved_ts.is_none()).take(limit).cloned().collect()
+    }
+}
+
+static JOURNAL: Mutex<Journal> = Mutex::new(Journal::new());
+static PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+fn save(j: &Journal) {
+    let Some(path) = PATH.lock().ok().and_then(|p| p.clone()) else { return };
+    let result = serde_json::to_vec(j).map_err(|e| e.to_string()).and_then(|bytes| {
+        let tmp = path.with_extension("json.partial");
+        std::fs::write(&tmp, bytes).and_then(|_| std::fs::rename(&tmp, &path)).map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        crate::log_line("ERROR", &format!("alert journal save failed: {}", e));
+    }
+}
+
+/// 修改日志并落盘，条目有变化时发出 alert_journal 事件
+fn update<T>(app: Option<&tauri::AppHandle>, f: impl FnOnce(&mut Journal) -> (T, Option<JournalEntry>)) -> Result<T, String> {
+    let mut j = JOURNAL.lock().map_err(|_| "alert journal lock poisoned".to_string())?;
+    let (out, entry) = f(&mut j);
+    if let Some(entry) = entry {
+        save(&j);
+        if let Some(app) = app {
+            let _ = app.emit("alert_journal", entry);
+        }
+    }
+    Ok(out)
+}
+
+/// 应用启动时调用：载入已有日志
+pub fn init(app: &tauri::AppHandle) {
+    let dir = match app.path().app_data_dir() {
+        Ok(d) => d,
+        Err(e) => {
+            crate::log_line("ERROR", &format!("alert journal: app data dir unavailable: {}", e));
+            return;
+        }
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join(JOURNAL_FILE);
+    if let Ok(bytes) = std::fs::read(&path) {
+        match serde_json::from_slice::<Journal>(&bytes) {
+            Ok(j) => {
+                if let Ok(mut g) = JOURNAL.lock() {
+                    *g = j;
+                }
+            }
+            Err(e) => crate::log_line("WARN", &format!("alert journal not loaded: {}", e)),
+        }
+    }
+    if let Ok(mut p) = PATH.lock() {
+        *p = Some(path);
+    }
+}
+
+/// 服务端 alert 通知与桥接侧规则触发时调用；返回是否应向前端转发 alert 事件
+pub fn on_alert(app: &tauri::AppHandle, payload: &Value, origin: Origin) -> bool {
+    update(Some(app), |j| {
+        let (notify, entry) = j.on_alert(payload, origin, crate::now_millis());
+        (notify, Some(entry))
+    })
+    .unwrap_or(true)
+}
+
+pub fn on_resolved(app: &tauri::AppHandle, key: &str, value: Option<f64>, ts: u64) {
+    let _ = update(Some(app), |j| ((), j.on_resolved(key, value, ts)));
+}
+
+#[tauri::command]
+pub fn list_alert_journal(app: tauri::AppHandle, open_only: Option<bool>, limit: Option<usize>) -> Result<Vec<JournalEntry>, String> {
+    let mut j = JOURNAL.lock().map_err(|_| "alert journal lock poisoned".to_string())?;
+    let expired = j.expire(crate::now_millis());
+    if !expired.is_empty() {
+        save(&j);
+        for e in expired {
+            let _ = app.emit("alert_journal", e);
+        }
+    }
+    Ok(j.list(open_only.unwrap_or(false), limit.unwrap_or(usize::MAX)))
+}
+
+#[tauri::command]
+pub fn ack_alert(app: tauri::AppHandle, id: u64) -> Result<JournalEntry, String> {
+    update(Some(&app), |j| match j.ack(id, crate::now_millis()) {
+        Ok(e) => (Ok(e.clone()), Some(e)),
+        Err(err) => (Err(err), None),
+    })?
+}
+
+#[tauri::command]
+pub fn snooze_alert(app: tauri::AppHandle, id: u64, duration_ms: u64) -> Result<JournalEntry, String> {
+    update(Some(&app), |j| match j.snooze(id, duration_ms, crate::now_millis()) {
+        Ok(e) => (Ok(e.clone()), Some(e)),
+        Err(err) => (Err(err), None),
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn alert(rule: &str, value: f64, ts: u64) -> Value {
+        json!({ "level": "warn", "metric": "cpu.usage_percent", "rule_id": rule, "value": value, "threshold": 90.0, "message": "hot", "ts": ts })
+    }
+
+    #[test]
+    fn merges_repeats_until_resolved() {
+        let mut j = Journal::new();
+        assert!(j.on_alert(&alert("cpu", 91.0, 1), Origin::Bridge, 1).0);
+        let (notify, e) = j.on_alert(&alert("cpu", 95.0, 2), Origin::Bridge, 2);
+        assert!(notify);
+        assert_eq!((e.id, e.count, e.value, e.fired_ts, e.last_ts), (1, 2, Some(95.0), 1, 2));
+        assert_eq!(j.on_resolved("cpu", Some(80.0), 3).unwrap().resolved_ts, Some(3));
+        assert!(j.on_resolved("cpu", None, 4).is_none());
+        let (_, e) = j.on_alert(&alert("cpu", 92.0, 5), Origin::Bridge, 5);
+        assert_eq!(e.id, 2);
+        assert_eq!(j.list(true, 10).len(), 1);
+        assert_eq!(j.list(false, 10).iter().map(|e| e.id).collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn ack_suppresses_until_resolved() {
+        let mut j = Journal::new();
+        j.on_alert(&alert("cpu", 91.0, 1), Origin::Bridge, 1);
+        j.ack(1, 2).unwrap();
+        let (notify, e) = j.on_alert(&alert("cpu", 93.0, 3), Origin::Bridge, 3);
+        assert!(!notify);
+        assert_eq!((e.acked_ts, e.suppressed), (Some(2), 1));
+        j.on_resolved("cpu", None, 4);
+        assert!(j.on_alert(&alert("cpu", 91.0, 5), Origin::Bridge, 5).0);
+        assert!(j.ack(99, 6).is_err());
+    }
+
+    #[test]
+    fn snooze_covers_key_and_expires() {
+        let mut j = Journal::new();
+        j.on_alert(&alert("cpu", 91.0, 1), Origin::Bridge, 1);
+        j.snooze(1, 1_000, 10).unwrap();
+        j.on_resolved("cpu", None, 20);
+        // 静音按去重键生效，解除后新的触发同样不转发
+        let (notify, e) = j.on_alert(&alert("cpu", 95.0, 30), Origin::Bridge, 30);
+        assert!(!notify);
+        assert_eq!((e.id, e.suppressed), (2, 1));
+        assert!(j.on_alert(&alert("other", 95.0, 40), Origin::Bridge, 40).0);
+        assert!(j.on_alert(&alert("cpu", 95.0, 1_010), Origin::Bridge, 1_010).0);
+        // 取消静音会截断记录的区间
+        j.snooze(2, 5_000, 2_000).unwrap();
+        let e = j.snooze(2, 0, 2_500).unwrap();
+        assert_eq!(e.snoozes, vec![Snooze { from: 2_000, until: 2_500 }]);
+        assert!(j.on_alert(&alert("cpu", 95.0, 2_600), Origin::Bridge, 2_600).0);
+        let saved = serde_json::to_string(&j).unwrap();
+        assert_eq!(serde_json::from_str::<Journal>(&saved).unwrap().list(false, 10), j.list(false, 10));
+    }
+
+    #[test]
+    fn acked_service_alert_resolves_after_quiet_period() {
+        let mut j = Journal::new();
+        let service = |value: f64, ts: u64| json!({ "level": "warn", "metric": "disk.free", "value": value, "ts": ts });
+        j.on_alert(&service(1.0, 0), Origin::Service, 0);
+        j.ack(1, 10).unwrap();
+        // 确认期间的重复告警不转发，并推迟静默解除
+        assert!(!j.on_alert(&service(1.0, 60_000), Origin::Service, 60_000).0);
+        assert!(j.expire(60_000 + SERVICE_QUIET_MS - 1).is_empty());
+        let later = 60_000 + SERVICE_QUIET_MS + 1;
+        let (notify, e) = j.on_alert(&service(2.0, later), Origin::Service, later);
+        assert!(notify);
+        assert_eq!((e.id, e.acked_ts), (2, None));
+        assert_eq!(j.list(false, 10)[1].resolved_ts, Some(60_000 + SERVICE_QUIET_MS));
+        // 桥接侧规则的条目只由规则解除
+        j.on_alert(&alert("cpu", 91.0, 0), Origin::Bridge, 0);
+        assert!(j.expire(10 * SERVICE_QUIET_MS).iter().all(|e| e.origin == Origin::Service));
+        assert_eq!(j.list(true, 10).iter().map(|e| e.key.as_str()).collect::<Vec<_>>(), vec!["cpu"]);
+    }
+}
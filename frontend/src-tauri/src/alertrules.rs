@@ -0,0 +1,458 @@
+// 告警规则文件
+// 规则保存在应用配置目录的 alert_rules.toml（[[rules]] 表数组）或 alert_rules.json（{ "rules": [AlertRule, ...] }），
+// 便于纳入版本管理；两者都存在时用 TOML。加载时逐条校验，语法/字段错误与规则编译错误都定位到文件中的行列。
+// 后台线程监听规则文件所在目录（Windows 目录变更通知），目录有变化时比较文件的修改时间与内容哈希，
+// 外部编辑后自动重新加载；文件有错时保留上一次生效的规则并发出错误。
+// 监听不可用（非 Windows 或创建失败）时退回每秒轮询；网络盘可能收不到通知，监听时也每 WATCH_FALLBACK_MS 兜底比对一次。
+
+use crate::alerts::AlertRule;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{Emitter, Manager};
+
+const RULES_FILE: &str = "alert_rules.json";
+const RULES_FILE_TOML: &str = "alert_rules.toml";
+const POLL_MS: u64 = 1_000;
+#[cfg(windows)]
+const WATCH_FALLBACK_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    fn file_name(self) -> &'static str {
+        match self {
+            Format::Json => RULES_FILE,
+            Format::Toml => RULES_FILE_TOML,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RulesError {
+    #[serde(skip)]
+    pub file: &'static str,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl RulesError {
+    fn io(message: String) -> Self {
+        RulesError { file: "", line: 0, column: 0, message }
+    }
+}
+
+impl std::fmt::Display for RulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+    }
+}
+
+/// 字节偏移对应的行列（均从 1 起）
+fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// serde_json 的错误信息去掉末尾的 " at line L column C"
+fn serde_error(e: &serde_json::Error, line: usize, column: usize) -> RulesError {
+    let msg = e.to_string();
+    let message = msg.rfind(" at line ").map(|i| msg[..i].to_string()).unwrap_or(msg);
+    RulesError { file: RULES_FILE, line, column, message }
+}
+
+#[derive(Deserialize)]
+struct RawFile<'a> {
+    #[serde(borrow)]
+    rules: Vec<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+struct TomlFile {
+    #[serde(default)]
+    rules: Vec<toml::Spanned<AlertRule>>,
+}
+
+#[derive(Serialize)]
+struct FileOut<'a> {
+    rules: &'a [AlertRule],
+}
+
+/// id 唯一且启用的规则可编译；出错时定位到该条规则
+fn check_rule(rules: &[AlertRule], rule: &AlertRule) -> Result<(), String> {
+    if rules.iter().any(|r| r.id == rule.id) {
+        return Err(format!("duplicate rule id: {}", rule.id));
+    }
+    if rule.enabled {
+        rule.compile()?;
+    }
+    Ok(())
+}
+
+/// 按格式解析并校验规则文件内容
+pub fn parse_as(format: Format, text: &str) -> Result<Vec<AlertRule>, RulesError> {
+    match format {
+        Format::Json => parse(text),
+        Format::Toml => parse_toml(text),
+    }
+}
+
+/// TOML 规则文件：每条规则为一个 [[rules]] 表
+pub fn parse_toml(text: &str) -> Result<Vec<AlertRule>, RulesError> {
+    let at = |offset: usize, message: String| {
+        let (line, column) = line_col(text, offset.min(text.len()));
+        RulesError { file: RULES_FILE_TOML, line, column, message }
+    };
+    let file: TomlFile = toml::from_str(text).map_err(|e| at(e.span().map(|s| s.start).unwrap_or(0), e.message().to_string()))?;
+    let mut rules: Vec<AlertRule> = Vec::with_capacity(file.rules.len());
+    for spanned in file.rules {
+        let start = spanned.span().start;
+        let rule = spanned.into_inner();
+        check_rule(&rules, &rule).map_err(|m| at(start, m))?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// 解析并校验 JSON 规则文件内容
+pub fn parse(text: &str) -> Result<Vec<AlertRule>, RulesError> {
+    let file: RawFile = serde_json::from_str(text).map_err(|e| serde_error(&e, e.line(), e.column()))?;
+    let mut rules: Vec<AlertRule> = Vec::with_capacity(file.rules.len());
+    for raw in file.rules {
+        // RawValue 借用自 text，可由指针差得到该条规则的起始位置
+        let (line, column) = line_col(text, raw.get().as_ptr() as usize - text.as_ptr() as usize);
+        let rule: AlertRule = serde_json::from_str(raw.get()).map_err(|e| {
+            let (l, c) = if e.line() == 1 { (line, column + e.column() - 1) } else { (line + e.line() - 1, e.column()) };
+            serde_error(&e, l, c)
+        })?;
+        check_rule(&rules, &rule).map_err(|message| RulesError { file: RULES_FILE, line, column, message })?;
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+pub fn to_text(format: Format, rules: &[AlertRule]) -> Result<String, String> {
+    let mut text = match format {
+        Format::Json => serde_json::to_string_pretty(&FileOut { rules }).map_err(|e| e.to_string())?,
+        Format::Toml => toml::to_string_pretty(&FileOut { rules }).map_err(|e| e.to_string())?,
+    };
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// 文件变化的判据：修改时间 + 内容哈希
+type Stamp = (Option<SystemTime>, u64);
+
+struct RulesFile {
+    path: PathBuf,
+    format: Format,
+    stamp: Option<Stamp>,
+    error: Option<RulesError>,
+}
+
+static FILE: Mutex<Option<RulesFile>> = Mutex::new(None);
+
+fn stamp_of(path: &Path, bytes: &[u8]) -> Stamp {
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    bytes.hash(&mut h);
+    (std::fs::metadata(path).and_then(|m| m.modified()).ok(), h.finish())
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    std::fs::read(path).ok().map(|b| stamp_of(path, &b))
+}
+
+/// 目录变更通知句柄（FindFirstChangeNotificationW）
+#[cfg(windows)]
+struct DirNotify(*mut std::ffi::c_void);
+
+// SAFETY: 通知句柄只在监听线程中使用，内核对象句柄可跨线程传递
+#[cfg(windows)]
+unsafe impl Send for DirNotify {}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn FindFirstChangeNotificationW(path: *const u16, watch_subtree: i32, filter: u32) -> *mut std::ffi::c_void;
+    fn FindNextChangeNotification(handle: *mut std::ffi::c_void) -> i32;
+    fn FindCloseChangeNotification(handle: *mut std::ffi::c_void) -> i32;
+    fn WaitForSingleObject(handle: *mut std::ffi::c_void, millis: u32) -> u32;
+}
+
+#[cfg(windows)]
+impl DirNotify {
+    fn open(dir: &Path) -> Option<Self> {
+        use std::os::windows::ffi::OsStrExt;
+        // FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_SIZE | FILE_NOTIFY_CHANGE_LAST_WRITE
+        const FILTER: u32 = 0x1 | 0x8 | 0x10;
+        let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+        // SAFETY: wide 为以 0 结尾的路径，调用期间有效
+        let handle = unsafe { FindFirstChangeNotificationW(wide.as_ptr(), 0, FILTER) };
+        // INVALID_HANDLE_VALUE
+        (handle as isize != -1 && !handle.is_null()).then_some(Self(handle))
+    }
+
+    /// 等到目录有变化（true）或超时（false），之后重新登记下一次通知
+    fn wait(&self, timeout: Duration) -> bool {
+        // SAFETY: 句柄在 self 存活期间有效
+        unsafe {
+            if WaitForSingleObject(self.0, timeout.as_millis() as u32) != 0 {
+                return false;
+            }
+            FindNextChangeNotification(self.0);
+        }
+        true
+    }
+}
+
+#[cfg(windows)]
+impl Drop for DirNotify {
+    fn drop(&mut self) {
+        // SAFETY: 句柄由 FindFirstChangeNotificationW 返回，只关闭一次
+        unsafe {
+            FindCloseChangeNotification(self.0);
+        }
+    }
+}
+
+/// 规则文件变化的等待方式：目录变更通知，不可用时轮询
+enum Watch {
+    #[cfg(windows)]
+    Notify(DirNotify),
+    Poll,
+}
+
+impl Watch {
+    fn new(dir: &Path) -> Self {
+        #[cfg(windows)]
+        if let Some(n) = DirNotify::open(dir) {
+            return Watch::Notify(n);
+        }
+        crate::log_line("INFO", &format!("alert rules: polling {} every {}ms", dir.display(), POLL_MS));
+        Watch::Poll
+    }
+
+    fn wait(&self) {
+        match self {
+            #[cfg(windows)]
+            Watch::Notify(n) => {
+                n.wait(Duration::from_millis(WATCH_FALLBACK_MS));
+            }
+            Watch::Poll => std::thread::sleep(Duration::from_millis(POLL_MS)),
+        }
+    }
+}
+
+/// 读取并应用规则文件；出错时保留当前规则
+fn load(file: &mut RulesFile) -> Result<usize, RulesError> {
+    let result = std::fs::read(&file.path)
+        .map_err(|e| {
+            // 记下“不可读”的状态，文件恢复前不再重复加载与报错
+            file.stamp = None;
+            RulesError::io(e.to_string())
+        })
+        .and_then(|bytes| {
+            file.stamp = Some(stamp_of(&file.path, &bytes));
+            String::from_utf8(bytes).map_err(|e| RulesError::io(e.to_string()))
+        })
+        .and_then(|text| parse_as(file.format, &text))
+        .and_then(|rules| crate::alerts::replace_rules(rules).map_err(RulesError::io));
+    file.error = result.as_ref().err().cloned();
+    result
+}
+
+fn reload(app: &tauri::AppHandle, file: &mut RulesFile) {
+    let payload = match load(file) {
+        Ok(count) => serde_json::json!({ "ok": true, "count": count, "error": null }),
+        Err(e) => {
+            crate::log_line("WARN", &format!("alert rules not reloaded: {}", e));
+            serde_json::json!({ "ok": false, "count": null, "error": e })
+        }
+    };
+    let _ = app.emit("alert_rules_reloaded", payload);
+}
+
+/// 应用启动时调用：加载规则文件（优先 TOML，都不存在时创建空的 JSON 文件）并启动监听线程
+pub fn init(app: &tauri::AppHandle) {
+    let dir = match app.path().app_config_dir() {
+        Ok(d) => d,
+        Err(e) => {
+            crate::log_line("ERROR", &format!("alert rules: app config dir unavailable: {}", e));
+            return;
+        }
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    let format = if dir.join(RULES_FILE_TOML).exists() { Format::Toml } else { Format::Json };
+    let path = dir.join(format.file_name());
+    if !path.exists() {
+        if let Err(e) = to_text(format, &[]).and_then(|t| std::fs::write(&path, t).map_err(|e| e.to_string())) {
+            crate::log_line("ERROR", &format!("alert rules file create failed: {}", e));
+        }
+    }
+    let watch = Watch::new(&dir);
+    let mut file = RulesFile { path, format, stamp: None, error: None };
+    if let Err(e) = load(&mut file) {
+        crate::log_line("WARN", &format!("alert rules not loaded: {}", e));
+    }
+    if let Ok(mut f) = FILE.lock() {
+        *f = Some(file);
+    }
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        watch.wait();
+        let Ok(mut guard) = FILE.lock() else { return };
+        if let Some(file) = guard.as_mut() {
+            if stamp(&file.path) != file.stamp {
+                reload(&app, file);
+            }
+        }
+    });
+}
+
+/// 在当前规则上做修改并写回文件；文件本身有错时拒绝（避免覆盖外部编辑）
+fn modify<T>(f: impl FnOnce(&mut Vec<AlertRule>) -> Result<T, String>) -> Result<T, String> {
+    let mut guard = FILE.lock().map_err(|_| "alert rules lock poisoned".to_string())?;
+    let file = guard.as_mut().ok_or_else(|| "alert rules not initialized".to_string())?;
+    if let Some(e) = &file.error {
+        return Err(format!("rules file has errors, fix it first: {}", e));
+    }
+    let mut rules = crate::alerts::rules();
+    let out = f(&mut rules)?;
+    crate::alerts::validate(&rules)?;
+    // 先写临时文件再替换，外部读取方不会看到半截内容
+    let text = to_text(file.format, &rules)?;
+    let tmp = file.path.with_extension("partial");
+    std::fs::write(&tmp, &text).and_then(|_| std::fs::rename(&tmp, &file.path)).map_err(|e| e.to_string())?;
+    file.stamp = Some(stamp_of(&file.path, text.as_bytes()));
+    crate::alerts::replace_rules(rules)?;
+    Ok(out)
+}
+
+#[derive(Debug, Serialize)]
+pub struct RulesStatus {
+    pub path: Option<PathBuf>,
+    pub rules: Vec<AlertRule>,
+    /// 规则文件当前的错误（此时 rules 为上一次生效的规则）
+    pub error: Option<RulesError>,
+}
+
+#[tauri::command]
+pub fn list_alert_rules() -> Result<RulesStatus, String> {
+    let guard = FILE.lock().map_err(|_| "alert rules lock poisoned".to_string())?;
+    let file = guard.as_ref();
+    Ok(RulesStatus {
+        path: file.map(|f| f.path.clone()),
+        rules: crate::alerts::rules(),
+        error: file.and_then(|f| f.error.clone()),
+    })
+}
+
+/// 按 id 替换或追加；返回 true 表示新增
+#[tauri::command]
+pub fn upsert_alert_rule(rule: AlertRule) -> Result<bool, String> {
+    modify(|rules| match rules.iter_mut().find(|r| r.id == rule.id) {
+        Some(r) => {
+            *r = rule;
+            Ok(false)
+        }
+        None => {
+            rules.push(rule);
+            Ok(true)
+        }
+    })
+}
+
+#[tauri::command]
+pub fn delete_alert_rule(id: String) -> Result<bool, String> {
+    modify(|rules| {
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        Ok(rules.len() != before)
+    })
+}
+
+/// 不保存规则，按独立状态对给定帧（缺省为最近评估过的实时帧，与 alerts::evaluate 所见相同）试运行
+#[tauri::command]
+pub fn test_alert_rule(rule: AlertRule, frames: Option<Vec<Value>>) -> Result<Value, String> {
+    let frames = frames.unwrap_or_else(crate::alerts::recent_frames);
+    crate::alerts::simulate(&rule, &frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn syntax_and_field_errors_are_located() {
+        let e = parse("{\n  \"rules\": [\n    { \"id\": \"a\", \"expr\": \"cpu.usage_percent > 90\" },\n  ]\n}").unwrap_err();
+        assert_eq!((e.line, e.column), (4, 3));
+        let text = "{ \"rules\": [\n  { \"id\": \"a\", \"expr\": \"cpu.usage_percent > 90\" },\n  { \"id\": \"b\",\n    \"level\": \"loud\" }\n] }";
+        let e = parse(text).unwrap_err();
+        assert_eq!(e.line, 4);
+        assert!(e.message.contains("loud"), "{}", e.message);
+        assert!(!e.message.contains("at line"));
+    }
+
+    #[test]
+    fn rule_errors_point_at_the_rule() {
+        let text = "{ \"rules\": [\n  { \"id\": \"a\", \"expr\": \"cpu.usage_percent > 90\" },\n    { \"id\": \"b\", \"expr\": \"cpu.usage_percent >> 90\" }\n] }";
+        let e = parse(text).unwrap_err();
+        assert_eq!((e.line, e.column), (3, 5));
+        assert!(e.to_string().starts_with("alert_rules.json:3:5: b: unknown operator"), "{}", e);
+        let dup = "{ \"rules\": [{ \"id\": \"a\", \"expr\": \"x > 1\" }, { \"id\": \"a\", \"expr\": \"x > 2\" }] }";
+        assert_eq!(parse(dup).unwrap_err().column, 45);
+        // 停用的规则不要求可编译
+        assert_eq!(parse("{ \"rules\": [{ \"id\": \"c\", \"enabled\": false }] }").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn saved_text_round_trips() {
+        let rules: Vec<AlertRule> = serde_json::from_value(json!([
+            { "id": "cpu", "expr": "cpu.usage_percent > 90 for 30s", "hysteresis": 5.0 },
+            { "id": "disk", "metric": "disk.capacity_totals.free_bytes", "op": "<", "threshold": 1e10, "level": "error" }
+        ]))
+        .unwrap();
+        let text = to_text(Format::Json, &rules).unwrap();
+        assert_eq!(parse(&text).unwrap(), rules);
+        assert!(text.find("\"id\"").unwrap() < text.find("\"expr\"").unwrap());
+        let text = to_text(Format::Toml, &rules).unwrap();
+        assert!(text.contains("[[rules]]"), "{}", text);
+        assert_eq!(parse_toml(&text).unwrap(), rules);
+    }
+
+    #[test]
+    fn unreadable_file_is_reported_once() {
+        let path = std::env::temp_dir().join("alert_rules_missing_test.json");
+        let _ = std::fs::remove_file(&path);
+        let mut file = RulesFile { path, format: Format::Json, stamp: Some((None, 1)), error: None };
+        assert!(load(&mut file).is_err());
+        // 轮询看到的状态与记下的一致，文件恢复前不会再次触发重新加载
+        assert_eq!(stamp(&file.path), file.stamp);
+    }
+
+    #[test]
+    fn toml_rules_are_located() {
+        let text = "[[rules]]\nid = \"a\"\nexpr = \"cpu.usage_percent > 90 for 30s\"\nhysteresis = 5\n\n[[rules]]\nid = \"b\"\nexpr = \"cpu.usage_percent >> 90\"\n";
+        let e = parse_toml(text).unwrap_err();
+        assert_eq!(e.line, 6);
+        assert!(e.to_string().starts_with("alert_rules.toml:6:"), "{}", e);
+        let e = parse_toml("[[rules]]\nid = \"a\"\nlevel = \"loud\"\n").unwrap_err();
+        assert!(e.message.contains("loud"), "{}", e.message);
+        let ok = parse_toml("[[rules]]\nid = \"a\"\nexpr = \"cpu.usage_percent > 90\"\nhysteresis = 5\n").unwrap();
+        assert_eq!(ok[0].hysteresis, 5.0);
+    }
+}
@@ -0,0 +1,547 @@
+// 应用内阈值告警规则
+// 服务端尚未实现 alert 事件（见文档保留的 alert 负载），这里在桥接侧对实时 metrics 评估规则：
+// - 持续窗口：条件连续满足 for_ms 后才触发；
+// - 迟滞：触发后需回落越过 threshold ∓ hysteresis 才解除，避免在阈值附近反复抖动；
+// - 冷却：两次触发间隔不足 cooldown_ms 时本次不再通知（仍记为激活）；
+// - while 条件：不满足时不触发，已激活的随即解除。
+// 规则可写成表达式，如 "cpu.usage_percent > 90 for 30s"、"power.battery.percentage < 15 while discharging"、
+// "disk.capacity_totals.free_bytes < 10 GiB"；触发时按文档的 alert 负载发出 alert 事件，解除时发出 alert_resolved。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Op {
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+impl Op {
+    fn parse(s: &str) -> Option<Op> {
+        Some(match s {
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            "==" | "=" => Op::Eq,
+            "!=" => Op::Ne,
+            _ => return None,
+        })
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+        }
+    }
+
+    fn test(self, a: f64, b: f64) -> bool {
+        match self {
+            Op::Gt => a > b,
+            Op::Ge => a >= b,
+            Op::Lt => a < b,
+            Op::Le => a <= b,
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+        }
+    }
+
+    fn test_value(self, a: &Value, b: &Value) -> bool {
+        match (a.as_f64(), b.as_f64()) {
+            (Some(x), Some(y)) => self.test(x, y),
+            _ => match self {
+                Op::Eq => a == b,
+                Op::Ne => a != b,
+                _ => false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    #[default]
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub metric: String,
+    pub op: Op,
+    pub value: Value,
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: String,
+    #[serde(default = "yes")]
+    pub enabled: bool,
+    /// 表达式写法；与下列字段同时给出时，显式字段优先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expr: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op: Option<Op>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub for_ms: Option<u64>,
+    #[serde(default, rename = "while", skip_serializing_if = "Option::is_none")]
+    pub while_cond: Option<Condition>,
+    #[serde(default)]
+    pub hysteresis: f64,
+    #[serde(default)]
+    pub cooldown_ms: u64,
+    #[serde(default)]
+    pub level: Level,
+    /// 支持 {metric} {value} {threshold} 占位符
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compiled {
+    pub id: String,
+    pub metric: String,
+    pub op: Op,
+    pub threshold: f64,
+    pub for_ms: u64,
+    pub while_cond: Option<Condition>,
+    pub hysteresis: f64,
+    pub cooldown_ms: u64,
+    pub level: Level,
+    pub message: Option<String>,
+}
+
+/// 解析带单位的数值：% 与 B/KB/MB/GB/TB（1000 进制）、KiB/MiB/GiB/TiB（1024 进制）
+fn parse_quantity(num: &str, unit: Option<&str>) -> Option<f64> {
+    let v: f64 = num.parse().ok()?;
+    let factor = match unit.map(|u| u.to_ascii_lowercase()).as_deref() {
+        None | Some("%") | Some("b") => 1.0,
+        Some("kb") => 1e3,
+        Some("mb") => 1e6,
+        Some("gb") => 1e9,
+        Some("tb") => 1e12,
+        Some("kib") => 1024.0,
+        Some("mib") => 1_048_576.0,
+        Some("gib") => 1_073_741_824.0,
+        Some("tib") => 1_099_511_627_776.0,
+        _ => return None,
+    };
+    Some(v * factor)
+}
+
+fn parse_duration_ms(s: &str) -> Option<u64> {
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let v: f64 = s[..split].parse().ok()?;
+    let factor = match &s[split..] {
+        "ms" => 1.0,
+        "" | "s" => 1_000.0,
+        "m" | "min" => 60_000.0,
+        "h" => 3_600_000.0,
+        _ => return None,
+    };
+    Some((v * factor) as u64)
+}
+
+/// 拆分数字与紧随的单位（"10GiB" / "15%"）
+fn split_number(tok: &str) -> (&str, Option<&str>) {
+    let split = tok.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-')).unwrap_or(tok.len());
+    let (n, u) = tok.split_at(split);
+    (n, (!u.is_empty()).then_some(u))
+}
+
+/// (metric, op, threshold, for_ms, while)
+pub type Parsed = (String, Op, f64, Option<u64>, Option<Condition>);
+
+/// `<metric> <op> <number>[unit] [for <duration>] [while <cond>]`；
+/// while 后为 `<metric> <op> <value>`，或单个词作为 power.battery.state 的取值
+pub fn parse_expr(expr: &str) -> Result<Parsed, String> {
+    let tokens: Vec<&str> = expr.split_whitespace().collect();
+    let [metric, op, num, rest @ ..] = tokens.as_slice() else {
+        return Err(format!("expected '<metric> <op> <threshold>': {}", expr));
+    };
+    let op = Op::parse(op).ok_or_else(|| format!("unknown operator: {}", op))?;
+    let (n, mut unit) = split_number(num);
+    let mut rest = rest;
+    if unit.is_none() {
+        if let Some((u, tail)) = rest.split_first().filter(|(u, _)| parse_quantity("1", Some(u)).is_some()) {
+            unit = Some(u);
+            rest = tail;
+        }
+    }
+    let threshold = parse_quantity(n, unit).ok_or_else(|| format!("invalid threshold: {}", num))?;
+    let mut for_ms = None;
+    let mut while_cond = None;
+    while let Some((kw, tail)) = rest.split_first() {
+        match (*kw, tail) {
+            ("for", [d, tail @ ..]) => {
+                for_ms = Some(parse_duration_ms(d).ok_or_else(|| format!("invalid duration: {}", d))?);
+                rest = tail;
+            }
+            ("while", [state]) => {
+                while_cond = Some(Condition { metric: "power.battery.state".into(), op: Op::Eq, value: Value::String(state.to_string()) });
+                rest = &[];
+            }
+            ("while", [m, o, v, tail @ ..]) => {
+                let op = Op::parse(o).ok_or_else(|| format!("unknown operator: {}", o))?;
+                let value = v.parse::<f64>().map(|f| serde_json::json!(f)).unwrap_or_else(|_| Value::String(v.to_string()));
+                while_cond = Some(Condition { metric: m.to_string(), op, value });
+                rest = tail;
+            }
+            _ => return Err(format!("unexpected '{}' in: {}", kw, expr)),
+        }
+    }
+    Ok((metric.to_string(), op, threshold, for_ms, while_cond))
+}
+
+impl AlertRule {
+    pub fn compile(&self) -> Result<Compiled, String> {
+        if self.id.trim().is_empty() {
+            return Err("rule id is empty".into());
+        }
+        let parsed = self.expr.as_deref().map(parse_expr).transpose().map_err(|e| format!("{}: {}", self.id, e))?;
+        let (metric, op, threshold, for_ms, while_cond) = match parsed {
+            Some((m, o, t, f, w)) => (Some(m), Some(o), Some(t), f, w),
+            None => (None, None, None, None, None),
+        };
+        let missing = |what: &str| format!("{}: missing {}", self.id, what);
+        let c = Compiled {
+            id: self.id.clone(),
+            metric: self.metric.clone().or(metric).ok_or_else(|| missing("metric"))?,
+            op: self.op.or(op).ok_or_else(|| missing("op"))?,
+            threshold: self.threshold.or(threshold).ok_or_else(|| missing("threshold"))?,
+            for_ms: self.for_ms.or(for_ms).unwrap_or(0),
+            while_cond: self.while_cond.clone().or(while_cond),
+            hysteresis: self.hysteresis,
+            cooldown_ms: self.cooldown_ms,
+            level: self.level,
+            message: self.message.clone(),
+        };
+        if !c.threshold.is_finite() || c.hysteresis < 0.0 || !c.hysteresis.is_finite() {
+            return Err(format!("{}: invalid threshold/hysteresis", self.id));
+        }
+        Ok(c)
+    }
+}
+
+fn lookup<'a>(frame: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('|').find_map(|p| p.trim().split('.').try_fold(frame, |v, k| v.get(k)).filter(|v| !v.is_null()))
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RuleState {
+    pub rule_id: String,
+    pub active: bool,
+    pub pending_since: Option<i64>,
+    pub last_raised: Option<i64>,
+    pub last_value: Option<f64>,
+    /// 本次激活是否已通知（冷却期内激活不通知）
+    notified: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition {
+    Raised(Value),
+    Resolved { rule_id: String, metric: String, value: Option<f64>, ts: i64 },
+}
+
+impl Compiled {
+    fn message(&self, value: f64) -> String {
+        let template = self.message.as_deref().unwrap_or("{metric} {op} {threshold} (value {value})");
+        template
+            .replace("{metric}", &self.metric)
+            .replace("{op}", self.op.symbol())
+            .replace("{threshold}", &self.threshold.to_string())
+            .replace("{value}", &value.to_string())
+    }
+
+    /// 已激活时是否回落到迟滞带之外
+    fn cleared(&self, value: f64) -> bool {
+        let h = self.hysteresis;
+        match self.op {
+            Op::Gt | Op::Ge => value < self.threshold - h || (h == 0.0 && !self.op.test(value, self.threshold)),
+            Op::Lt | Op::Le => value > self.threshold + h || (h == 0.0 && !self.op.test(value, self.threshold)),
+            _ => !self.op.test(value, self.threshold),
+        }
+    }
+
+    pub fn step(&self, st: &mut RuleState, frame: &Value, ts: i64) -> Option<Transition> {
+        let value = lookup(frame, &self.metric).and_then(|v| v.as_f64());
+        let cond_ok = self
+            .while_cond
+            .as_ref()
+            .is_none_or(|c| lookup(frame, &c.metric).is_some_and(|v| c.op.test_value(v, &c.value)));
+        if value.is_some() {
+            st.last_value = value;
+        }
+        if st.active {
+            // 数据缺失时保持激活
+            if !cond_ok || value.is_some_and(|v| self.cleared(v)) {
+                st.active = false;
+                st.pending_since = None;
+                let notified = std::mem::take(&mut st.notified);
+                return notified.then(|| Transition::Resolved { rule_id: self.id.clone(), metric: self.metric.clone(), value, ts });
+            }
+            return None;
+        }
+        let v = value?;
+        if !cond_ok || !self.op.test(v, self.threshold) {
+            st.pending_since = None;
+            return None;
+        }
+        let since = *st.pending_since.get_or_insert(ts);
+        if ts - since < self.for_ms as i64 {
+            return None;
+        }
+        st.active = true;
+        if st.last_raised.is_some_and(|t| ts - t < self.cooldown_ms as i64) {
+            return None;
+        }
+        st.last_raised = Some(ts);
+        st.notified = true;
+        Some(Transition::Raised(serde_json::json!({
+            "level": self.level,
+            "metric": self.metric,
+            "value": v,
+            "threshold": self.threshold,
+            "rule_id": self.id,
+            "message": self.message(v),
+            "ts": ts,
+        })))
+    }
+}
+
+impl Transition {
+    /// 事件/模拟结果的 JSON 形态
+    pub fn to_json(&self) -> Value {
+        match self {
+            Transition::Raised(payload) => payload.clone(),
+            Transition::Resolved { rule_id, metric, value, ts } => {
+                serde_json::json!({ "rule_id": rule_id, "metric": metric, "value": value, "ts": ts })
+            }
+        }
+    }
+}
+
+/// id 不重复且启用的规则都能编译
+pub fn validate(rules: &[AlertRule]) -> Result<(), String> {
+    let mut ids = std::collections::HashSet::new();
+    for r in rules {
+        if !ids.insert(r.id.as_str()) {
+            return Err(format!("duplicate rule id: {}", r.id));
+        }
+        if r.enabled {
+            r.compile()?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct Engine {
+    rules: Vec<AlertRule>,
+    compiled: Vec<(Compiled, RuleState)>,
+}
+
+impl Engine {
+    pub const fn new() -> Self {
+        Self { rules: Vec::new(), compiled: Vec::new() }
+    }
+
+    /// 整体替换规则；id 与定义都未变化的规则保留运行状态
+    pub fn set_rules(&mut self, rules: Vec<AlertRule>) -> Result<(), String> {
+        validate(&rules)?;
+        let mut compiled = Vec::new();
+        for c in rules.iter().filter(|r| r.enabled).filter_map(|r| r.compile().ok()) {
+            let state = self
+                .compiled
+                .iter()
+                .find(|(old, _)| *old == c)
+                .map(|(_, s)| s.clone())
+                .unwrap_or_else(|| RuleState { rule_id: c.id.clone(), ..Default::default() });
+            compiled.push((c, state));
+        }
+        self.rules = rules;
+        self.compiled = compiled;
+        Ok(())
+    }
+
+    pub fn rules(&self) -> &[AlertRule] {
+        &self.rules
+    }
+
+    pub fn states(&self) -> Vec<RuleState> {
+        self.compiled.iter().map(|(_, s)| s.clone()).collect()
+    }
+
+    pub fn evaluate(&mut self, frame: &Value) -> Vec<Transition> {
+        let ts = frame.get("ts").and_then(|t| t.as_i64()).unwrap_or_else(|| crate::now_millis() as i64);
+        self.compiled.iter_mut().filter_map(|(c, st)| c.step(st, frame, ts)).collect()
+    }
+}
+
+static ENGINE: Mutex<Engine> = Mutex::new(Engine::new());
+
+/// test_alert_rule 的缺省输入：最近评估过的实时帧（含 derived、未做单位换算）
+const RECENT_FRAMES: usize = 300;
+static RECENT: Mutex<VecDeque<Value>> = Mutex::new(VecDeque::new());
+
+pub fn recent_frames() -> Vec<Value> {
+    RECENT.lock().map(|r| r.iter().cloned().collect()).unwrap_or_default()
+}
+
+fn lock() -> Result<std::sync::MutexGuard<'static, Engine>, String> {
+    ENGINE.lock().map_err(|_| "alert engine lock poisoned".to_string())
+}
+
+/// 替换生效的规则（规则文件加载/修改后调用），返回启用的规则数
+pub fn replace_rules(rules: Vec<AlertRule>) -> Result<usize, String> {
+    let mut e = lock()?;
+    e.set_rules(rules)?;
+    Ok(e.compiled.len())
+}
+
+/// 有启用的规则时需要实时帧（见 autopause.rs）
+pub fn has_rules() -> bool {
+    lock().map(|e| !e.compiled.is_empty()).unwrap_or(false)
+}
+
+pub fn rules() -> Vec<AlertRule> {
+    lock().map(|e| e.rules().to_vec()).unwrap_or_default()
+}
+
+/// 用独立状态把规则依次作用于 frames，返回其间的触发/解除与最终状态
+pub fn simulate(rule: &AlertRule, frames: &[Value]) -> Result<Value, String> {
+    let c = rule.compile()?;
+    let mut st = RuleState { rule_id: c.id.clone(), ..Default::default() };
+    let mut transitions = Vec::new();
+    for (i, f) in frames.iter().enumerate() {
+        let ts = f.get("ts").and_then(|t| t.as_i64()).unwrap_or(i as i64);
+        if let Some(t) = c.step(&mut st, f, ts) {
+            let kind = if matches!(t, Transition::Raised(_)) { "alert" } else { "alert_resolved" };
+            transitions.push(serde_json::json!({ "event": kind, "payload": t.to_json() }));
+        }
+    }
+    Ok(serde_json::json!({ "frames": frames.len(), "transitions": transitions, "state": st }))
+}
+
+/// 由 dispatch_notification 对每帧实时 metrics 调用（回放期间不评估）
+pub fn evaluate(app: &tauri::AppHandle, frame: &Value) {
+    if let Ok(mut r) = RECENT.lock() {
+        if r.len() >= RECENT_FRAMES {
+            r.pop_front();
+        }
+        r.push_back(frame.clone());
+    }
+    let transitions = match ENGINE.lock() {
+        Ok(mut e) if !e.compiled.is_empty() => e.evaluate(frame),
+        _ => return,
+    };
+    for t in transitions {
+        match &t {
+            Transition::Raised(payload) => {
+                crate::notifications::observe("alert", payload);
+                if crate::alertjournal::on_alert(app, payload, crate::alertjournal::Origin::Bridge) {
+                    let _ = app.emit("alert", payload);
+                }
+            }
+            Transition::Resolved { rule_id, value, ts, .. } => {
+                crate::notifications::resolve_alert(rule_id);
+                crate::alertjournal::on_resolved(app, rule_id, *value, *ts as u64);
+                let _ = app.emit("alert_resolved", t.to_json());
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_alert_states() -> Result<Vec<RuleState>, String> {
+    Ok(lock()?.states())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(expr: &str) -> AlertRule {
+        serde_json::from_value(json!({ "id": "r", "expr": expr })).unwrap()
+    }
+
+    #[test]
+    fn parses_expressions() {
+        let c = rule("cpu.usage_percent > 90 for 30s").compile().unwrap();
+        assert_eq!((c.metric.as_str(), c.op, c.threshold, c.for_ms), ("cpu.usage_percent", Op::Gt, 90.0, 30_000));
+        let c = rule("power.battery.percentage < 15 while discharging").compile().unwrap();
+        assert_eq!(c.while_cond.unwrap().value, json!("discharging"));
+        let c = rule("disk.capacity_totals.free_bytes < 10 GiB").compile().unwrap();
+        assert_eq!(c.threshold, 10.0 * 1_073_741_824.0);
+        assert_eq!(rule("disk.capacity_totals.free_bytes < 10GB for 1m").compile().unwrap().for_ms, 60_000);
+        assert!(rule("cpu.usage_percent >> 90").compile().is_err());
+        assert!(rule("cpu.usage_percent > 90 during 5s").compile().is_err());
+    }
+
+    #[test]
+    fn duration_hysteresis_and_cooldown() {
+        let mut r = rule("cpu.usage_percent > 90 for 2s");
+        r.hysteresis = 5.0;
+        r.cooldown_ms = 60_000;
+        let c = r.compile().unwrap();
+        let mut st = RuleState::default();
+        let f = |v: f64| json!({ "cpu": { "usage_percent": v } });
+        assert_eq!(c.step(&mut st, &f(95.0), 0), None);
+        assert_eq!(c.step(&mut st, &f(95.0), 1_000), None);
+        let Some(Transition::Raised(p)) = c.step(&mut st, &f(96.0), 2_000) else { panic!("expected alert") };
+        assert_eq!((p["value"].clone(), p["threshold"].clone(), p["level"].clone(), p["ts"].clone()), (json!(96.0), json!(90.0), json!("warn"), json!(2_000)));
+        // 迟滞带内不解除
+        assert_eq!(c.step(&mut st, &f(87.0), 3_000), None);
+        assert!(matches!(c.step(&mut st, &f(84.0), 4_000), Some(Transition::Resolved { .. })));
+        // 冷却期内再次满足：激活但不通知，解除也不通知
+        assert_eq!(c.step(&mut st, &f(95.0), 5_000), None);
+        assert_eq!(c.step(&mut st, &f(95.0), 7_000), None);
+        assert!(st.active);
+        assert_eq!(c.step(&mut st, &f(10.0), 8_000), None);
+        assert!(!st.active);
+    }
+
+    #[test]
+    fn while_condition_gates_and_resolves() {
+        let c = rule("power.battery.percentage < 15 while discharging").compile().unwrap();
+        let mut st = RuleState::default();
+        let f = |pct: f64, state: &str| json!({ "power": { "battery": { "percentage": pct, "state": state } } });
+        assert_eq!(c.step(&mut st, &f(10.0, "charging"), 0), None);
+        assert!(matches!(c.step(&mut st, &f(10.0, "discharging"), 1), Some(Transition::Raised(_))));
+        assert!(matches!(c.step(&mut st, &f(10.0, "charging"), 2), Some(Transition::Resolved { .. })));
+
+        let mut e = Engine::new();
+        let dup = vec![rule("cpu.usage_percent > 1"), rule("cpu.usage_percent > 2")];
+        assert!(e.set_rules(dup).is_err());
+    }
+}
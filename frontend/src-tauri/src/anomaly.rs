@@ -0,0 +1,335 @@
+// 统计异常检测
+// 固定阈值发现不了“对这台机器而言不寻常”的情况（如平时 0.1 的磁盘 queue_length 持续数分钟在 4 左右）。
+// 对选定序列维护 EWMA 均值/方差作为基线，按 z = (x - 均值) / 标准差 打分：
+// - 预热期内只学习基线（前 warmup 个样本按累计平均收敛），不打分；
+// - |z| ≥ z_threshold 持续 for_ms 后发出 anomaly（state: "start"），回落后发出 state: "end"；
+// - 异常样本不更新基线；异常持续 rebaseline_ms 后视为水平变化，发出 end（reason: "rebaseline"）并从该值重新预热；
+// - 标准差下限为 max(min_std, 1% × |均值|)，避免平稳序列上微小波动得到极大的分数。
+// 不建模日周期等季节性。字段路径同 derived.rs，可引用 derived.<name>，可用 | 给出备选路径。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Both,
+    High,
+    Low,
+}
+
+fn default_alpha() -> f64 {
+    0.05
+}
+fn default_z() -> f64 {
+    4.0
+}
+fn default_warmup() -> u32 {
+    60
+}
+fn default_for_ms() -> u64 {
+    10_000
+}
+fn default_min_std() -> f64 {
+    0.01
+}
+fn default_rebaseline_ms() -> u64 {
+    600_000
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeriesConfig {
+    pub metric: String,
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+    #[serde(default = "default_z")]
+    pub z_threshold: f64,
+    #[serde(default = "default_warmup")]
+    pub warmup: u32,
+    #[serde(default = "default_for_ms")]
+    pub for_ms: u64,
+    #[serde(default = "default_min_std")]
+    pub min_std: f64,
+    /// 0 表示不重新建立基线
+    #[serde(default = "default_rebaseline_ms")]
+    pub rebaseline_ms: u64,
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+impl SeriesConfig {
+    pub fn new(metric: &str) -> Self {
+        serde_json::from_value(serde_json::json!({ "metric": metric })).expect("defaults")
+    }
+}
+
+pub fn default_series() -> Vec<SeriesConfig> {
+    vec![
+        SeriesConfig::new("cpu.usage_percent"),
+        SeriesConfig::new("derived.memory_used_percent"),
+        SeriesConfig::new("disk.totals.queue_length|disk.queue_length"),
+    ]
+}
+
+pub fn validate(series: &[SeriesConfig]) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for s in series {
+        if s.metric.trim().is_empty() || !seen.insert(s.metric.as_str()) {
+            return Err(format!("invalid or duplicate metric: {:?}", s.metric));
+        }
+        let alpha_ok = s.alpha > 0.0 && s.alpha <= 1.0;
+        if !alpha_ok {
+            return Err(format!("{}: alpha must be in (0, 1]", s.metric));
+        }
+        let z_ok = s.z_threshold > 0.0 && s.z_threshold.is_finite();
+        let min_std_ok = s.min_std >= 0.0 && s.min_std.is_finite();
+        if !z_ok || !min_std_ok {
+            return Err(format!("{}: invalid z_threshold/min_std", s.metric));
+        }
+    }
+    Ok(())
+}
+
+fn number(frame: &Value, spec: &str) -> Option<f64> {
+    spec.split('|').find_map(|p| p.trim().split('.').try_fold(frame, |v, k| v.get(k)).and_then(|x| x.as_f64()))
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SeriesState {
+    pub metric: String,
+    pub samples: u64,
+    pub mean: f64,
+    pub var: f64,
+    pub last_score: Option<f64>,
+    pub active: bool,
+    pub outlier_since: Option<i64>,
+    /// 本次异常发出 start 的时间
+    pub started_ts: Option<i64>,
+}
+
+impl SeriesState {
+    fn learn(&mut self, x: f64, alpha: f64) {
+        self.samples += 1;
+        if self.samples == 1 {
+            self.mean = x;
+            self.var = 0.0;
+            return;
+        }
+        let diff = x - self.mean;
+        let incr = alpha * diff;
+        self.mean += incr;
+        self.var = (1.0 - alpha) * (self.var + diff * incr);
+    }
+
+    pub fn std(&self) -> f64 {
+        self.var.max(0.0).sqrt()
+    }
+}
+
+impl SeriesConfig {
+    /// 处理一个样本，返回需要发出的 anomaly 负载
+    pub fn step(&self, st: &mut SeriesState, x: f64, ts: i64) -> Option<Value> {
+        if (st.samples as u32) < self.warmup {
+            let n = st.samples as f64 + 1.0;
+            st.learn(x, self.alpha.max(1.0 / n));
+            return None;
+        }
+        let (mean, std) = (st.mean, st.std());
+        let scale = std.max(self.min_std).max(mean.abs() * 0.01);
+        let z = if scale > 0.0 { (x - mean) / scale } else { 0.0 };
+        st.last_score = Some(z);
+        let outlier = match self.direction {
+            Direction::Both => z.abs() >= self.z_threshold,
+            Direction::High => z >= self.z_threshold,
+            Direction::Low => z <= -self.z_threshold,
+        };
+        let payload = |state: &str| {
+            serde_json::json!({
+                "metric": self.metric,
+                "state": state,
+                "value": x,
+                "score": z,
+                "baseline": { "mean": mean, "std": std },
+                "ts": ts,
+            })
+        };
+        let end = |st: &mut SeriesState, reason: &str| {
+            st.active = false;
+            let started = st.started_ts.take()?;
+            let mut p = payload("end");
+            p["reason"] = serde_json::json!(reason);
+            p["duration_ms"] = serde_json::json!(ts - started);
+            Some(p)
+        };
+        if !outlier {
+            st.learn(x, self.alpha);
+            st.outlier_since = None;
+            return end(st, "recovered");
+        }
+        let since = *st.outlier_since.get_or_insert(ts);
+        if self.rebaseline_ms > 0 && ts - since >= self.rebaseline_ms as i64 {
+            let ended = end(st, "rebaseline");
+            *st = SeriesState { metric: st.metric.clone(), ..Default::default() };
+            st.learn(x, 1.0);
+            return ended;
+        }
+        if st.active || ts - since < self.for_ms as i64 {
+            return None;
+        }
+        st.active = true;
+        st.started_ts = Some(since);
+        Some(payload("start"))
+    }
+}
+
+pub struct Detector {
+    enabled: bool,
+    series: Vec<SeriesConfig>,
+    states: Vec<SeriesState>,
+}
+
+impl Detector {
+    pub fn new(series: Vec<SeriesConfig>) -> Self {
+        let states = series.iter().map(|s| SeriesState { metric: s.metric.clone(), ..Default::default() }).collect();
+        Self { enabled: true, series, states }
+    }
+
+    pub fn evaluate(&mut self, frame: &Value) -> Vec<Value> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let ts = frame.get("ts").and_then(|t| t.as_i64()).unwrap_or_else(|| crate::now_millis() as i64);
+        let mut out = Vec::new();
+        for (cfg, st) in self.series.iter().zip(self.states.iter_mut()) {
+            let Some(x) = number(frame, &cfg.metric).filter(|x| x.is_finite()) else { continue };
+            out.extend(cfg.step(st, x, ts));
+        }
+        out
+    }
+}
+
+static DETECTOR: Mutex<Option<Detector>> = Mutex::new(None);
+
+fn with_detector<T>(f: impl FnOnce(&mut Detector) -> T) -> Result<T, String> {
+    let mut guard = DETECTOR.lock().map_err(|_| "anomaly detector lock poisoned".to_string())?;
+    Ok(f(guard.get_or_insert_with(|| Detector::new(default_series()))))
+}
+
+/// 启用且配置了序列时需要实时帧（见 autopause.rs）
+pub fn is_active() -> bool {
+    with_detector(|d| d.enabled && !d.series.is_empty()).unwrap_or(false)
+}
+
+/// 由 dispatch_notification 对每帧实时 metrics 调用（回放期间不评估）
+pub fn evaluate(app: &tauri::AppHandle, frame: &Value) {
+    for payload in with_detector(|d| d.evaluate(frame)).unwrap_or_default() {
+        let _ = app.emit("anomaly", payload);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnomalyConfig {
+    pub enabled: bool,
+    pub series: Vec<SeriesConfig>,
+}
+
+#[tauri::command]
+pub fn get_anomaly_config() -> Result<AnomalyConfig, String> {
+    with_detector(|d| AnomalyConfig { enabled: d.enabled, series: d.series.clone() })
+}
+
+/// series 整体替换；定义未变的序列保留已学习的基线。reset_defaults 恢复内置序列
+#[tauri::command]
+pub fn set_anomaly_config(enabled: Option<bool>, series: Option<Vec<SeriesConfig>>, reset_defaults: Option<bool>) -> Result<AnomalyConfig, String> {
+    let series = if reset_defaults.unwrap_or(false) { Some(default_series()) } else { series };
+    if let Some(s) = &series {
+        validate(s)?;
+    }
+    with_detector(|d| {
+        if let Some(s) = series {
+            let mut next = Detector::new(s);
+            next.enabled = d.enabled;
+            for (cfg, st) in next.series.iter().zip(next.states.iter_mut()) {
+                if let Some(i) = d.series.iter().position(|old| old == cfg) {
+                    *st = d.states[i].clone();
+                }
+            }
+            *d = next;
+        }
+        if let Some(v) = enabled {
+            d.enabled = v;
+        }
+        AnomalyConfig { enabled: d.enabled, series: d.series.clone() }
+    })
+}
+
+#[tauri::command]
+pub fn get_anomaly_state() -> Result<Vec<SeriesState>, String> {
+    with_detector(|d| d.states.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn frame(ts: i64, q: f64) -> Value {
+        json!({ "ts": ts, "disk": { "totals": { "queue_length": q } } })
+    }
+
+    #[test]
+    fn sustained_shift_starts_and_ends() {
+        let mut d = Detector::new(vec![SeriesConfig::new("disk.totals.queue_length|disk.queue_length")]);
+        let mut events = Vec::new();
+        // 基线约 0.1，带少量抖动
+        for i in 0..120 {
+            events.extend(d.evaluate(&frame(i * 1000, if i % 2 == 0 { 0.08 } else { 0.12 })));
+        }
+        assert!(events.is_empty());
+        for i in 120..135 {
+            events.extend(d.evaluate(&frame(i * 1000, 4.0)));
+        }
+        assert_eq!(events.len(), 1);
+        let start = &events[0];
+        assert_eq!((start["state"].clone(), start["ts"].clone()), (json!("start"), json!(130_000)));
+        assert!(start["score"].as_f64().unwrap() > 4.0);
+        assert!((start["baseline"]["mean"].as_f64().unwrap() - 0.1).abs() < 0.05);
+        let end = d.evaluate(&frame(135_000, 0.1));
+        assert_eq!((end[0]["state"].clone(), end[0]["reason"].clone()), (json!("end"), json!("recovered")));
+        assert_eq!(end[0]["duration_ms"], json!(15_000));
+    }
+
+    #[test]
+    fn warmup_and_direction() {
+        let mut cfg = SeriesConfig::new("cpu.usage_percent");
+        cfg.warmup = 10;
+        cfg.for_ms = 0;
+        cfg.direction = Direction::High;
+        let mut st = SeriesState::default();
+        for i in 0..10 {
+            // 预热期内即使剧烈变化也不打分
+            assert_eq!(cfg.step(&mut st, if i == 5 { 100.0 } else { 10.0 }, i), None);
+        }
+        assert_eq!(st.samples, 10);
+        assert_eq!(cfg.step(&mut st, -1_000.0, 10), None);
+        assert!(st.last_score.unwrap() < -4.0);
+        assert_eq!(cfg.step(&mut st, 10_000.0, 11).unwrap()["state"], json!("start"));
+        // 持续异常超过 rebaseline_ms：结束本次异常并从当前值重新预热
+        cfg.rebaseline_ms = 5;
+        let end = cfg.step(&mut st, 10_000.0, 16).unwrap();
+        assert_eq!((end["state"].clone(), end["reason"].clone(), end["duration_ms"].clone()), (json!("end"), json!("rebaseline"), json!(5)));
+        assert_eq!((st.samples, st.mean, st.active), (1, 10_000.0, false));
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        let mut bad = SeriesConfig::new("cpu.usage_percent");
+        bad.alpha = 0.0;
+        assert!(validate(&[bad]).is_err());
+        assert!(validate(&[SeriesConfig::new("a"), SeriesConfig::new("a")]).is_err());
+        assert!(validate(&default_series()).is_ok());
+    }
+}
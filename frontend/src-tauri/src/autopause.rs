@@ -0,0 +1,252 @@
+// 无可见窗口时自动暂停推送
+// 监视线程定期检查窗口可见性（窗口事件会提前唤醒它），全部窗口隐藏/最小化持续超过宽限期后暂停：
+// 事件桥只在原连接上发送 subscribe_metrics(false)，连接保持（经桥的 burst 续订与调用照常），可选同时 stop 采集；
+// 任一窗口重新可见即恢复订阅。启用的告警规则或进行中的电池会话仍需实时帧时不暂停；
+// 异常检测与本地历史库默认总在运行，只有在配置中选择保持（hold_for_*）时才阻止暂停。
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const DEFAULT_GRACE_MS: u64 = 30_000;
+const POLL_MS: u64 = 1_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoPauseConfig {
+    pub enabled: bool,
+    pub grace_ms: u64,
+    /// 暂停时是否同时 stop 采集（全局生效，会影响其他客户端）
+    pub stop_collect: bool,
+    /// 异常检测启用时不暂停
+    #[serde(default)]
+    pub hold_for_anomaly: bool,
+    /// 本地历史库打开时不暂停（隐藏期间继续落盘）
+    #[serde(default)]
+    pub hold_for_localstore: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Pause,
+    Resume,
+}
+
+pub struct AutoPause {
+    config: AutoPauseConfig,
+    hidden_since: Option<u64>,
+    paused: bool,
+}
+
+impl AutoPause {
+    pub const fn new() -> Self {
+        Self {
+            config: AutoPauseConfig {
+                enabled: true,
+                grace_ms: DEFAULT_GRACE_MS,
+                stop_collect: false,
+                hold_for_anomaly: false,
+                hold_for_localstore: false,
+            },
+            hidden_since: None,
+            paused: false,
+        }
+    }
+
+    /// held：进程内仍有消费者需要实时帧，效果同有可见窗口
+    pub fn tick(&mut self, any_visible: bool, held: bool, now: u64) -> Option<Action> {
+        if any_visible || held || !self.config.enabled {
+            self.hidden_since = None;
+            return std::mem::take(&mut self.paused).then_some(Action::Resume);
+        }
+        let since = *self.hidden_since.get_or_insert(now);
+        if !self.paused && now.saturating_sub(since) >= self.config.grace_ms {
+            self.paused = true;
+            return Some(Action::Pause);
+        }
+        None
+    }
+}
+
+static STATE: Mutex<AutoPause> = Mutex::new(AutoPause::new());
+static PAUSED: AtomicBool = AtomicBool::new(false);
+// 本次暂停是否 stop 了采集，恢复时据此重新 start
+static STOPPED: AtomicBool = AtomicBool::new(false);
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+// 状态变化时唤醒监视线程与等待恢复的事件桥
+static WAKE_LOCK: Mutex<()> = Mutex::new(());
+static WAKE: Condvar = Condvar::new();
+
+fn wait(timeout: Duration) {
+    if let Ok(guard) = WAKE_LOCK.lock() {
+        let _ = WAKE.wait_timeout(guard, timeout);
+    }
+}
+
+/// 窗口事件（焦点/尺寸/销毁等）触发立即重新检查
+pub fn nudge() {
+    WAKE.notify_all();
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+type Consumer = (&'static str, bool, fn() -> bool);
+
+/// 仍需实时帧的进程内消费者；异常检测与本地历史库仅在配置选择保持时计入
+pub fn live_consumers(config: &AutoPauseConfig) -> Vec<&'static str> {
+    let checks: [Consumer; 4] = [
+        ("alerts", true, crate::alerts::has_rules),
+        ("battery_sessions", true, crate::batterysessions::is_tracking),
+        ("anomaly", config.hold_for_anomaly, crate::anomaly::is_active),
+        ("localstore", config.hold_for_localstore, crate::localstore::is_recording),
+    ];
+    checks.iter().filter(|(_, opted, f)| *opted && f()).map(|(name, _, _)| *name).collect()
+}
+
+fn any_window_visible(app: &tauri::AppHandle) -> bool {
+    app.webview_windows()
+        .values()
+        .any(|w| w.is_visible().unwrap_or(true) && !w.is_minimized().unwrap_or(false))
+}
+
+fn apply(app: &tauri::AppHandle, action: Action, stop_collect: bool) {
+    match action {
+        Action::Pause => {
+            PAUSED.store(true, Ordering::SeqCst);
+            crate::log_line("INFO", &format!("auto pause: no visible window (stop_collect={})", stop_collect));
+            if stop_collect {
+                // 直接走短连接，不计入会话状态
+                match crate::call_over_named_pipe("stop", None) {
+                    Ok(_) => STOPPED.store(true, Ordering::SeqCst),
+                    Err(e) => crate::log_line("WARN", &format!("auto pause stop failed: {}", e)),
+                }
+            }
+            let _ = app.emit("bridge_paused", serde_json::json!({ "ts": crate::now_millis(), "stop_collect": stop_collect }));
+        }
+        Action::Resume => {
+            PAUSED.store(false, Ordering::SeqCst);
+            crate::log_line("INFO", "auto pause: resumed");
+            if STOPPED.swap(false, Ordering::SeqCst) {
+                // 连接未断开，不会触发服务端的自动 start；重新 start 后按会话状态重放（用户若已 stop 则恢复为 stop）
+                if let Err(e) = crate::call_over_named_pipe("start", None) {
+                    crate::log_line("WARN", &format!("auto pause resume start failed: {}", e));
+                }
+                crate::session::restore_after_hello(app);
+            }
+            let _ = app.emit("bridge_resumed", serde_json::json!({ "ts": crate::now_millis() }));
+        }
+    }
+    WAKE.notify_all();
+}
+
+pub fn start_watcher(app: tauri::AppHandle) {
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(move || loop {
+        let visible = any_window_visible(&app);
+        let step = STATE.lock().ok().and_then(|mut s| {
+            let held = !live_consumers(&s.config).is_empty();
+            s.tick(visible, held, crate::now_millis()).map(|a| (a, s.config.stop_collect))
+        });
+        if let Some((action, stop_collect)) = step {
+            apply(&app, action, stop_collect);
+        }
+        wait(Duration::from_millis(POLL_MS));
+    });
+}
+
+#[tauri::command]
+pub fn set_auto_pause(
+    enabled: Option<bool>,
+    grace_ms: Option<u64>,
+    stop_collect: Option<bool>,
+    hold_for_anomaly: Option<bool>,
+    hold_for_localstore: Option<bool>,
+) -> Result<AutoPauseConfig, String> {
+    let mut s = STATE.lock().map_err(|_| "auto pause lock poisoned".to_string())?;
+    if let Some(v) = enabled {
+        s.config.enabled = v;
+    }
+    if let Some(v) = grace_ms {
+        s.config.grace_ms = v;
+    }
+    if let Some(v) = stop_collect {
+        s.config.stop_collect = v;
+    }
+    if let Some(v) = hold_for_anomaly {
+        s.config.hold_for_anomaly = v;
+    }
+    if let Some(v) = hold_for_localstore {
+        s.config.hold_for_localstore = v;
+    }
+    let config = s.config.clone();
+    drop(s);
+    nudge();
+    Ok(config)
+}
+
+#[tauri::command]
+pub fn get_auto_pause() -> Result<serde_json::Value, String> {
+    let s = STATE.lock().map_err(|_| "auto pause lock poisoned".to_string())?;
+    Ok(serde_json::json!({
+        "config": s.config,
+        "paused": s.paused,
+        "hidden_since": s.hidden_since,
+        "held_by": live_consumers(&s.config),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pauses_after_grace_and_resumes_on_visible() {
+        let mut p = AutoPause::new();
+        p.config.grace_ms = 1_000;
+        assert_eq!(p.tick(false, false, 0), None);
+        assert_eq!(p.tick(false, false, 999), None);
+        assert_eq!(p.tick(false, false, 1_000), Some(Action::Pause));
+        assert_eq!(p.tick(false, false, 5_000), None);
+        assert_eq!(p.tick(true, false, 6_000), Some(Action::Resume));
+        // 重新隐藏后宽限期重新计时
+        assert_eq!(p.tick(false, false, 7_000), None);
+        assert_eq!(p.tick(true, false, 7_500), None);
+        assert_eq!(p.tick(false, false, 8_000), None);
+        assert_eq!(p.tick(false, false, 8_999), None);
+    }
+
+    #[test]
+    fn disabling_resumes() {
+        let mut p = AutoPause::new();
+        p.config.grace_ms = 0;
+        assert_eq!(p.tick(false, false, 0), Some(Action::Pause));
+        p.config.enabled = false;
+        assert_eq!(p.tick(false, false, 1), Some(Action::Resume));
+        assert_eq!(p.tick(false, false, 2), None);
+    }
+
+    #[test]
+    fn live_consumers_hold_off_pause() {
+        let mut p = AutoPause::new();
+        p.config.grace_ms = 0;
+        assert_eq!(p.tick(false, true, 0), None);
+        assert_eq!(p.tick(false, false, 1), Some(Action::Pause));
+        // 暂停中出现需要实时帧的消费者时恢复
+        assert_eq!(p.tick(false, true, 2), Some(Action::Resume));
+    }
+
+    #[test]
+    fn default_setup_has_no_live_consumers() {
+        // 默认配置下异常检测启用、本地库可能已打开，但都不应阻止暂停
+        let config = AutoPause::new().config;
+        assert!(crate::anomaly::is_active());
+        assert_eq!(live_consumers(&config), Vec::<&str>::new());
+        let opted = AutoPauseConfig { hold_for_anomaly: true, ..config };
+        assert_eq!(live_consumers(&opted), vec!["anomaly"]);
+    }
+}
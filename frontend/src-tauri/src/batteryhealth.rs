@@ -0,0 +1,260 @@
+// 电池健康长期跟踪
+// 原始行只保留数小时，整理时（见 localstore.rs 写入线程）把每个 UTC 日最后一次的满充容量、设计容量、循环次数与
+// condition 记入 battery_health_daily（每日一行，不参与过期清理）。
+// battery_health_report 据此计算损耗百分比、每 100 次循环的容量衰减，并按时间线性外推满充容量降到设计容量 80% 的日期。
+
+use crate::localstore::Store;
+use rusqlite::Connection;
+use serde::Serialize;
+
+const DAY_MS: i64 = 86_400_000;
+/// 外推的健康度阈值（满充容量占设计容量的百分比）
+const THRESHOLD_PERCENT: f64 = 80.0;
+
+pub fn ensure_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS battery_health_daily (
+            day INTEGER NOT NULL PRIMARY KEY,
+            ts INTEGER NOT NULL,
+            full_charge_capacity_mah REAL NULL,
+            design_capacity_mah REAL NULL,
+            cycle_count REAL NULL,
+            condition TEXT NULL
+        );",
+    )
+}
+
+/// 自 1970-01-01 起的天数转为 YYYY-MM-DD（公历）
+pub fn date_string(day: i64) -> String {
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyHealth {
+    pub day: i64,
+    pub date: String,
+    pub ts: i64,
+    pub full_charge_capacity_mah: Option<f64>,
+    pub design_capacity_mah: Option<f64>,
+    pub cycle_count: Option<f64>,
+    pub condition: Option<String>,
+    /// 满充容量 / 设计容量 × 100
+    pub health_percent: Option<f64>,
+    pub wear_percent: Option<f64>,
+}
+
+impl DailyHealth {
+    pub fn new(ts: i64, fcc: Option<f64>, design: Option<f64>, cycles: Option<f64>, condition: Option<String>) -> Self {
+        let day = ts.div_euclid(DAY_MS);
+        let health = match (fcc, design) {
+            (Some(f), Some(d)) if d > 0.0 => Some(f / d * 100.0),
+            _ => None,
+        };
+        Self {
+            day,
+            date: date_string(day),
+            ts,
+            full_charge_capacity_mah: fcc,
+            design_capacity_mah: design,
+            cycle_count: cycles,
+            condition,
+            health_percent: health,
+            wear_percent: health.map(|h| 100.0 - h),
+        }
+    }
+}
+
+impl Store {
+    /// 把最近一个已记录日（含）以来每个 UTC 日最后一次的电池容量记入日志；返回写入的天数
+    pub fn log_battery_health(&mut self) -> rusqlite::Result<usize> {
+        let last_day: Option<i64> = self.conn.query_row("SELECT MAX(day) FROM battery_health_daily", [], |r| r.get(0))?;
+        let from = last_day.map(|d| d * DAY_MS).unwrap_or(i64::MIN);
+        let rows = {
+            let mut stmt = self.conn.prepare(
+                "SELECT ts, battery_full_charge_capacity_mah, battery_design_capacity_mah, battery_cycle_count, battery_condition
+                 FROM metrics WHERE ts IN (
+                     SELECT MAX(ts) FROM metrics
+                     WHERE ts >= ?1 AND battery_full_charge_capacity_mah IS NOT NULL
+                     GROUP BY ts / 86400000)
+                 ORDER BY ts ASC",
+            )?;
+            let rows = stmt.query_map([from], |r| Ok((r.get::<_, i64>(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)))?;
+            rows.collect::<rusqlite::Result<Vec<(i64, Option<f64>, Option<f64>, Option<f64>, Option<String>)>>>()?
+        };
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO battery_health_daily(day, ts, full_charge_capacity_mah, design_capacity_mah, cycle_count, condition)
+                 VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for (ts, fcc, design, cycles, condition) in &rows {
+                stmt.execute(rusqlite::params![ts.div_euclid(DAY_MS), ts, fcc, design, cycles, condition])?;
+            }
+        }
+        tx.commit()?;
+        Ok(rows.len())
+    }
+
+    /// 最近 days 天（缺省全部）的日志，按日期升序
+    pub fn battery_health_days(&self, days: Option<u32>) -> rusqlite::Result<Vec<DailyHealth>> {
+        let last_day: Option<i64> = self.conn.query_row("SELECT MAX(day) FROM battery_health_daily", [], |r| r.get(0))?;
+        let from_day = match (last_day, days) {
+            (Some(last), Some(n)) => last - i64::from(n) + 1,
+            _ => i64::MIN,
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, full_charge_capacity_mah, design_capacity_mah, cycle_count, condition
+             FROM battery_health_daily WHERE day >= ?1 ORDER BY day ASC",
+        )?;
+        let rows = stmt.query_map([from_day], |r| Ok(DailyHealth::new(r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)))?;
+        rows.collect()
+    }
+}
+
+/// 最小二乘直线 y = a·x + b；x 无变化时为 None
+fn fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    if points.len() < 2 {
+        return None;
+    }
+    let (sx, sy) = points.iter().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    let (mx, my) = (sx / n, sy / n);
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mx).powi(2)).sum();
+    if sxx <= f64::EPSILON {
+        return None;
+    }
+    let sxy: f64 = points.iter().map(|(x, y)| (x - mx) * (y - my)).sum();
+    let a = sxy / sxx;
+    Some((a, my - a * mx))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Projection {
+    pub threshold_percent: f64,
+    /// 最新健康度已不高于阈值
+    pub reached: bool,
+    pub days_remaining: Option<f64>,
+    pub date: Option<String>,
+    /// 按循环次数外推，达到阈值时的循环次数
+    pub cycle_count: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    pub days: Vec<DailyHealth>,
+    pub latest: Option<DailyHealth>,
+    pub wear_percent: Option<f64>,
+    /// 每 100 次循环损失的健康度百分点（按循环次数线性拟合）
+    pub fade_per_100_cycles: Option<f64>,
+    /// 每 30 天损失的健康度百分点（按日期线性拟合）
+    pub fade_per_30_days: Option<f64>,
+    pub projection: Option<Projection>,
+}
+
+pub fn report(days: Vec<DailyHealth>) -> HealthReport {
+    let latest = days.iter().rev().find(|d| d.health_percent.is_some()).cloned();
+    let by_day: Vec<(f64, f64)> = days.iter().filter_map(|d| Some((d.day as f64, d.health_percent?))).collect();
+    let by_cycle: Vec<(f64, f64)> = days.iter().filter_map(|d| Some((d.cycle_count?, d.health_percent?))).collect();
+    let day_fit = fit(&by_day);
+    let cycle_fit = fit(&by_cycle);
+    let projection = latest.as_ref().and_then(|l| {
+        let health = l.health_percent?;
+        if health <= THRESHOLD_PERCENT {
+            return Some(Projection { threshold_percent: THRESHOLD_PERCENT, reached: true, days_remaining: Some(0.0), date: Some(l.date.clone()), cycle_count: l.cycle_count });
+        }
+        // 只在拟合结果为衰减时外推
+        let at = |(a, b): (f64, f64)| (a < 0.0).then(|| (THRESHOLD_PERCENT - b) / a);
+        let day = day_fit.and_then(at).map(|d| d.max(l.day as f64));
+        Some(Projection {
+            threshold_percent: THRESHOLD_PERCENT,
+            reached: false,
+            days_remaining: day.map(|d| d - l.day as f64),
+            date: day.map(|d| date_string(d.round() as i64)),
+            cycle_count: cycle_fit.and_then(at),
+        })
+    });
+    HealthReport {
+        wear_percent: latest.as_ref().and_then(|l| l.wear_percent),
+        fade_per_100_cycles: cycle_fit.map(|(a, _)| -a * 100.0),
+        fade_per_30_days: day_fit.map(|(a, _)| -a * 30.0),
+        projection,
+        latest,
+        days,
+    }
+}
+
+#[tauri::command]
+pub async fn battery_health_report(days: Option<u32>) -> Result<HealthReport, String> {
+    let path = crate::localstore::db_path()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = Store::open_readonly(&path).map_err(|e| e.to_string())?;
+        Ok(report(store.battery_health_days(days).map_err(|e| e.to_string())?))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(19_782), "2024-02-29");
+        assert_eq!(date_string(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn logs_last_reading_per_day() {
+        let mut store = Store::open_in_memory().unwrap();
+        let frame = |ts: i64, fcc: f64, cycles: f64| {
+            json!({ "ts": ts, "power": { "battery": { "full_charge_capacity_mah": fcc, "design_capacity_mah": 5000.0,
+                                                      "cycle_count": cycles, "condition": "normal" } } })
+        };
+        let rows: Vec<_> = [frame(1_000, 4900.0, 10.0), frame(2_000, 4890.0, 11.0), frame(DAY_MS + 5, 4880.0, 12.0)]
+            .iter()
+            .filter_map(crate::localstore::extract)
+            .collect();
+        store.insert_batch(&rows).unwrap();
+        assert_eq!(store.log_battery_health().unwrap(), 2);
+        // 再次记录只重算最近一天
+        assert_eq!(store.log_battery_health().unwrap(), 1);
+        let days = store.battery_health_days(None).unwrap();
+        assert_eq!(days.iter().map(|d| (d.day, d.full_charge_capacity_mah)).collect::<Vec<_>>(), vec![(0, Some(4890.0)), (1, Some(4880.0))]);
+        assert_eq!(days[0].condition.as_deref(), Some("normal"));
+        assert_eq!(store.battery_health_days(Some(1)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn wear_fade_and_projection() {
+        // 每天 +1 次循环、-0.1 个百分点
+        let days: Vec<DailyHealth> = (0..10)
+            .map(|i| DailyHealth::new(i * DAY_MS, Some(4500.0 - 5.0 * i as f64), Some(5000.0), Some(100.0 + i as f64), None))
+            .collect();
+        let r = report(days);
+        assert!((r.wear_percent.unwrap() - 10.9).abs() < 1e-9);
+        assert!((r.fade_per_100_cycles.unwrap() - 10.0).abs() < 1e-9);
+        assert!((r.fade_per_30_days.unwrap() - 3.0).abs() < 1e-9);
+        let p = r.projection.unwrap();
+        // 89.1% 降到 80% 还需 91 天、91 次循环
+        assert!(!p.reached);
+        assert!((p.days_remaining.unwrap() - 91.0).abs() < 1e-6);
+        assert!((p.cycle_count.unwrap() - 200.0).abs() < 1e-6);
+        assert_eq!(p.date.as_deref(), Some(date_string(100).as_str()));
+
+        let flat = report(vec![DailyHealth::new(0, Some(3900.0), Some(5000.0), None, None)]);
+        assert!(flat.projection.unwrap().reached);
+        assert_eq!(report(Vec::new()).projection, None);
+    }
+}
@@ -0,0 +1,477 @@
+// 电池充放电会话
+// 按实时帧的 power.battery.state / ac_line_online 判定当前处于放电或充电：
+// state 为 discharging（或未在充电且 ac_line_online 为 false）视为放电，charging 视为充电，其余（full/ac/unknown）不计会话。
+// 会话期间按梯形积分累计能量（power_w，缺失时用 voltage_mv × current_ma），状态变化或帧间隔超过 MAX_GAP_MS（如睡眠）时结束；
+// 短于 MIN_SESSION_MS 的会话丢弃。结束的会话写入本地历史库 battery_sessions 表，经 battery_sessions 查询。
+// 进行中的会话每 CHECKPOINT_MS 以 end_reason = "open" 的行保存一次（至多一行），重启后距上次保存不超过 MAX_GAP_MS 时接续，
+// 否则按 "gap" 结束。所有写入经本地库的写入线程执行。
+
+use crate::localstore::Store;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Mutex;
+
+const MIN_SESSION_MS: i64 = 60_000;
+const MAX_GAP_MS: i64 = 300_000;
+const CHECKPOINT_MS: i64 = 60_000;
+const OPEN: &str = "open";
+const MS_PER_HOUR: f64 = 3_600_000.0;
+
+pub fn ensure_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS battery_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            start_ts INTEGER NOT NULL,
+            end_ts INTEGER NOT NULL,
+            start_percent REAL NULL,
+            end_percent REAL NULL,
+            energy_wh REAL NOT NULL,
+            samples INTEGER NOT NULL,
+            end_reason TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_battery_sessions_start ON battery_sessions(start_ts);",
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Charge,
+    Discharge,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Charge => "charge",
+            Kind::Discharge => "discharge",
+        }
+    }
+}
+
+/// 当前帧的会话类型与功率（瓦，取绝对值）；帧中没有电池数据时为 None
+fn classify(frame: &Value) -> Option<(Option<Kind>, Option<f64>, Option<f64>)> {
+    let b = frame.get("power")?.get("battery")?;
+    let state = b.get("state").and_then(|s| s.as_str()).unwrap_or("unknown");
+    let on_ac = b.get("ac_line_online").and_then(|v| v.as_bool());
+    let kind = match state {
+        "charging" => Some(Kind::Charge),
+        "discharging" => Some(Kind::Discharge),
+        "full" | "ac" => None,
+        _ => (on_ac == Some(false)).then_some(Kind::Discharge),
+    };
+    let num = |k: &str| b.get(k).and_then(|v| v.as_f64());
+    let power = num("power_w").or_else(|| Some(num("voltage_mv")? * num("current_ma")? / 1e6)).map(f64::abs);
+    Some((kind, power, num("percentage")))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Session {
+    pub id: Option<i64>,
+    pub kind: Kind,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub duration_ms: i64,
+    pub start_percent: Option<f64>,
+    pub end_percent: Option<f64>,
+    pub percent_delta: Option<f64>,
+    /// 放电为放出、充电为充入的能量
+    pub energy_wh: f64,
+    pub avg_power_w: Option<f64>,
+    /// 按本次的能量与电量变化推算的满电能量
+    pub estimated_full_wh: Option<f64>,
+    /// 放电会话：以平均功耗从 100% 用到 0% 的小时数
+    pub projected_full_runtime_h: Option<f64>,
+    pub samples: i64,
+    /// "state_change" | "gap"；进行中的会话为 "open"
+    pub end_reason: String,
+}
+
+impl Session {
+    /// 由原始字段计算派生字段；id / end_reason 由调用方补充
+    pub fn new(kind: Kind, start_ts: i64, end_ts: i64, start_percent: Option<f64>, end_percent: Option<f64>, energy_wh: f64, samples: i64) -> Self {
+        let duration_ms = end_ts - start_ts;
+        let percent_delta = end_percent.zip(start_percent).map(|(e, s)| e - s);
+        let hours = duration_ms as f64 / MS_PER_HOUR;
+        let avg_power_w = (hours > 0.0 && samples > 1).then(|| energy_wh / hours);
+        // 电量变化不足 1 个百分点时推算误差过大
+        let fraction = percent_delta.map(|d| d.abs() / 100.0).filter(|f| *f >= 0.01);
+        let estimated_full_wh = fraction.filter(|_| energy_wh > 0.0).map(|f| energy_wh / f);
+        let projected_full_runtime_h = match kind {
+            Kind::Discharge => estimated_full_wh.zip(avg_power_w.filter(|p| *p > 0.0)).map(|(wh, w)| wh / w),
+            Kind::Charge => None,
+        };
+        Self {
+            id: None,
+            kind,
+            start_ts,
+            end_ts,
+            duration_ms,
+            start_percent,
+            end_percent,
+            percent_delta,
+            energy_wh,
+            avg_power_w,
+            estimated_full_wh,
+            projected_full_runtime_h,
+            samples,
+            end_reason: String::new(),
+        }
+    }
+}
+
+struct Open {
+    kind: Kind,
+    start_ts: i64,
+    last_ts: i64,
+    start_percent: Option<f64>,
+    last_percent: Option<f64>,
+    last_power: Option<f64>,
+    energy_wh: f64,
+    samples: i64,
+    last_checkpoint: i64,
+    /// 已保存过 "open" 行
+    checkpointed: bool,
+}
+
+impl Open {
+    fn finish(self, reason: &str) -> Option<Session> {
+        (self.last_ts - self.start_ts >= MIN_SESSION_MS).then(|| {
+            let s = Session::new(self.kind, self.start_ts, self.last_ts, self.start_percent, self.last_percent, self.energy_wh, self.samples);
+            Session { end_reason: reason.into(), ..s }
+        })
+    }
+}
+
+/// 进行中会话的持久化操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum Checkpoint {
+    Save(Session),
+    /// 已保存过的会话结束了，删除 "open" 行
+    Clear,
+}
+
+#[derive(Default)]
+pub struct Tracker {
+    open: Option<Open>,
+    stale: bool,
+}
+
+impl Tracker {
+    pub const fn new() -> Self {
+        Self { open: None, stale: false }
+    }
+
+    /// 接续重启前保存的会话；功率未保存，下一帧按单点功率积分
+    pub fn resume(&mut self, s: &Session) {
+        self.open = Some(Open {
+            kind: s.kind,
+            start_ts: s.start_ts,
+            last_ts: s.end_ts,
+            start_percent: s.start_percent,
+            last_percent: s.end_percent,
+            last_power: None,
+            energy_wh: s.energy_wh,
+            samples: s.samples,
+            last_checkpoint: s.end_ts,
+            checkpointed: true,
+        });
+    }
+
+    /// observe 之后调用：到期时保存进行中的会话，已保存的会话结束时清除
+    pub fn checkpoint(&mut self, ts: i64) -> Option<Checkpoint> {
+        if let Some(o) = self.open.as_mut() {
+            if ts - o.last_checkpoint >= CHECKPOINT_MS {
+                o.last_checkpoint = ts;
+                o.checkpointed = true;
+                self.stale = false;
+                return self.current().map(Checkpoint::Save);
+            }
+        }
+        std::mem::take(&mut self.stale).then_some(Checkpoint::Clear)
+    }
+
+    /// 处理一帧；有会话结束（且足够长）时返回它
+    pub fn observe(&mut self, frame: &Value, ts: i64) -> Option<Session> {
+        let (kind, power, percent) = classify(frame)?;
+        let mut finished = None;
+        if let Some(o) = self.open.take() {
+            if ts - o.last_ts > MAX_GAP_MS {
+                self.stale |= o.checkpointed;
+                finished = o.finish("gap");
+            } else if Some(o.kind) != kind {
+                self.stale |= o.checkpointed;
+                finished = o.finish("state_change");
+            } else {
+                self.open = Some(o);
+            }
+        }
+        let Some(kind) = kind else { return finished };
+        match self.open.as_mut() {
+            Some(o) => {
+                let avg = match (o.last_power, power) {
+                    (Some(a), Some(b)) => Some((a + b) / 2.0),
+                    (a, b) => a.or(b),
+                };
+                o.energy_wh += avg.unwrap_or(0.0) * (ts - o.last_ts) as f64 / MS_PER_HOUR;
+                o.last_ts = ts;
+                o.last_power = power;
+                o.last_percent = percent.or(o.last_percent);
+                o.samples += 1;
+            }
+            None => {
+                self.open = Some(Open {
+                    kind,
+                    start_ts: ts,
+                    last_ts: ts,
+                    start_percent: percent,
+                    last_percent: percent,
+                    last_power: power,
+                    energy_wh: 0.0,
+                    samples: 1,
+                    last_checkpoint: ts,
+                    checkpointed: false,
+                });
+            }
+        }
+        finished
+    }
+
+    /// 进行中的会话（不论时长）
+    pub fn current(&self) -> Option<Session> {
+        let o = self.open.as_ref()?;
+        let s = Session::new(o.kind, o.start_ts, o.last_ts, o.start_percent, o.last_percent, o.energy_wh, o.samples);
+        Some(Session { end_reason: OPEN.into(), ..s })
+    }
+}
+
+fn kind_of(s: &str) -> Kind {
+    if s == "charge" {
+        Kind::Charge
+    } else {
+        Kind::Discharge
+    }
+}
+
+impl Store {
+    pub fn insert_battery_session(&self, s: &Session) -> rusqlite::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO battery_sessions(kind, start_ts, end_ts, start_percent, end_percent, energy_wh, samples, end_reason)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![s.kind.as_str(), s.start_ts, s.end_ts, s.start_percent, s.end_percent, s.energy_wh, s.samples, s.end_reason],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// 替换或删除（None）保存的进行中会话
+    pub fn save_open_battery_session(&self, s: Option<&Session>) -> rusqlite::Result<()> {
+        self.conn.execute("DELETE FROM battery_sessions WHERE end_reason = ?1", [OPEN])?;
+        if let Some(s) = s {
+            self.insert_battery_session(&Session { end_reason: OPEN.into(), ..s.clone() })?;
+        }
+        Ok(())
+    }
+
+    pub fn open_battery_session(&self) -> rusqlite::Result<Option<Session>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, start_ts, end_ts, start_percent, end_percent, energy_wh, samples FROM battery_sessions
+             WHERE end_reason = ?1 ORDER BY id DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query_map([OPEN], |r| {
+            let s = Session::new(kind_of(&r.get::<_, String>(0)?), r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?);
+            Ok(Session { end_reason: OPEN.into(), ..s })
+        })?;
+        rows.next().transpose()
+    }
+
+    /// 与 [from, to] 有交集的会话，按开始时间倒序
+    pub fn battery_sessions(&self, p: &SessionParams, now: i64) -> rusqlite::Result<Vec<Session>> {
+        let to = if p.to_ts <= 0 { now } else { p.to_ts };
+        let mut stmt = self.conn.prepare(
+            "SELECT id, kind, start_ts, end_ts, start_percent, end_percent, energy_wh, samples, end_reason FROM battery_sessions
+             WHERE end_ts >= ?1 AND start_ts <= ?2 AND (?3 IS NULL OR kind = ?3) AND end_reason <> ?5
+             ORDER BY start_ts DESC LIMIT ?4",
+        )?;
+        let kind = p.kind.map(Kind::as_str);
+        let limit = p.limit.map(|l| l as i64).unwrap_or(-1);
+        let rows = stmt.query_map(rusqlite::params![p.from_ts, to, kind, limit, OPEN], |r| {
+            let s = Session::new(kind_of(&r.get::<_, String>(1)?), r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?, r.get(7)?);
+            Ok(Session { id: r.get(0)?, end_reason: r.get(8)?, ..s })
+        })?;
+        rows.collect()
+    }
+}
+
+static TRACKER: Mutex<Tracker> = Mutex::new(Tracker::new());
+
+/// 有进行中的会话时需要实时帧，否则能量积分会因断档被截断
+pub fn is_tracking() -> bool {
+    TRACKER.lock().map(|t| t.open.is_some()).unwrap_or(false)
+}
+
+/// 本地库打开时调用（写入线程启动前）：接续或结束上次保存的进行中会话
+pub fn restore(store: &Store, now: i64) {
+    let result = store.open_battery_session().and_then(|open| match open {
+        Some(s) if now - s.end_ts <= MAX_GAP_MS => {
+            if let Ok(mut t) = TRACKER.lock() {
+                t.resume(&s);
+            }
+            Ok(())
+        }
+        // 断档过长：保存时的状态即为会话终点
+        Some(_) => store.conn.execute("UPDATE battery_sessions SET end_reason = 'gap' WHERE end_reason = ?1", [OPEN]).map(|_| ()),
+        None => Ok(()),
+    });
+    if let Err(e) = result {
+        crate::log_line("ERROR", &format!("battery session restore failed: {}", e));
+    }
+}
+
+/// 由 dispatch_notification 对每帧实时 metrics 调用（回放期间不记录）
+pub fn observe(frame: &Value) {
+    let ts = frame.get("ts").and_then(|t| t.as_i64()).unwrap_or_else(|| crate::now_millis() as i64);
+    let Ok(mut t) = TRACKER.lock() else { return };
+    let finished = t.observe(frame, ts);
+    let checkpoint = t.checkpoint(ts);
+    drop(t);
+    if finished.is_none() && checkpoint.is_none() {
+        return;
+    }
+    let submitted = crate::localstore::submit(move |store| {
+        let result = finished.as_ref().map_or(Ok(0), |s| store.insert_battery_session(s)).and_then(|_| match &checkpoint {
+            Some(Checkpoint::Save(s)) => store.save_open_battery_session(Some(s)),
+            Some(Checkpoint::Clear) => store.save_open_battery_session(None),
+            None => Ok(()),
+        });
+        if let Err(e) = result {
+            crate::log_line("ERROR", &format!("battery session write failed: {}", e));
+        }
+    });
+    if !submitted {
+        crate::log_line("WARN", "battery session write dropped: local store unavailable");
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct SessionParams {
+    #[serde(default)]
+    pub from_ts: i64,
+    /// 0 表示当前时间
+    #[serde(default)]
+    pub to_ts: i64,
+    pub kind: Option<Kind>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsResult {
+    pub sessions: Vec<Session>,
+    pub current: Option<Session>,
+}
+
+#[tauri::command]
+pub async fn battery_sessions(params: Option<SessionParams>) -> Result<SessionsResult, String> {
+    let p = params.unwrap_or_default();
+    let current = TRACKER.lock().map_err(|_| "battery session lock poisoned".to_string())?.current();
+    let path = crate::localstore::db_path()?;
+    let sessions = tauri::async_runtime::spawn_blocking(move || {
+        let store = Store::open_readonly(&path).map_err(|e| e.to_string())?;
+        store.battery_sessions(&p, crate::now_millis() as i64).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    Ok(SessionsResult { sessions, current })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn frame(state: &str, pct: f64, power: Option<f64>) -> Value {
+        json!({ "power": { "battery": { "state": state, "percentage": pct, "power_w": power, "voltage_mv": 12_000, "current_ma": -1_000 } } })
+    }
+
+    #[test]
+    fn integrates_discharge_session() {
+        let mut t = Tracker::new();
+        // 10 W 放电一小时（每分钟一帧），电量 80% -> 70%
+        for i in 0..=60 {
+            assert_eq!(t.observe(&frame("discharging", 80.0 - i as f64 / 6.0, Some(10.0)), i * 60_000), None);
+        }
+        let s = t.observe(&frame("charging", 70.0, Some(20.0)), 3_660_000).unwrap();
+        assert_eq!((s.kind, s.duration_ms, s.samples, s.end_reason.as_str()), (Kind::Discharge, 3_600_000, 61, "state_change"));
+        assert!((s.energy_wh - 10.0).abs() < 1e-9);
+        assert!((s.avg_power_w.unwrap() - 10.0).abs() < 1e-9);
+        assert!((s.percent_delta.unwrap() + 10.0).abs() < 1e-9);
+        assert!((s.estimated_full_wh.unwrap() - 100.0).abs() < 1e-6);
+        assert!((s.projected_full_runtime_h.unwrap() - 10.0).abs() < 1e-6);
+        assert_eq!(t.current().unwrap().kind, Kind::Charge);
+    }
+
+    #[test]
+    fn falls_back_to_voltage_current_and_splits_on_gaps() {
+        let mut t = Tracker::new();
+        t.observe(&frame("unknown", 50.0, None), 0);
+        // unknown 且未标明断开交流电时不计会话
+        assert!(t.current().is_none());
+        t.observe(&frame("discharging", 50.0, None), 0);
+        t.observe(&frame("discharging", 49.0, None), 120_000);
+        // 睡眠导致的断档：结束会话，不跨断档积分
+        let s = t.observe(&frame("discharging", 40.0, None), 120_000 + MAX_GAP_MS + 1).unwrap();
+        assert_eq!(s.end_reason, "gap");
+        assert!((s.energy_wh - 12.0 * 120_000.0 / MS_PER_HOUR).abs() < 1e-9);
+        // 短会话丢弃
+        assert_eq!(t.observe(&frame("full", 40.0, None), 120_000 + MAX_GAP_MS + 30_000), None);
+        assert!(t.current().is_none());
+        // 没有电池数据的帧不影响会话
+        assert_eq!(t.observe(&json!({ "cpu": { "usage_percent": 1 } }), 0), None);
+    }
+
+    #[test]
+    fn checkpoints_and_resumes_open_session() {
+        let store = Store::open_in_memory().unwrap();
+        let mut t = Tracker::new();
+        t.observe(&frame("discharging", 80.0, Some(10.0)), 0);
+        assert_eq!(t.checkpoint(0), None);
+        t.observe(&frame("discharging", 79.0, Some(10.0)), CHECKPOINT_MS);
+        let Some(Checkpoint::Save(s)) = t.checkpoint(CHECKPOINT_MS) else { panic!("expected checkpoint") };
+        store.save_open_battery_session(Some(&s)).unwrap();
+        // 进行中的会话不出现在已结束会话的查询中
+        assert!(store.battery_sessions(&SessionParams::default(), 0).unwrap().is_empty());
+
+        // 重启后接续：能量继续累计，开始时间不变
+        let saved = store.open_battery_session().unwrap().unwrap();
+        let mut resumed = Tracker::new();
+        resumed.resume(&saved);
+        resumed.observe(&frame("discharging", 78.0, Some(10.0)), 2 * CHECKPOINT_MS);
+        let cur = resumed.current().unwrap();
+        assert_eq!((cur.start_ts, cur.samples), (0, 3));
+        assert!((cur.energy_wh - 10.0 * 2.0 * CHECKPOINT_MS as f64 / MS_PER_HOUR).abs() < 1e-9);
+
+        // 已保存的会话结束时清除 "open" 行
+        let s = resumed.observe(&frame("charging", 78.0, Some(20.0)), 2 * CHECKPOINT_MS + 1_000).unwrap();
+        assert_eq!(s.end_reason, "state_change");
+        assert_eq!(resumed.checkpoint(2 * CHECKPOINT_MS + 1_000), Some(Checkpoint::Clear));
+        store.save_open_battery_session(None).unwrap();
+        assert_eq!(store.open_battery_session().unwrap(), None);
+    }
+
+    #[test]
+    fn persists_and_filters_sessions() {
+        let store = Store::open_in_memory().unwrap();
+        let s = |kind, start: i64| Session { end_reason: "state_change".into(), ..Session::new(kind, start, start + 100, Some(50.0), Some(40.0), 1.0, 2) };
+        store.insert_battery_session(&s(Kind::Discharge, 0)).unwrap();
+        store.insert_battery_session(&s(Kind::Charge, 1_000)).unwrap();
+        store.insert_battery_session(&s(Kind::Discharge, 2_000)).unwrap();
+        let all = store.battery_sessions(&SessionParams::default(), 10_000).unwrap();
+        assert_eq!(all.iter().map(|s| s.start_ts).collect::<Vec<_>>(), vec![2_000, 1_000, 0]);
+        assert_eq!(all[0].id, Some(3));
+        let p = SessionParams { from_ts: 50, to_ts: 1_500, kind: Some(Kind::Discharge), limit: None };
+        assert_eq!(store.battery_sessions(&p, 10_000).unwrap().len(), 1);
+        let p = SessionParams { limit: Some(1), ..Default::default() };
+        assert_eq!(store.battery_sessions(&p, 10_000).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,290 @@
+// burst 订阅租约管理
+// 各窗口按期望的采样间隔申请租约；只要仍有租约存活，后台线程就在服务端到期前续订 burst_subscribe。
+// 全部租约释放（或窗口关闭、租约自身时限到达）后不再续订，由服务端按 ttl 自然回落到常规间隔。
+// 注意：服务端的 burst 状态按连接维护，续订必须走事件桥连接（call_over_bridge）。
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 每次续订向服务端申请的 ttl
+const BURST_TTL_MS: u64 = 10_000;
+/// 距服务端到期不足该值时续订
+const RENEW_MARGIN_MS: u64 = 3_000;
+/// 续订失败后的重试间隔
+const RETRY_MS: u64 = 1_000;
+const TICK_MS: u64 = 250;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Lease {
+    pub id: u64,
+    pub window: String,
+    pub interval_ms: u64,
+    /// None 表示不限模块
+    pub modules: Option<Vec<String>>,
+    pub acquired_at: u64,
+    /// 租约自身时限；None 表示直到显式释放
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BurstRequest {
+    pub interval_ms: u64,
+    pub modules: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ActiveBurst {
+    interval_ms: u64,
+    modules: Option<Vec<String>>,
+    expires_at: u64,
+}
+
+pub struct LeaseManager {
+    next_id: u64,
+    leases: Vec<Lease>,
+    active: Option<ActiveBurst>,
+    retry_after: u64,
+}
+
+impl LeaseManager {
+    pub const fn new() -> Self {
+        Self { next_id: 1, leases: Vec::new(), active: None, retry_after: 0 }
+    }
+
+    pub fn acquire(&mut self, window: &str, interval_ms: u64, modules: Option<Vec<String>>, duration_ms: Option<u64>, now: u64) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.leases.push(Lease {
+            id,
+            window: window.to_string(),
+            interval_ms,
+            modules,
+            acquired_at: now,
+            expires_at: duration_ms.map(|d| now + d),
+        });
+        id
+    }
+
+    pub fn release(&mut self, id: u64) -> bool {
+        let before = self.leases.len();
+        self.leases.retain(|l| l.id != id);
+        self.leases.len() != before
+    }
+
+    pub fn release_window(&mut self, window: &str) -> usize {
+        let before = self.leases.len();
+        self.leases.retain(|l| l.window != window);
+        before - self.leases.len()
+    }
+
+    /// 重连后服务端的 burst 状态已丢失，下一次 tick 立即重新订阅
+    pub fn force_renew(&mut self) {
+        self.active = None;
+        self.retry_after = 0;
+    }
+
+    /// 所有存活租约合并后的期望：取最小间隔，模块取并集（任一租约不限模块则整体不限）
+    fn desired(&self) -> Option<BurstRequest> {
+        let interval_ms = self.leases.iter().map(|l| l.interval_ms).min()?;
+        let mut modules: Option<Vec<String>> = Some(Vec::new());
+        for l in &self.leases {
+            match (&mut modules, &l.modules) {
+                (Some(acc), Some(m)) => {
+                    for name in m {
+                        if !acc.contains(name) {
+                            acc.push(name.clone());
+                        }
+                    }
+                }
+                _ => modules = None,
+            }
+        }
+        if let Some(m) = modules.as_mut() {
+            m.sort();
+        }
+        Some(BurstRequest { interval_ms, modules })
+    }
+
+    /// 清理过期租约，并判断此刻是否需要（续）订 burst
+    pub fn plan(&mut self, now: u64) -> Option<BurstRequest> {
+        self.leases.retain(|l| l.expires_at.is_none_or(|t| t > now));
+        if self.active.as_ref().is_some_and(|a| a.expires_at <= now) {
+            self.active = None;
+        }
+        let want = self.desired()?;
+        if now < self.retry_after {
+            return None;
+        }
+        let due = match &self.active {
+            None => true,
+            Some(a) => a.interval_ms != want.interval_ms || a.modules != want.modules || now + RENEW_MARGIN_MS >= a.expires_at,
+        };
+        due.then_some(want)
+    }
+
+    pub fn on_subscribed(&mut self, req: BurstRequest, expires_at: u64) {
+        self.active = Some(ActiveBurst { interval_ms: req.interval_ms, modules: req.modules, expires_at });
+    }
+
+    pub fn on_failed(&mut self, now: u64) {
+        self.retry_after = now + RETRY_MS;
+    }
+}
+
+static MANAGER: Mutex<LeaseManager> = Mutex::new(LeaseManager::new());
+static WORKER_STARTED: AtomicBool = AtomicBool::new(false);
+
+fn ensure_worker() {
+    if WORKER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(|| loop {
+        let now = crate::now_millis();
+        let req = MANAGER.lock().ok().and_then(|mut m| m.plan(now));
+        if let Some(req) = req {
+            let mut params = serde_json::json!({
+                "interval_ms": req.interval_ms,
+                "ttl_ms": BURST_TTL_MS,
+            });
+            if let Some(modules) = &req.modules {
+                params["modules"] = serde_json::json!(modules);
+            }
+            match crate::call_over_bridge("burst_subscribe", Some(params), Duration::from_secs(5)) {
+                Ok(res) => {
+                    let expires_at = res
+                        .get("expires_at")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(now + BURST_TTL_MS);
+                    if let Ok(mut m) = MANAGER.lock() {
+                        m.on_subscribed(req, expires_at);
+                    }
+                }
+                Err(e) => {
+                    crate::log_line("WARN", &format!("burst renew failed: {}", e));
+                    if let Ok(mut m) = MANAGER.lock() {
+                        m.on_failed(crate::now_millis());
+                    }
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(TICK_MS));
+    });
+}
+
+pub fn release_window(label: &str) {
+    if let Ok(mut m) = MANAGER.lock() {
+        m.release_window(label);
+    }
+}
+
+pub fn has_leases() -> bool {
+    MANAGER.lock().map(|m| !m.leases.is_empty()).unwrap_or(false)
+}
+
+pub fn force_renew() {
+    if let Ok(mut m) = MANAGER.lock() {
+        m.force_renew();
+    }
+}
+
+#[tauri::command]
+pub fn burst_acquire(window: tauri::Window, interval_ms: u64, modules: Option<Vec<String>>, duration_ms: Option<u64>) -> Result<u64, String> {
+    if interval_ms == 0 {
+        return Err("invalid_params: interval_ms>0 required".to_string());
+    }
+    let id = MANAGER
+        .lock()
+        .map_err(|_| "burst manager lock poisoned".to_string())?
+        .acquire(window.label(), interval_ms, modules, duration_ms, crate::now_millis());
+    ensure_worker();
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn burst_release(lease_id: u64) -> Result<bool, String> {
+    Ok(MANAGER
+        .lock()
+        .map_err(|_| "burst manager lock poisoned".to_string())?
+        .release(lease_id))
+}
+
+/// 返回本地租约与续订状态，并附带服务端 get_config 中的 burst_expires_at（0 表示无突发）
+#[tauri::command]
+pub async fn burst_status() -> Result<Value, String> {
+    let (leases, active) = {
+        let m = MANAGER.lock().map_err(|_| "burst manager lock poisoned".to_string())?;
+        (m.leases.clone(), m.active.clone())
+    };
+    let cfg = tauri::async_runtime::spawn_blocking(|| {
+        crate::call_over_bridge("get_config", None, Duration::from_secs(5))
+    })
+    .await
+    .map_err(|e| format!("burst status join error: {}", e))?;
+    let burst_expires_at = match cfg {
+        Ok(v) => v.get("burst_expires_at").cloned().unwrap_or(Value::Null),
+        Err(e) => {
+            crate::log_line("WARN", &format!("burst status get_config failed: {}", e));
+            Value::Null
+        }
+    };
+    Ok(serde_json::json!({
+        "leases": leases,
+        "active": active,
+        "burst_expires_at": burst_expires_at,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renews_before_expiry_and_lapses_after_release() {
+        let mut m = LeaseManager::new();
+        assert_eq!(m.plan(0), None);
+
+        let id = m.acquire("main", 200, Some(vec!["cpu".into()]), None, 0);
+        let req = m.plan(0).expect("initial subscribe");
+        assert_eq!(req.interval_ms, 200);
+        m.on_subscribed(req, BURST_TTL_MS);
+
+        assert_eq!(m.plan(1_000), None);
+        assert!(m.plan(BURST_TTL_MS - RENEW_MARGIN_MS).is_some());
+
+        assert!(m.release(id));
+        assert_eq!(m.plan(BURST_TTL_MS - RENEW_MARGIN_MS), None);
+    }
+
+    #[test]
+    fn merges_leases_and_honours_window_and_duration() {
+        let mut m = LeaseManager::new();
+        m.acquire("a", 500, Some(vec!["net".into()]), None, 0);
+        m.acquire("b", 200, Some(vec!["cpu".into(), "net".into()]), Some(5_000), 0);
+        let req = m.plan(0).unwrap();
+        assert_eq!(req.interval_ms, 200);
+        assert_eq!(req.modules, Some(vec!["cpu".to_string(), "net".to_string()]));
+        m.on_subscribed(req, BURST_TTL_MS);
+
+        // b 的时限到达后间隔回到 500，需要立即重新订阅
+        let req = m.plan(5_000).unwrap();
+        assert_eq!(req.interval_ms, 500);
+        m.on_subscribed(req, 5_000 + BURST_TTL_MS);
+
+        assert_eq!(m.release_window("a"), 1);
+        assert_eq!(m.plan(6_000), None);
+    }
+
+    #[test]
+    fn force_renew_resubscribes_immediately() {
+        let mut m = LeaseManager::new();
+        m.acquire("main", 100, None, None, 0);
+        let req = m.plan(0).unwrap();
+        m.on_subscribed(req, BURST_TTL_MS);
+        assert_eq!(m.plan(10), None);
+        m.force_renew();
+        assert!(m.plan(10).is_some());
+    }
+}
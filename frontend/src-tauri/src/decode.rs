@@ -0,0 +1,181 @@
+// metrics 强类型解码与失败统计
+// 事件桥收到的每帧 metrics 按模块逐一尝试解码为 dto.rs 中的模型；解码结果不影响原样转发，
+// 失败时计数、发出 metrics_decode_error 诊断事件，并把整帧写入隔离文件便于复现。
+
+use crate::dto;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+/// 隔离文件超过该大小后轮转为 .1，只保留一份历史
+const QUARANTINE_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ModuleDecodeStats {
+    pub ok: u64,
+    pub errors: u64,
+    /// 服务端预热占位（status = "warming_up"）等跳过的次数
+    pub skipped: u64,
+    pub last_error: Option<String>,
+    pub last_error_ts: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct DecodeStats {
+    pub frames: u64,
+    pub failed_frames: u64,
+    pub modules: BTreeMap<String, ModuleDecodeStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodeError {
+    pub module: String,
+    pub error: String,
+}
+
+fn check<'a, T: Deserialize<'a>>(v: &'a Value) -> Result<(), String> {
+    T::deserialize(v).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// 按模块名选择模型；dto.ts 中没有定义的模块（network/gpu/sensor 等）不做校验
+fn check_module(name: &str, v: &Value) -> Option<Result<(), String>> {
+    let res = match name {
+        "cpu" => check::<dto::CpuMetrics>(v),
+        "memory" => check::<dto::MemoryMetrics>(v),
+        "disk" => check::<dto::DiskMetrics>(v),
+        "power" => check::<dto::PowerMetrics>(v),
+        "peripherals" => check::<dto::PeripheralsMetrics>(v),
+        _ => return None,
+    };
+    Some(res)
+}
+
+fn is_warmup_placeholder(v: &Value) -> bool {
+    v.get("status").and_then(|s| s.as_str()) == Some("warming_up")
+}
+
+pub fn decode_frame(payload: &Value, stats: &mut DecodeStats, now: u64) -> Vec<DecodeError> {
+    let mut errors = Vec::new();
+    stats.frames += 1;
+    let mut results = vec![("frame".to_string(), Some(check::<dto::MetricsFrame>(payload)))];
+    if let Some(obj) = payload.as_object() {
+        for (name, v) in obj {
+            // 模块显式为 null 表示本帧未采集，不算失败
+            if name == "ts" || name == "seq" || v.is_null() {
+                continue;
+            }
+            if is_warmup_placeholder(v) {
+                stats.modules.entry(name.clone()).or_default().skipped += 1;
+                continue;
+            }
+            results.push((name.clone(), check_module(name, v)));
+        }
+    }
+    for (name, res) in results {
+        let Some(res) = res else { continue };
+        let entry = stats.modules.entry(name.clone()).or_default();
+        match res {
+            Ok(()) => entry.ok += 1,
+            Err(e) => {
+                entry.errors += 1;
+                entry.last_error = Some(e.clone());
+                entry.last_error_ts = Some(now);
+                errors.push(DecodeError { module: name, error: e });
+            }
+        }
+    }
+    if !errors.is_empty() {
+        stats.failed_frames += 1;
+    }
+    errors
+}
+
+static STATS: Mutex<DecodeStats> = Mutex::new(DecodeStats { frames: 0, failed_frames: 0, modules: BTreeMap::new() });
+
+pub fn decode_error_count() -> u64 {
+    STATS.lock().map(|s| s.modules.values().map(|m| m.errors).sum()).unwrap_or(0)
+}
+
+/// 校验一帧 metrics；有模块解码失败时发出诊断事件并隔离整帧
+pub fn check_metrics(app: &tauri::AppHandle, payload: &Value) {
+    let now = crate::now_millis();
+    let errors = match STATS.lock() {
+        Ok(mut s) => decode_frame(payload, &mut s, now),
+        Err(_) => return,
+    };
+    if errors.is_empty() {
+        return;
+    }
+    let _ = app.emit(
+        "metrics_decode_error",
+        serde_json::json!({
+            "ts": now,
+            "seq": payload.get("seq"),
+            "errors": errors,
+        }),
+    );
+    quarantine(payload, &errors, now);
+}
+
+fn quarantine(payload: &Value, errors: &[DecodeError], now: u64) {
+    let dir = crate::log_dir().join("quarantine");
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join("metrics_decode.ndjson");
+    if std::fs::metadata(&path).map(|m| m.len() > QUARANTINE_MAX_BYTES).unwrap_or(false) {
+        let _ = std::fs::rename(&path, dir.join("metrics_decode.ndjson.1"));
+    }
+    let line = serde_json::json!({ "ts": now, "errors": errors, "frame": payload });
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(mut f) => {
+            let _ = writeln!(f, "{}", line);
+        }
+        Err(e) => crate::log_line("ERROR", &format!("quarantine write failed: {}", e)),
+    }
+}
+
+#[tauri::command]
+pub fn metrics_decode_stats() -> Result<DecodeStats, String> {
+    STATS.lock().map(|s| s.clone()).map_err(|_| "decode stats lock poisoned".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tolerates_unknown_fields_and_modules() {
+        let mut stats = DecodeStats::default();
+        let frame = json!({
+            "ts": 1, "seq": 2,
+            "cpu": { "usage_percent": 12.5, "per_core": [1.0, 2.0] },
+            "memory": { "total_mb": 16000, "used_mb": 2048, "percent_used": 12.8 },
+            "network": { "anything": true },
+            "power": { "battery": { "state": "ac", "percentage": null, "vendor_ext": 1 } }
+        });
+        assert!(decode_frame(&frame, &mut stats, 0).is_empty());
+        assert_eq!(stats.modules["cpu"].ok, 1);
+        assert!(!stats.modules.contains_key("network"));
+    }
+
+    #[test]
+    fn counts_type_drift_per_module() {
+        let mut stats = DecodeStats::default();
+        let frame = json!({
+            "ts": 1, "seq": 2,
+            "cpu": { "usage_percent": "12.5" },
+            "disk": { "status": "warming_up" }
+        });
+        let errors = decode_frame(&frame, &mut stats, 42);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].module, "cpu");
+        assert_eq!(stats.failed_frames, 1);
+        assert_eq!(stats.modules["cpu"].errors, 1);
+        assert_eq!(stats.modules["cpu"].last_error_ts, Some(42));
+        assert_eq!(stats.modules["disk"].skipped, 1);
+    }
+}
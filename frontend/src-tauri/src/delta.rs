@@ -0,0 +1,248 @@
+// metrics 增量推送（按窗口开启）
+// 开启的窗口不再收到整帧 metrics 事件，改为收到 metrics_delta：周期性关键帧 + 相邻帧之间的
+// JSON Merge Patch（RFC 7396）增量。前端按 base_id 校验连续性，断链时调用 metrics_request_keyframe。
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{Emitter, EventTarget};
+
+/// 默认关键帧间隔
+const DEFAULT_KEYFRAME_INTERVAL_MS: u64 = 10_000;
+/// 关键帧间隔下限，避免退化为每帧全量
+const MIN_KEYFRAME_INTERVAL_MS: u64 = 1_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeltaMessage {
+    /// "key" | "delta"
+    pub kind: &'static str,
+    /// 窗口内单调递增的消息编号
+    pub id: u64,
+    /// delta 基于的上一条消息编号；关键帧为 None
+    pub base_id: Option<u64>,
+    pub data: Value,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct DeltaStats {
+    pub frames: u64,
+    pub keyframes: u64,
+    /// 若按整帧发送的字节数
+    pub full_bytes: u64,
+    /// 实际发送的字节数
+    pub sent_bytes: u64,
+}
+
+struct WindowStream {
+    keyframe_interval_ms: u64,
+    last: Option<Value>,
+    last_id: u64,
+    last_key_at: u64,
+    force_key: bool,
+    stats: DeltaStats,
+}
+
+impl WindowStream {
+    fn new(keyframe_interval_ms: u64) -> Self {
+        Self {
+            keyframe_interval_ms: keyframe_interval_ms.max(MIN_KEYFRAME_INTERVAL_MS),
+            last: None,
+            last_id: 0,
+            last_key_at: 0,
+            force_key: true,
+            stats: DeltaStats::default(),
+        }
+    }
+
+    fn encode(&mut self, frame: &Value, now: u64) -> DeltaMessage {
+        let full_len = frame.to_string().len() as u64;
+        let due = self.force_key || now.saturating_sub(self.last_key_at) >= self.keyframe_interval_ms;
+        let patch = match (&self.last, due) {
+            (Some(last), false) => Some(diff(last, frame).unwrap_or_else(|| Value::Object(Map::new()))),
+            _ => None,
+        };
+        self.last_id += 1;
+        // 增量不比整帧小时直接发关键帧
+        let msg = match patch {
+            Some(p) if (p.to_string().len() as u64) < full_len => {
+                DeltaMessage { kind: "delta", id: self.last_id, base_id: Some(self.last_id - 1), data: p }
+            }
+            _ => {
+                self.force_key = false;
+                self.last_key_at = now;
+                self.stats.keyframes += 1;
+                DeltaMessage { kind: "key", id: self.last_id, base_id: None, data: frame.clone() }
+            }
+        };
+        self.stats.frames += 1;
+        self.stats.full_bytes += full_len;
+        self.stats.sent_bytes += msg.data.to_string().len() as u64;
+        self.last = Some(frame.clone());
+        msg
+    }
+}
+
+/// 计算 old -> new 的 merge patch；无变化返回 None
+/// merge patch 中 null 表示删除，因此 new 中的 null 字段与缺失等价
+pub fn diff(old: &Value, new: &Value) -> Option<Value> {
+    match (old, new) {
+        (Value::Object(o), Value::Object(n)) => {
+            let mut patch = Map::new();
+            for (k, nv) in n {
+                match o.get(k) {
+                    Some(ov) => {
+                        if let Some(p) = diff(ov, nv) {
+                            patch.insert(k.clone(), p);
+                        }
+                    }
+                    None if nv.is_null() => {}
+                    None => {
+                        patch.insert(k.clone(), nv.clone());
+                    }
+                }
+            }
+            for (k, ov) in o {
+                if !n.contains_key(k) && !ov.is_null() {
+                    patch.insert(k.clone(), Value::Null);
+                }
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        // 数组与标量整体替换；对象 -> 非对象时 patch 非对象值即替换
+        _ if old == new => None,
+        _ => Some(new.clone()),
+    }
+}
+
+static STREAMS: Mutex<BTreeMap<String, WindowStream>> = Mutex::new(BTreeMap::new());
+
+/// 向已开启增量模式的窗口发送 metrics_delta，返回这些窗口的 label（整帧广播需排除它们）
+pub fn publish(app: &tauri::AppHandle, frame: &Value) -> Vec<String> {
+    let now = crate::now_millis();
+    let messages: Vec<(String, DeltaMessage)> = match STREAMS.lock() {
+        Ok(mut streams) => streams.iter_mut().map(|(label, s)| (label.clone(), s.encode(frame, now))).collect(),
+        Err(_) => return Vec::new(),
+    };
+    messages
+        .into_iter()
+        .map(|(label, msg)| {
+            let _ = app.emit_to(EventTarget::AnyLabel { label: label.clone() }, "metrics_delta", msg);
+            label
+        })
+        .collect()
+}
+
+/// 目标是否属于给定窗口集合；App 级监听与广播目标不受影响
+pub fn targets_any(target: &EventTarget, labels: &[String]) -> bool {
+    match target {
+        EventTarget::AnyLabel { label }
+        | EventTarget::Window { label }
+        | EventTarget::Webview { label }
+        | EventTarget::WebviewWindow { label } => labels.iter().any(|l| l == label),
+        _ => false,
+    }
+}
+
+pub fn remove_window(label: &str) {
+    if let Ok(mut streams) = STREAMS.lock() {
+        streams.remove(label);
+    }
+}
+
+fn lock() -> Result<std::sync::MutexGuard<'static, BTreeMap<String, WindowStream>>, String> {
+    STREAMS.lock().map_err(|_| "delta lock poisoned".to_string())
+}
+
+#[tauri::command]
+pub fn metrics_delta_enable(window: tauri::Window, enable: bool, keyframe_interval_ms: Option<u64>) -> Result<(), String> {
+    let mut streams = lock()?;
+    let label = window.label().to_string();
+    if enable {
+        let interval = keyframe_interval_ms.unwrap_or(DEFAULT_KEYFRAME_INTERVAL_MS);
+        match streams.get_mut(&label) {
+            Some(s) => {
+                s.keyframe_interval_ms = interval.max(MIN_KEYFRAME_INTERVAL_MS);
+                s.force_key = true;
+            }
+            None => {
+                streams.insert(label, WindowStream::new(interval));
+            }
+        }
+    } else {
+        streams.remove(&label);
+    }
+    Ok(())
+}
+
+/// 下一帧改发关键帧
+#[tauri::command]
+pub fn metrics_request_keyframe(window: tauri::Window) -> Result<(), String> {
+    match lock()?.get_mut(window.label()) {
+        Some(s) => {
+            s.force_key = true;
+            Ok(())
+        }
+        None => Err("delta mode not enabled for this window".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn metrics_delta_stats(window: tauri::Window) -> Result<Option<DeltaStats>, String> {
+    Ok(lock()?.get(window.label()).map(|s| s.stats.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 按 RFC 7396 应用 merge patch，与前端 applyMergePatch 一致
+    fn apply(target: &mut Value, patch: &Value) {
+        let Value::Object(p) = patch else {
+            *target = patch.clone();
+            return;
+        };
+        if !target.is_object() {
+            *target = Value::Object(Map::new());
+        }
+        let Value::Object(t) = target else { return };
+        for (k, v) in p {
+            if v.is_null() {
+                t.remove(k);
+            } else {
+                apply(t.entry(k.clone()).or_insert(Value::Null), v);
+            }
+        }
+    }
+
+    #[test]
+    fn diff_then_apply_roundtrips() {
+        let a = json!({ "ts": 1, "cpu": { "usage_percent": 10.0 }, "disk": { "per_volume": [1, 2] }, "gpu": 1 });
+        let b = json!({ "ts": 2, "cpu": { "usage_percent": 10.0 }, "disk": { "per_volume": [1, 3] }, "usb": [] });
+        let p = diff(&a, &b).unwrap();
+        assert_eq!(p, json!({ "ts": 2, "disk": { "per_volume": [1, 3] }, "usb": [], "gpu": null }));
+        let mut t = a.clone();
+        apply(&mut t, &p);
+        assert_eq!(t, b);
+        assert!(diff(&b, &b).is_none());
+    }
+
+    #[test]
+    fn emits_keyframes_on_schedule_and_request() {
+        let mut s = WindowStream::new(5_000);
+        let big = "x".repeat(200);
+        let f1 = json!({ "ts": 1, "static": big, "cpu": 1 });
+        let f2 = json!({ "ts": 2, "static": big, "cpu": 2 });
+        let m1 = s.encode(&f1, 0);
+        assert_eq!((m1.kind, m1.id, m1.base_id), ("key", 1, None));
+        let m2 = s.encode(&f2, 1_000);
+        assert_eq!((m2.kind, m2.base_id), ("delta", Some(1)));
+        assert_eq!(m2.data, json!({ "ts": 2, "cpu": 2 }));
+        assert_eq!(s.encode(&f1, 5_000).kind, "key");
+        s.force_key = true;
+        assert_eq!(s.encode(&f2, 5_100).kind, "key");
+        assert_eq!(s.stats.keyframes, 3);
+        assert!(s.stats.sent_bytes < s.stats.full_bytes);
+    }
+}
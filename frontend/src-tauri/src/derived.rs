@@ -0,0 +1,304 @@
+// 派生指标
+// 按声明的定义对每帧 metrics 计算派生序列（比值、数组求和、累计计数器速率、EWMA 平滑），结果写入帧的 derived 键；
+// 实时帧共用一份状态（速率/平滑依赖前一帧），历史结果每次查询用新的状态按时间顺序计算。
+// 字段路径为点分形式，可用 | 给出备选路径（如 memory.used|memory.used_mb），取第一个存在的数值。
+
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Expr {
+    /// num / den × scale；分母为 0 时为 null
+    Ratio {
+        num: String,
+        den: String,
+        #[serde(default = "default_scale")]
+        scale: f64,
+    },
+    /// 对数组各元素的 field 求和；数组缺失时为 null
+    Sum { array: String, field: String },
+    /// 累计计数器的每秒速率；计数器回退（重置）时为 null 并重新起算
+    Rate { counter: String },
+    /// 指数加权平均，source 可引用前面定义的派生指标（derived.<name>）
+    Ewma { source: String, alpha: f64 },
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DerivedDef {
+    pub name: String,
+    #[serde(flatten)]
+    pub expr: Expr,
+}
+
+fn def(name: &str, expr: Expr) -> DerivedDef {
+    DerivedDef { name: name.to_string(), expr }
+}
+
+pub fn default_defs() -> Vec<DerivedDef> {
+    let ratio = |num: &str, den: &str| Expr::Ratio { num: num.into(), den: den.into(), scale: 100.0 };
+    let sum = |field: &str| Expr::Sum { array: "disk.per_volume_io".into(), field: field.into() };
+    vec![
+        def("memory_used_percent", ratio("memory.used|memory.used_mb", "memory.total|memory.total_mb")),
+        def("disk_capacity_used_percent", ratio("disk.capacity_totals.used_bytes", "disk.capacity_totals.total_bytes")),
+        def("disk_volumes_read_bytes_per_sec", sum("read_bytes_per_sec")),
+        def("disk_volumes_write_bytes_per_sec", sum("write_bytes_per_sec")),
+        def("cpu_usage_ewma", Expr::Ewma { source: "cpu.usage_percent".into(), alpha: 0.3 }),
+    ]
+}
+
+pub fn validate(defs: &[DerivedDef]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for d in defs {
+        if d.name.is_empty() || d.name.contains('.') {
+            return Err(format!("invalid derived name: {:?}", d.name));
+        }
+        if !names.insert(d.name.as_str()) {
+            return Err(format!("duplicate derived name: {}", d.name));
+        }
+        match &d.expr {
+            Expr::Ratio { scale, .. } if !scale.is_finite() => return Err(format!("{}: invalid scale", d.name)),
+            Expr::Ewma { alpha, .. } if !(*alpha > 0.0 && *alpha <= 1.0) => {
+                return Err(format!("{}: alpha must be in (0, 1]", d.name))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn get_path<'a>(v: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(v, |v, k| v.get(k))
+}
+
+fn number(v: &Value, spec: &str) -> Option<f64> {
+    spec.split('|').find_map(|p| get_path(v, p.trim()).and_then(|x| x.as_f64()))
+}
+
+pub struct Engine {
+    enabled: bool,
+    defs: Vec<DerivedDef>,
+    /// 每个定义的前一次 (值, ts)，供速率与平滑使用
+    state: Vec<Option<(f64, i64)>>,
+}
+
+impl Engine {
+    pub fn new(defs: Vec<DerivedDef>) -> Self {
+        let state = vec![None; defs.len()];
+        Self { enabled: true, defs, state }
+    }
+
+    fn eval(&mut self, i: usize, frame: &Value, ts: i64) -> Option<f64> {
+        match &self.defs[i].expr {
+            Expr::Ratio { num, den, scale } => {
+                let (n, d) = (number(frame, num)?, number(frame, den)?);
+                (d != 0.0).then(|| n / d * scale)
+            }
+            Expr::Sum { array, field } => {
+                let arr = get_path(frame, array)?.as_array()?;
+                Some(arr.iter().filter_map(|e| number(e, field)).sum())
+            }
+            Expr::Rate { counter } => {
+                let v = number(frame, counter)?;
+                let prev = self.state[i].replace((v, ts));
+                let (pv, pts) = prev?;
+                (ts > pts && v >= pv).then(|| (v - pv) / (ts - pts) as f64 * 1000.0)
+            }
+            Expr::Ewma { source, alpha } => {
+                let x = number(frame, source)?;
+                let s = match self.state[i] {
+                    Some((prev, _)) => alpha * x + (1.0 - alpha) * prev,
+                    None => x,
+                };
+                self.state[i] = Some((s, ts));
+                Some(s)
+            }
+        }
+    }
+
+    /// 清空速率/平滑状态，下一帧重新起算
+    pub fn reset(&mut self) {
+        self.state = vec![None; self.defs.len()];
+    }
+
+    /// 计算并写入 frame.derived；未启用或没有定义时返回 false
+    pub fn apply(&mut self, frame: &mut Value) -> bool {
+        if !self.enabled || self.defs.is_empty() || !frame.is_object() {
+            return false;
+        }
+        let ts = frame.get("ts").and_then(|t| t.as_i64()).unwrap_or(0);
+        frame["derived"] = Value::Object(Map::new());
+        for i in 0..self.defs.len() {
+            let v = self.eval(i, frame, ts).filter(|f| f.is_finite());
+            let name = self.defs[i].name.clone();
+            frame["derived"][name] = v.map(|f| serde_json::json!(f)).unwrap_or(Value::Null);
+        }
+        true
+    }
+
+    /// 对 { ok, items } 形态的历史结果按时间顺序计算（使用新的状态）
+    pub fn apply_history(&self, result: &mut Value) {
+        let mut engine = Engine { enabled: self.enabled, defs: self.defs.clone(), state: vec![None; self.defs.len()] };
+        if let Some(Value::Array(items)) = result.get_mut("items") {
+            for item in items {
+                engine.apply(item);
+            }
+        }
+    }
+}
+
+static ENGINE: Mutex<Option<Engine>> = Mutex::new(None);
+
+fn with_engine<T>(f: impl FnOnce(&mut Engine) -> T) -> Option<T> {
+    let mut guard = ENGINE.lock().ok()?;
+    Some(f(guard.get_or_insert_with(|| Engine::new(default_defs()))))
+}
+
+/// 由 dispatch_notification 对每帧实时 metrics 调用；写入了 derived 时返回 true
+pub fn apply_live(frame: &mut Value) -> bool {
+    with_engine(|e| e.apply(frame)).unwrap_or(false)
+}
+
+/// 把 derived 追加到原始帧字节末尾，其余字段不重新序列化；帧不是对象时返回 None
+pub fn splice_raw(raw: &RawValue, derived: &Value) -> Option<Box<RawValue>> {
+    let body = raw.get().trim_end().strip_suffix('}')?;
+    let sep = if body.trim_end().ends_with('{') { "" } else { "," };
+    let derived = serde_json::to_string(derived).ok()?;
+    RawValue::from_string(format!("{}{}\"derived\":{}}}", body, sep, derived)).ok()
+}
+
+/// 回放开始/定位/结束时调用：实时帧与回放帧的速率、平滑状态互不沿用
+pub fn reset_live() {
+    with_engine(|e| e.reset());
+}
+
+/// 对历史结果（query_history / local_query_history / get_recent_metrics）补充 derived
+pub fn apply_history(result: &mut Value) {
+    with_engine(|e| e.apply_history(result));
+}
+
+#[derive(Debug, Serialize)]
+pub struct DerivedConfig {
+    pub enabled: bool,
+    pub defs: Vec<DerivedDef>,
+}
+
+#[tauri::command]
+pub fn get_derived_metrics() -> Result<DerivedConfig, String> {
+    with_engine(|e| DerivedConfig { enabled: e.enabled, defs: e.defs.clone() }).ok_or_else(|| "derived lock poisoned".to_string())
+}
+
+/// defs 整体替换（传空数组即清空）；reset_defaults 恢复内置定义
+#[tauri::command]
+pub fn set_derived_metrics(enabled: Option<bool>, defs: Option<Vec<DerivedDef>>, reset_defaults: Option<bool>) -> Result<DerivedConfig, String> {
+    let defs = if reset_defaults.unwrap_or(false) { Some(default_defs()) } else { defs };
+    if let Some(d) = &defs {
+        validate(d)?;
+    }
+    with_engine(|e| {
+        if let Some(d) = defs {
+            *e = Engine { enabled: e.enabled, state: vec![None; d.len()], defs: d };
+        }
+        if let Some(v) = enabled {
+            e.enabled = v;
+        }
+        DerivedConfig { enabled: e.enabled, defs: e.defs.clone() }
+    })
+    .ok_or_else(|| "derived lock poisoned".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn default_ratios_and_sums() {
+        let mut e = Engine::new(default_defs());
+        let mut frame = json!({
+            "ts": 1,
+            "memory": { "total_mb": 8000, "used_mb": 2000 },
+            "disk": { "per_volume_io": [{ "read_bytes_per_sec": 10, "write_bytes_per_sec": 1 }, { "read_bytes_per_sec": 5 }] },
+            "cpu": { "usage_percent": 40 }
+        });
+        assert!(e.apply(&mut frame));
+        assert_eq!(
+            frame["derived"],
+            json!({
+                "memory_used_percent": 25.0,
+                "disk_capacity_used_percent": null,
+                "disk_volumes_read_bytes_per_sec": 15.0,
+                "disk_volumes_write_bytes_per_sec": 1.0,
+                "cpu_usage_ewma": 40.0
+            })
+        );
+    }
+
+    #[test]
+    fn rates_and_ewma_carry_state() {
+        let defs: Vec<DerivedDef> = serde_json::from_value(json!([
+            { "name": "rx", "kind": "rate", "counter": "net.rx_bytes" },
+            { "name": "rx_smooth", "kind": "ewma", "source": "derived.rx", "alpha": 0.5 }
+        ]))
+        .unwrap();
+        let mut result = json!({ "ok": true, "items": [
+            { "ts": 0, "net": { "rx_bytes": 0 } },
+            { "ts": 1000, "net": { "rx_bytes": 100 } },
+            { "ts": 2000, "net": { "rx_bytes": 400 } },
+            { "ts": 3000, "net": { "rx_bytes": 50 } }
+        ]});
+        Engine::new(defs).apply_history(&mut result);
+        let got: Vec<Value> = result["items"].as_array().unwrap().iter().map(|i| i["derived"].clone()).collect();
+        assert_eq!(
+            got,
+            vec![
+                json!({ "rx": null, "rx_smooth": null }),
+                json!({ "rx": 100.0, "rx_smooth": 100.0 }),
+                json!({ "rx": 300.0, "rx_smooth": 200.0 }),
+                json!({ "rx": null, "rx_smooth": null }),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_restarts_rates_and_smoothing() {
+        let defs: Vec<DerivedDef> = serde_json::from_value(json!([
+            { "name": "rx", "kind": "rate", "counter": "net.rx_bytes" },
+            { "name": "cpu", "kind": "ewma", "source": "cpu.usage_percent", "alpha": 0.5 }
+        ]))
+        .unwrap();
+        let mut e = Engine::new(defs);
+        e.apply(&mut json!({ "ts": 0, "net": { "rx_bytes": 0 }, "cpu": { "usage_percent": 0 } }));
+        e.reset();
+        // 回放帧的时间与计数器与实时帧无关，reset 后不应沿用上一帧
+        let mut frame = json!({ "ts": 1000, "net": { "rx_bytes": 500 }, "cpu": { "usage_percent": 80 } });
+        e.apply(&mut frame);
+        assert_eq!(frame["derived"], json!({ "rx": null, "cpu": 80.0 }));
+    }
+
+    #[test]
+    fn splice_appends_without_reserializing() {
+        let raw = RawValue::from_string(r#"{"ts":1, "cpu":{"usage_percent":1.50}}"#.to_string()).unwrap();
+        let out = splice_raw(&raw, &json!({ "x": 2.0 })).unwrap();
+        // 原有字段保持原始字节（1.50 不会变成 1.5）
+        assert_eq!(out.get(), r#"{"ts":1, "cpu":{"usage_percent":1.50},"derived":{"x":2.0}}"#);
+        let empty = RawValue::from_string("{ }".to_string()).unwrap();
+        assert_eq!(splice_raw(&empty, &json!({})).unwrap().get(), r#"{ "derived":{}}"#);
+    }
+
+    #[test]
+    fn rejects_invalid_defs() {
+        let ewma = |name: &str, alpha: f64| def(name, Expr::Ewma { source: "cpu.usage_percent".into(), alpha });
+        assert!(validate(&[ewma("a", 0.5), ewma("a", 0.5)]).is_err());
+        assert!(validate(&[ewma("a", 1.5)]).is_err());
+        assert!(validate(&[ewma("a.b", 0.5)]).is_err());
+        assert!(validate(&default_defs()).is_ok());
+    }
+}
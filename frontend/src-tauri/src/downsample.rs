@@ -0,0 +1,316 @@
+// 面向图表的历史降采样
+// query_history 的 step_ms 每桶只保留最后一条，长时间窗口下会丢失尖峰。
+// 此处在桥接侧对历史结果（服务端 query_history、local_query_history、get_recent_metrics）按图表宽度降到 N 个点：
+// - lttb：Largest-Triangle-Three-Buckets，按 field 序列选取原始样本，保形且保留尖峰
+// - minmax：按时间等分 N/2 个桶，每桶保留 field 最小与最大的两个原始样本（时间顺序）
+// 图表同时绘制多条序列时以 fields 指定，点数预算按序列均分，各序列选中的样本取并集（时间顺序）。
+// - avg：按时间等分 N 个桶，每桶数值字段取平均，其余字段取最后一条，ts 为桶内最后一条的 ts
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[default]
+    Lttb,
+    MinMax,
+    Avg,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DownsampleSpec {
+    /// 目标点数（通常为图表像素宽度）
+    pub points: usize,
+    #[serde(default)]
+    pub method: Method,
+    /// lttb/minmax 选点依据的字段路径，如 "cpu.usage_percent"；缺省取首条样本的第一个数值字段
+    pub field: Option<String>,
+    /// 多条序列的字段路径，给出时优先于 field
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+impl DownsampleSpec {
+    fn select_fields(&self, items: &[Value]) -> Vec<String> {
+        if !self.fields.is_empty() {
+            return self.fields.clone();
+        }
+        self.field.clone().or_else(|| default_field(items)).into_iter().collect()
+    }
+}
+
+fn get_path<'a>(item: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(item, |v, k| v.get(k))
+}
+
+/// 收集数值叶子字段（跳过 ts/seq），路径为各级键
+fn numeric_leaves(v: &Value, prefix: &mut Vec<String>, out: &mut Vec<(Vec<String>, f64)>) {
+    let Value::Object(m) = v else { return };
+    for (k, child) in m {
+        if prefix.is_empty() && (k == "ts" || k == "seq") {
+            continue;
+        }
+        prefix.push(k.clone());
+        match child {
+            Value::Number(n) => {
+                if let Some(f) = n.as_f64() {
+                    out.push((prefix.clone(), f));
+                }
+            }
+            Value::Object(_) => numeric_leaves(child, prefix, out),
+            _ => {}
+        }
+        prefix.pop();
+    }
+}
+
+fn set_path(obj: &mut Map<String, Value>, path: &[String], value: Value) {
+    let Some((last, parents)) = path.split_last() else { return };
+    let mut cur = obj;
+    for p in parents {
+        let Some(Value::Object(m)) = cur.get_mut(p) else { return };
+        cur = m;
+    }
+    cur.insert(last.clone(), value);
+}
+
+fn ts_of(item: &Value) -> f64 {
+    item.get("ts").and_then(|t| t.as_f64()).unwrap_or(0.0)
+}
+
+fn default_field(items: &[Value]) -> Option<String> {
+    items.iter().find_map(|it| {
+        let mut out = Vec::new();
+        numeric_leaves(it, &mut Vec::new(), &mut out);
+        out.into_iter().next().map(|(p, _)| p.join("."))
+    })
+}
+
+/// field 序列；缺失值沿用前一个值（开头缺失记 0），保证选点计算连续
+fn series(items: &[Value], field: &str) -> Vec<f64> {
+    let mut prev = 0.0;
+    items
+        .iter()
+        .map(|it| {
+            if let Some(f) = get_path(it, field).and_then(|v| v.as_f64()) {
+                prev = f;
+            }
+            prev
+        })
+        .collect()
+}
+
+/// 按时间等分为 n 个桶，返回各非空桶的下标区间
+fn time_buckets(items: &[Value], n: usize) -> Vec<std::ops::Range<usize>> {
+    let (t0, t1) = (ts_of(&items[0]), ts_of(&items[items.len() - 1]));
+    let width = ((t1 - t0) / n as f64).max(f64::MIN_POSITIVE);
+    let mut out: Vec<std::ops::Range<usize>> = Vec::new();
+    let mut current = usize::MAX;
+    for (i, it) in items.iter().enumerate() {
+        let b = (((ts_of(it) - t0) / width) as usize).min(n - 1);
+        match out.last_mut() {
+            Some(r) if b == current => r.end = i + 1,
+            _ => out.push(i..i + 1),
+        }
+        current = b;
+    }
+    out
+}
+
+pub fn lttb_indices(x: &[f64], y: &[f64], threshold: usize) -> Vec<usize> {
+    let n = x.len();
+    if threshold >= n || threshold < 3 {
+        return (0..n).collect();
+    }
+    let every = (n - 2) as f64 / (threshold - 2) as f64;
+    let mut out = Vec::with_capacity(threshold);
+    let mut a = 0;
+    out.push(a);
+    for i in 0..threshold - 2 {
+        let next_start = ((i + 1) as f64 * every) as usize + 1;
+        let next_end = (((i + 2) as f64 * every) as usize + 1).min(n);
+        let len = (next_end - next_start) as f64;
+        let avg_x = x[next_start..next_end].iter().sum::<f64>() / len;
+        let avg_y = y[next_start..next_end].iter().sum::<f64>() / len;
+
+        let start = (i as f64 * every) as usize + 1;
+        let end = next_start;
+        let mut best = (start, -1.0);
+        for j in start..end {
+            let area = ((x[a] - avg_x) * (y[j] - y[a]) - (x[a] - x[j]) * (avg_y - y[a])).abs();
+            if area > best.1 {
+                best = (j, area);
+            }
+        }
+        a = best.0;
+        out.push(a);
+    }
+    out.push(n - 1);
+    out
+}
+
+fn minmax(items: &[Value], y: &[f64], points: usize) -> Vec<usize> {
+    let mut out = Vec::with_capacity(points);
+    for r in time_buckets(items, (points / 2).max(1)) {
+        let (mut lo, mut hi) = (r.start, r.start);
+        for i in r {
+            if y[i] < y[lo] {
+                lo = i;
+            }
+            if y[i] > y[hi] {
+                hi = i;
+            }
+        }
+        out.push(lo.min(hi));
+        if lo != hi {
+            out.push(lo.max(hi));
+        }
+    }
+    out
+}
+
+fn average(items: &[Value], points: usize) -> Vec<Value> {
+    time_buckets(items, points)
+        .into_iter()
+        .map(|r| {
+            let mut sums: BTreeMap<Vec<String>, (f64, u32)> = BTreeMap::new();
+            for it in &items[r.clone()] {
+                let mut leaves = Vec::new();
+                numeric_leaves(it, &mut Vec::new(), &mut leaves);
+                for (path, f) in leaves {
+                    let e = sums.entry(path).or_default();
+                    e.0 += f;
+                    e.1 += 1;
+                }
+            }
+            let mut out = items[r.end - 1].clone();
+            if let Value::Object(m) = &mut out {
+                for (path, (sum, n)) in sums {
+                    set_path(m, &path, serde_json::json!(sum / n as f64));
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// 降采样 items（需按 ts 升序）；点数不超过 points 时原样返回
+pub fn apply(items: Vec<Value>, spec: &DownsampleSpec) -> Vec<Value> {
+    if spec.points == 0 || items.len() <= spec.points {
+        return items;
+    }
+    if spec.method == Method::Avg {
+        return average(&items, spec.points);
+    }
+    let fields = spec.select_fields(&items);
+    if fields.is_empty() {
+        return items;
+    }
+    let budget = (spec.points / fields.len()).max(3);
+    let x: Vec<f64> = items.iter().map(ts_of).collect();
+    let mut keep: Vec<usize> = Vec::new();
+    for field in &fields {
+        let y = series(&items, field);
+        keep.extend(match spec.method {
+            Method::Lttb => lttb_indices(&x, &y, budget),
+            _ => minmax(&items, &y, budget),
+        });
+    }
+    keep.sort_unstable();
+    keep.dedup();
+    let mut items: Vec<Option<Value>> = items.into_iter().map(Some).collect();
+    keep.into_iter().filter_map(|i| items[i].take()).collect()
+}
+
+/// 对 { ok, items } 形态的结果就地降采样
+pub fn apply_result(result: &mut Value, spec: &DownsampleSpec) {
+    if let Some(Value::Array(items)) = result.get_mut("items") {
+        *items = apply(std::mem::take(items), spec);
+    }
+}
+
+/// 从 query_history 参数中取出 downsample（服务端不识别该字段）
+pub fn take_spec(params: &mut Option<Value>) -> Result<Option<DownsampleSpec>, String> {
+    let Some(spec) = params.as_mut().and_then(|p| p.as_object_mut()).and_then(|m| m.remove("downsample")) else {
+        return Ok(None);
+    };
+    if spec.is_null() {
+        return Ok(None);
+    }
+    serde_json::from_value(spec).map(Some).map_err(|e| format!("invalid downsample: {}", e))
+}
+
+#[tauri::command]
+pub fn downsample_history(items: Vec<Value>, spec: DownsampleSpec) -> Vec<Value> {
+    apply(items, &spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn items(ys: &[f64]) -> Vec<Value> {
+        ys.iter().enumerate().map(|(i, y)| json!({ "ts": i as i64 * 1_000, "cpu": { "usage_percent": y } })).collect()
+    }
+
+    fn cpu(items: &[Value]) -> Vec<f64> {
+        items.iter().map(|it| it["cpu"]["usage_percent"].as_f64().unwrap()).collect()
+    }
+
+    #[test]
+    fn lttb_keeps_endpoints_and_spike() {
+        let mut ys = vec![1.0; 100];
+        ys[37] = 95.0;
+        let spec = DownsampleSpec { points: 10, method: Method::Lttb, field: None, fields: vec![] };
+        let out = apply(items(&ys), &spec);
+        assert_eq!(out.len(), 10);
+        assert_eq!((out[0]["ts"].clone(), out[9]["ts"].clone()), (json!(0), json!(99_000)));
+        assert!(cpu(&out).contains(&95.0));
+        assert!(out.windows(2).all(|w| w[0]["ts"].as_i64() < w[1]["ts"].as_i64()));
+    }
+
+    #[test]
+    fn minmax_keeps_extremes_in_time_order() {
+        let ys: Vec<f64> = (0..40).map(|i| if i == 5 { -3.0 } else if i == 7 { 50.0 } else { i as f64 % 4.0 }).collect();
+        let spec = DownsampleSpec { points: 8, method: Method::MinMax, field: Some("cpu.usage_percent".into()), fields: vec![] };
+        let out = apply(items(&ys), &spec);
+        assert!(out.len() <= 8);
+        let got = cpu(&out);
+        assert!(got.contains(&-3.0) && got.contains(&50.0));
+        assert!(out.windows(2).all(|w| w[0]["ts"].as_i64() < w[1]["ts"].as_i64()));
+    }
+
+    #[test]
+    fn averages_numeric_fields_per_bucket() {
+        let mut src = items(&[1.0, 3.0, 5.0, 7.0]);
+        src[1]["power"] = json!({ "battery": { "state": "charging" } });
+        let spec = DownsampleSpec { points: 2, method: Method::Avg, field: None, fields: vec![] };
+        let out = apply(src, &spec);
+        assert_eq!(cpu(&out), vec![2.0, 6.0]);
+        assert_eq!(out[0]["ts"], json!(1_000));
+        assert_eq!(out[0]["power"]["battery"]["state"], json!("charging"));
+    }
+
+    #[test]
+    fn multiple_fields_keep_spikes_of_each_series() {
+        let mut src = items(&[1.0; 100]);
+        for (i, it) in src.iter_mut().enumerate() {
+            it["derived"] = json!({ "memory_used_percent": if i == 62 { 99.0 } else { 40.0 } });
+        }
+        src[20]["cpu"]["usage_percent"] = json!(90.0);
+        let spec = DownsampleSpec {
+            points: 12,
+            method: Method::Lttb,
+            field: None,
+            fields: vec!["cpu.usage_percent".into(), "derived.memory_used_percent".into()],
+        };
+        let out = apply(src, &spec);
+        assert!(out.len() <= 12);
+        assert!(cpu(&out).contains(&90.0));
+        assert!(out.iter().any(|it| it["derived"]["memory_used_percent"] == json!(99.0)));
+        assert!(out.windows(2).all(|w| w[0]["ts"].as_i64() < w[1]["ts"].as_i64()));
+    }
+}
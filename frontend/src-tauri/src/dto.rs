@@ -0,0 +1,229 @@
+// metrics 负载的强类型模型，对应 frontend/src/api/dto.ts 中的 SnapshotResult
+// 宽松模式：未知字段一律忽略；必填字段缺失或类型不符才算解码失败。
+// 字段名保持 snake_case，与服务端序列化策略一致。
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MetricsFrame {
+    pub ts: u64,
+    pub seq: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CpuMetrics {
+    pub usage_percent: f64,
+}
+
+// dto.ts 写作 total/used，服务端实际下发 total_mb/used_mb，两种写法都接受
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryMetrics {
+    #[serde(alias = "total_mb")]
+    pub total: f64,
+    #[serde(alias = "used_mb")]
+    pub used: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiskIoTotals {
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub read_iops: Option<f64>,
+    pub write_iops: Option<f64>,
+    pub busy_percent: Option<f64>,
+    pub queue_length: Option<f64>,
+    pub avg_read_latency_ms: Option<f64>,
+    pub avg_write_latency_ms: Option<f64>,
+    pub read_p50_ms: Option<f64>,
+    pub read_p95_ms: Option<f64>,
+    pub read_p99_ms: Option<f64>,
+    pub write_p50_ms: Option<f64>,
+    pub write_p95_ms: Option<f64>,
+    pub write_p99_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiskPerVolumeIo {
+    pub volume_id: String,
+    pub free_percent: Option<f64>,
+    #[serde(flatten)]
+    pub io: DiskIoTotals,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiskCapacityTotals {
+    pub total_bytes: Option<f64>,
+    pub used_bytes: Option<f64>,
+    pub free_bytes: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TopProcessDisk {
+    pub pid: i64,
+    pub name: String,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiskVolumeInfo {
+    pub id: String,
+    pub mount_point: String,
+    pub fs_type: Option<String>,
+    pub size_total_bytes: Option<f64>,
+    pub size_used_bytes: Option<f64>,
+    pub size_free_bytes: Option<f64>,
+    pub read_only: Option<bool>,
+    pub is_removable: Option<bool>,
+    pub bitlocker_encryption: Option<String>,
+    pub free_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiskPhysicalInfo {
+    pub id: String,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub firmware: Option<String>,
+    pub size_total_bytes: Option<f64>,
+    pub partitions: Option<f64>,
+    pub media_type: Option<String>,
+    pub spindle_speed_rpm: Option<f64>,
+    pub interface_type: Option<String>,
+    pub trim_supported: Option<bool>,
+    pub bus_type: Option<String>,
+    pub negotiated_link_speed: Option<String>,
+    pub is_removable: Option<bool>,
+    pub eject_capable: Option<bool>,
+}
+
+// SMART 字段众多且按介质差异很大，这里只约束标识与常用数值，其余字段按未知字段忽略
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SmartHealth {
+    pub disk_id: String,
+    pub overall_health: Option<String>,
+    pub temperature_c: Option<f64>,
+    pub power_on_hours: Option<f64>,
+    pub nvme_percentage_used: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiskMetrics {
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub queue_length: f64,
+    pub totals_source: Option<String>,
+    pub totals: Option<DiskIoTotals>,
+    pub per_physical_disk_io: Option<Vec<DiskIoTotals>>,
+    pub per_volume_io: Option<Vec<DiskPerVolumeIo>>,
+    pub top_processes_by_disk: Option<Vec<TopProcessDisk>>,
+    pub capacity_totals: Option<DiskCapacityTotals>,
+    pub vm_swapfiles_bytes: Option<f64>,
+    pub purgeable_space_bytes: Option<f64>,
+    pub apfs_local_snapshots_count: Option<f64>,
+    pub apfs_local_snapshots_bytes: Option<f64>,
+    pub per_volume: Option<Vec<DiskVolumeInfo>>,
+    pub per_physical_disk: Option<Vec<DiskPhysicalInfo>>,
+    pub smart_health: Option<Vec<SmartHealth>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BatteryMetrics {
+    pub percentage: Option<f64>,
+    pub state: String,
+    pub time_remaining_min: Option<f64>,
+    pub time_to_full_min: Option<f64>,
+    pub ac_line_online: Option<bool>,
+    pub time_on_battery_sec: Option<f64>,
+    pub temperature_c: Option<f64>,
+    pub cycle_count: Option<f64>,
+    pub condition: Option<String>,
+    pub full_charge_capacity_mah: Option<f64>,
+    pub design_capacity_mah: Option<f64>,
+    pub voltage_mv: Option<f64>,
+    pub current_ma: Option<f64>,
+    pub power_w: Option<f64>,
+    pub manufacturer: Option<String>,
+    pub serial_number: Option<String>,
+    pub manufacture_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PdProfile {
+    pub voltage_v: Option<f64>,
+    pub current_a: Option<f64>,
+    pub power_w: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PdInfo {
+    pub protocol: Option<String>,
+    pub negotiated_profile: Option<PdProfile>,
+    pub caps: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AdapterMetrics {
+    pub present: Option<bool>,
+    pub rated_watts: Option<f64>,
+    pub negotiated_watts: Option<f64>,
+    pub voltage_mv: Option<f64>,
+    pub current_ma: Option<f64>,
+    pub is_pd_fast_charge: Option<bool>,
+    pub charge_mode: Option<String>,
+    pub pd: Option<PdInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UpsMetrics {
+    pub present: Option<bool>,
+    pub percentage: Option<f64>,
+    pub runtime_min: Option<f64>,
+    pub power_source: Option<String>,
+    pub input_voltage_v: Option<f64>,
+    pub input_frequency_hz: Option<f64>,
+    pub load_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UsbDevice {
+    pub name: Option<String>,
+    pub pnp_class: Option<String>,
+    pub device_id: Option<String>,
+    pub status: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UsbMetrics {
+    pub devices: Vec<UsbDevice>,
+    pub ucsi_controllers: Option<Vec<UsbDevice>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PowerMetrics {
+    pub battery: BatteryMetrics,
+    pub adapter: Option<AdapterMetrics>,
+    pub ups: Option<UpsMetrics>,
+    pub usb: Option<UsbMetrics>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PeripheralBattery {
+    pub battery_percent: Option<f64>,
+    pub battery_mv: Option<f64>,
+    pub charging: Option<bool>,
+    pub connection: Option<String>,
+    pub source: Option<String>,
+    pub name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub last_seen_ts: Option<f64>,
+    pub ttl_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PeripheralsMetrics {
+    pub batteries: Vec<PeripheralBattery>,
+}
@@ -0,0 +1,558 @@
+// 历史导出（CSV / NDJSON）
+// 目标文件由用户在保存对话框中选定（pick_export_path，Windows 通用对话框，覆盖已有文件前会确认）；
+// export_history 只接受经对话框选出的路径，每个路径只能用于一次导出，前端无法写入任意位置。
+// 非 Windows 平台没有对话框，改为应用数据目录下 exports 目录中的同名文件（不覆盖）。
+// 按 chunk_ms 分段从服务端 query_history 或本地历史库读取，每段与 rpc_call 的 query_history 做相同的后处理
+// （derived、单位换算），逐段写入临时文件，完成后改名为目标文件；每段写完发送 export_progress 事件。分桶查询的分段边界对齐到桶边界，避免同一个桶被拆到两段。
+// CSV 列名为嵌套字段的点分路径（如 disk.totals.read_bytes_per_sec），ts 在首列，其余按路径字典序；
+// 未指定 columns 时先把各段暂存为 NDJSON 并收集全部行出现过的字段，结束后再转写为 CSV，表头不会遗漏后出现的字段。
+// 数组值以 JSON 文本写入单元格。
+
+use crate::localstore::{bucket_end, HistoryParams, Store};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::Emitter;
+#[cfg(not(windows))]
+use tauri::Manager;
+
+const DEFAULT_CHUNK_MS: i64 = 3_600_000;
+#[cfg(not(windows))]
+const EXPORT_DIR: &str = "exports";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// 服务端 query_history
+    #[default]
+    Service,
+    /// 本地历史库（localstore.rs）
+    Local,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportParams {
+    /// pick_export_path 返回的路径
+    pub path: String,
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default)]
+    pub source: Source,
+    #[serde(default)]
+    pub from_ts: i64,
+    #[serde(default)]
+    pub to_ts: i64,
+    pub modules: Option<Vec<String>>,
+    pub step_ms: Option<i64>,
+    pub agg: Option<String>,
+    /// 只导出这些列（点分路径）；CSV 按此顺序输出，NDJSON 按路径还原为嵌套对象
+    pub columns: Option<Vec<String>>,
+    pub chunk_ms: Option<i64>,
+}
+
+impl ExportParams {
+    fn bucket_ms(&self) -> Option<i64> {
+        match self.agg.as_deref().map(|a| a.to_ascii_lowercase()).as_deref() {
+            Some("10s") => Some(10_000),
+            Some("1m") => Some(60_000),
+            _ => self.step_ms.filter(|s| *s > 0),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ExportProgress {
+    pub export_id: u64,
+    pub path: String,
+    pub rows: u64,
+    pub bytes: u64,
+    /// 0..1，按已处理的时间范围计
+    pub progress: f64,
+    pub done: bool,
+    pub error: Option<String>,
+}
+
+/// 展开为 (点分路径, 叶子值)；数组视为叶子
+pub fn flatten(v: &Value, prefix: &str, out: &mut Vec<(String, Value)>) {
+    match v {
+        Value::Object(m) => {
+            for (k, child) in m {
+                let path = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+                flatten(child, &path, out);
+            }
+        }
+        _ if !prefix.is_empty() => out.push((prefix.to_string(), v.clone())),
+        _ => {}
+    }
+}
+
+fn get_path<'a>(item: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(item, |v, k| v.get(k))
+}
+
+fn set_path(obj: &mut Map<String, Value>, path: &str, value: Value) {
+    let mut parts: Vec<&str> = path.split('.').collect();
+    let Some(last) = parts.pop() else { return };
+    let mut cur = obj;
+    for p in parts {
+        let next = cur.entry(p.to_string()).or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(m) = next else { return };
+        cur = m;
+    }
+    cur.insert(last.to_string(), value);
+}
+
+pub fn csv_field(v: &Value) -> String {
+    let s = match v {
+        Value::Null => return String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+pub struct ExportWriter<W: Write> {
+    out: W,
+    format: Format,
+    columns: Option<Vec<String>>,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+impl<W: Write> ExportWriter<W> {
+    pub fn new(out: W, format: Format, columns: Option<Vec<String>>) -> Self {
+        Self { out, format, columns: columns.filter(|c| !c.is_empty()), rows: 0, bytes: 0 }
+    }
+
+    fn emit(&mut self, line: String) -> std::io::Result<()> {
+        self.out.write_all(line.as_bytes())?;
+        self.out.write_all(b"\n")?;
+        self.bytes += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    pub fn write_items(&mut self, items: &[Value]) -> std::io::Result<()> {
+        for item in items {
+            match self.format {
+                Format::Csv => {
+                    // 未给出列时以本次写入的全部行确定表头（run 总是先收集全部列再写 CSV）
+                    if self.columns.is_none() {
+                        self.columns = Some(csv_columns(&collect_columns(items)));
+                    }
+                    let cols = self.columns.clone().unwrap_or_default();
+                    if self.rows == 0 {
+                        self.emit(cols.iter().map(|c| csv_field(&Value::String(c.clone()))).collect::<Vec<_>>().join(","))?;
+                    }
+                    let line = cols
+                        .iter()
+                        .map(|c| get_path(item, c).map(csv_field).unwrap_or_default())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    self.emit(line)?;
+                }
+                Format::Ndjson => {
+                    let line = match &self.columns {
+                        Some(cols) => {
+                            let mut m = Map::new();
+                            for c in cols {
+                                if let Some(v) = get_path(item, c) {
+                                    set_path(&mut m, c, v.clone());
+                                }
+                            }
+                            Value::Object(m).to_string()
+                        }
+                        None => item.to_string(),
+                    };
+                    self.emit(line)?;
+                }
+            }
+            self.rows += 1;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// 收集各行出现过的点分路径
+pub fn collect_columns(items: &[Value]) -> BTreeSet<String> {
+    let mut set = BTreeSet::new();
+    for item in items {
+        let mut flat = Vec::new();
+        flatten(item, "", &mut flat);
+        set.extend(flat.into_iter().map(|(k, _)| k));
+    }
+    set
+}
+
+/// CSV 表头：ts 在首列，其余按路径字典序
+pub fn csv_columns(set: &BTreeSet<String>) -> Vec<String> {
+    std::iter::once("ts".to_string()).chain(set.iter().filter(|k| *k != "ts").cloned()).collect()
+}
+
+/// 建议的文件名只能是单个文件名，拒绝目录分隔符、盘符与隐藏/上级目录名
+pub fn validate_file_name(name: &str) -> Result<(), String> {
+    let bad = name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\', ':'])
+        || name.chars().any(|c| c.is_control());
+    if bad {
+        return Err(format!("invalid export file name: {:?}", name));
+    }
+    Ok(())
+}
+
+/// 经保存对话框选出、尚未用于导出的路径
+static GRANTED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+fn grant(path: PathBuf) {
+    if let Ok(mut g) = GRANTED.lock() {
+        g.push(path);
+    }
+}
+
+/// 取走一个已选定的路径；不是经对话框选出的路径返回 false
+fn take_grant(path: &Path) -> bool {
+    let Ok(mut g) = GRANTED.lock() else { return false };
+    match g.iter().position(|p| p == path) {
+        Some(i) => {
+            g.swap_remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Windows 通用“另存为”对话框；用户取消时返回 None
+#[cfg(windows)]
+fn choose_path(_app: &tauri::AppHandle, default_name: &str, format: Format) -> Result<Option<PathBuf>, String> {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStringExt;
+
+    #[repr(C)]
+    struct OpenFileNameW {
+        struct_size: u32,
+        owner: *mut c_void,
+        instance: *mut c_void,
+        filter: *const u16,
+        custom_filter: *mut u16,
+        max_cust_filter: u32,
+        filter_index: u32,
+        file: *mut u16,
+        max_file: u32,
+        file_title: *mut u16,
+        max_file_title: u32,
+        initial_dir: *const u16,
+        title: *const u16,
+        flags: u32,
+        file_offset: u16,
+        file_extension: u16,
+        def_ext: *const u16,
+        cust_data: isize,
+        hook: *mut c_void,
+        template_name: *const u16,
+        reserved_ptr: *mut c_void,
+        reserved: u32,
+        flags_ex: u32,
+    }
+    #[link(name = "comdlg32")]
+    extern "system" {
+        fn GetSaveFileNameW(ofn: *mut OpenFileNameW) -> i32;
+        fn CommDlgExtendedError() -> u32;
+    }
+    #[link(name = "ole32")]
+    extern "system" {
+        fn CoInitializeEx(reserved: *mut c_void, coinit: u32) -> i32;
+        fn CoUninitialize();
+    }
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetForegroundWindow() -> *mut c_void;
+    }
+    const OFN_OVERWRITEPROMPT: u32 = 0x2;
+    const OFN_NOCHANGEDIR: u32 = 0x8;
+    const OFN_PATHMUSTEXIST: u32 = 0x800;
+    const OFN_EXPLORER: u32 = 0x80000;
+    const COINIT_APARTMENTTHREADED: u32 = 0x2;
+
+    let wide = |s: &str| -> Vec<u16> { s.encode_utf16().chain(std::iter::once(0)).collect() };
+    let (filter, ext) = match format {
+        Format::Csv => ("CSV (*.csv)\0*.csv\0", "csv"),
+        Format::Ndjson => ("NDJSON (*.ndjson)\0*.ndjson\0", "ndjson"),
+    };
+    // 过滤器为成对的 \0 分隔字符串，以两个 \0 结尾
+    let filter = wide(&format!("{}All files (*.*)\0*.*\0", filter));
+    let ext = wide(ext);
+    let mut file = vec![0u16; 1024];
+    for (dst, src) in file.iter_mut().zip(default_name.encode_utf16().take(1023)) {
+        *dst = src;
+    }
+    // SAFETY: 结构体字段均为整数或指针，全零是合法初值；各缓冲区在调用期间有效
+    let ok = unsafe {
+        let mut ofn: OpenFileNameW = std::mem::zeroed();
+        ofn.struct_size = std::mem::size_of::<OpenFileNameW>() as u32;
+        ofn.owner = GetForegroundWindow();
+        ofn.filter = filter.as_ptr();
+        ofn.filter_index = 1;
+        ofn.file = file.as_mut_ptr();
+        ofn.max_file = file.len() as u32;
+        ofn.def_ext = ext.as_ptr();
+        ofn.flags = OFN_OVERWRITEPROMPT | OFN_NOCHANGEDIR | OFN_PATHMUSTEXIST | OFN_EXPLORER;
+        let hr = CoInitializeEx(std::ptr::null_mut(), COINIT_APARTMENTTHREADED);
+        let ok = GetSaveFileNameW(&mut ofn);
+        if hr >= 0 {
+            CoUninitialize();
+        }
+        ok
+    };
+    if ok == 0 {
+        // SAFETY: 无参数，读取本线程最近一次对话框错误
+        let err = unsafe { CommDlgExtendedError() };
+        return if err == 0 { Ok(None) } else { Err(format!("save dialog failed: {:#x}", err)) };
+    }
+    let len = file.iter().position(|c| *c == 0).unwrap_or(file.len());
+    Ok(Some(PathBuf::from(std::ffi::OsString::from_wide(&file[..len]))))
+}
+
+/// 没有系统对话框时写入应用数据目录下的 exports 目录，已存在的文件不覆盖
+#[cfg(not(windows))]
+fn choose_path(app: &tauri::AppHandle, default_name: &str, _format: Format) -> Result<Option<PathBuf>, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join(EXPORT_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {}", dir.display(), e))?;
+    let path = dir.join(default_name);
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    Ok(Some(path))
+}
+
+/// 分段的结束时间（含）；分桶时对齐到桶边界
+pub fn chunk_end(start: i64, chunk_ms: i64, bucket: Option<i64>) -> i64 {
+    match bucket {
+        Some(b) => {
+            let e = (start - 1 + chunk_ms.max(b)).div_euclid(b) * b;
+            if e < start {
+                bucket_end(start, b)
+            } else {
+                e
+            }
+        }
+        None => start + chunk_ms.max(1) - 1,
+    }
+}
+
+fn fetch(p: &ExportParams, from: i64, to: i64) -> Result<Vec<Value>, String> {
+    let result = match p.source {
+        Source::Service => {
+            let mut params = serde_json::json!({ "from_ts": from, "to_ts": to });
+            for (k, v) in [("modules", serde_json::json!(p.modules)), ("step_ms", serde_json::json!(p.step_ms)), ("agg", serde_json::json!(p.agg))] {
+                if !v.is_null() {
+                    params[k] = v;
+                }
+            }
+            let mut result = crate::call_over_named_pipe("query_history", Some(params)).map_err(|e| e.to_string())?;
+            crate::postprocess_history(&mut result);
+            result
+        }
+        Source::Local => {
+            let path = crate::localstore::db_path()?;
+            let store = Store::open_readonly(&path).map_err(|e| e.to_string())?;
+            let hp = HistoryParams { from_ts: from, to_ts: to, modules: p.modules.clone(), step_ms: p.step_ms, agg: p.agg.clone(), ..Default::default() };
+            let mut result = store.query(&hp, crate::now_millis() as i64).map_err(|e| e.to_string())?;
+            crate::postprocess_history(&mut result);
+            result
+        }
+    };
+    if result.get("ok").and_then(|v| v.as_bool()) == Some(false) {
+        return Err(format!("query_history failed: {}", result));
+    }
+    match result.get("items") {
+        Some(Value::Array(items)) => Ok(items.clone()),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn partial_paths(target: &std::path::Path) -> (PathBuf, PathBuf) {
+    let t = target.display();
+    (PathBuf::from(format!("{}.partial", t)), PathBuf::from(format!("{}.rows.partial", t)))
+}
+
+fn run(app: &tauri::AppHandle, p: &ExportParams, target: &std::path::Path, progress: &mut ExportProgress) -> Result<(), String> {
+    let now = crate::now_millis() as i64;
+    let from = p.from_ts;
+    let to = if p.to_ts <= 0 || p.to_ts < from { now } else { p.to_ts };
+    let bucket = p.bucket_ms();
+    let chunk_ms = p.chunk_ms.filter(|c| *c > 0).unwrap_or(DEFAULT_CHUNK_MS);
+
+    let (tmp, staging) = partial_paths(target);
+    // CSV 未指定列时先暂存为 NDJSON，收集全部列后再转写
+    let csv_auto = p.format == Format::Csv && p.columns.as_ref().is_none_or(|c| c.is_empty());
+    let (first, format) = if csv_auto { (&staging, Format::Ndjson) } else { (&tmp, p.format) };
+    let file = File::create(first).map_err(|e| format!("create {}: {}", first.display(), e))?;
+    let mut w = ExportWriter::new(BufWriter::new(file), format, p.columns.clone());
+    let mut seen = BTreeSet::new();
+    let mut last_ts = i64::MIN;
+    let mut start = from;
+    while start <= to {
+        let end = chunk_end(start, chunk_ms, bucket).min(to);
+        let hi = bucket.map_or(end, |b| bucket_end(end, b));
+        // 丢弃超出分段的项（服务端无数据时会补当前即时值）与跨段重复的 ts
+        let items: Vec<Value> = fetch(p, start, end)?
+            .into_iter()
+            .filter(|it| it.get("ts").and_then(|t| t.as_i64()).is_some_and(|ts| ts >= start && ts <= hi && ts > last_ts))
+            .collect();
+        if let Some(ts) = items.last().and_then(|it| it.get("ts")).and_then(|t| t.as_i64()) {
+            last_ts = ts;
+        }
+        if csv_auto {
+            seen.extend(collect_columns(&items));
+        }
+        w.write_items(&items).map_err(|e| e.to_string())?;
+        progress.rows = w.rows;
+        progress.bytes = w.bytes;
+        progress.progress = if to > from { ((end - from) as f64 / (to - from) as f64).min(1.0) } else { 1.0 };
+        let _ = app.emit("export_progress", &*progress);
+        start = end + 1;
+    }
+    w.finish().map_err(|e| e.to_string())?;
+    if csv_auto {
+        let reader = BufReader::new(File::open(&staging).map_err(|e| e.to_string())?);
+        let file = File::create(&tmp).map_err(|e| format!("create {}: {}", tmp.display(), e))?;
+        let mut csv = ExportWriter::new(BufWriter::new(file), Format::Csv, Some(csv_columns(&seen)));
+        for line in reader.lines() {
+            let item: Value = serde_json::from_str(&line.map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+            csv.write_items(std::slice::from_ref(&item)).map_err(|e| e.to_string())?;
+        }
+        progress.bytes = csv.bytes;
+        csv.finish().map_err(|e| e.to_string())?;
+        let _ = std::fs::remove_file(&staging);
+    }
+    std::fs::rename(&tmp, target).map_err(|e| format!("rename to {}: {}", target.display(), e))?;
+    Ok(())
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 弹出保存对话框选择导出文件；返回选定的路径，用户取消时为 null
+#[tauri::command]
+pub async fn pick_export_path(app: tauri::AppHandle, default_name: Option<String>, format: Option<Format>) -> Result<Option<String>, String> {
+    let format = format.unwrap_or_default();
+    let default_name = default_name.unwrap_or_else(|| {
+        let ext = if format == Format::Csv { "csv" } else { "ndjson" };
+        format!("history-{}.{}", crate::now_millis(), ext)
+    });
+    validate_file_name(&default_name)?;
+    // 对话框需要自己的 STA 线程
+    let picked = tauri::async_runtime::spawn_blocking(move || {
+        std::thread::spawn(move || choose_path(&app, &default_name, format)).join().map_err(|_| "save dialog panicked".to_string())?
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))??;
+    Ok(picked.map(|p| {
+        let s = p.display().to_string();
+        grant(p);
+        s
+    }))
+}
+
+/// 导出到 params.path（须为 pick_export_path 返回且未用过的路径）；返回最终进度，期间发送 export_progress 事件
+#[tauri::command]
+pub async fn export_history(app: tauri::AppHandle, params: ExportParams) -> Result<ExportProgress, String> {
+    let target = PathBuf::from(&params.path);
+    if !take_grant(&target) {
+        return Err(format!("export path was not chosen in the save dialog: {}", params.path));
+    }
+    let export_id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut progress = ExportProgress { export_id, path: params.path.clone(), ..Default::default() };
+        let result = run(&app, &params, &target, &mut progress);
+        progress.done = true;
+        if let Err(e) = &result {
+            crate::log_line("ERROR", &format!("export {} failed: {}", export_id, e));
+            let (tmp, staging) = partial_paths(&target);
+            let _ = std::fs::remove_file(tmp);
+            let _ = std::fs::remove_file(staging);
+            progress.error = Some(e.clone());
+        }
+        let _ = app.emit("export_progress", &progress);
+        result.map(|_| progress)
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(ts: i64) -> Value {
+        json!({ "ts": ts, "disk": { "totals": { "read_bytes_per_sec": 1.5 }, "devices": [1, 2] }, "cpu": { "usage_percent": 3 }, "power": null })
+    }
+
+    #[test]
+    fn csv_uses_stable_dotted_columns() {
+        let mut w = ExportWriter::new(Vec::new(), Format::Csv, None);
+        w.write_items(&[item(1), json!({ "ts": 2, "cpu": { "usage_percent": 4, "extra": 1 } })]).unwrap();
+        let out = String::from_utf8(w.finish().unwrap()).unwrap();
+        // 表头取全部行的字段并集，第二行新出现的 cpu.extra 不会被丢弃
+        assert_eq!(
+            out,
+            "ts,cpu.extra,cpu.usage_percent,disk.devices,disk.totals.read_bytes_per_sec,power\n1,,3,\"[1,2]\",1.5,\n2,1,4,,,\n"
+        );
+    }
+
+    #[test]
+    fn only_dialog_paths_are_writable_once() {
+        assert!(validate_file_name("history-2024.csv").is_ok());
+        for bad in ["", "../x.csv", "a/b.csv", "a\\b.csv", "C:x.csv", ".hidden", ".."] {
+            assert!(validate_file_name(bad).is_err(), "{}", bad);
+        }
+        let picked = std::env::temp_dir().join("picked-export.csv");
+        assert!(!take_grant(&picked));
+        grant(picked.clone());
+        assert!(take_grant(&picked));
+        // 每次选择只能导出一次
+        assert!(!take_grant(&picked));
+    }
+
+    #[test]
+    fn selected_columns_for_csv_and_ndjson() {
+        let cols = Some(vec!["ts".to_string(), "disk.totals.read_bytes_per_sec".to_string()]);
+        let mut w = ExportWriter::new(Vec::new(), Format::Ndjson, cols.clone());
+        w.write_items(&[item(7)]).unwrap();
+        assert_eq!(
+            String::from_utf8(w.finish().unwrap()).unwrap(),
+            "{\"disk\":{\"totals\":{\"read_bytes_per_sec\":1.5}},\"ts\":7}\n"
+        );
+        assert_eq!(csv_field(&json!("a,\"b\"")), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn chunks_align_to_bucket_boundaries() {
+        assert_eq!(chunk_end(0, 1_000, None), 999);
+        assert_eq!(chunk_end(12_345, 60_000, Some(10_000)), 70_000);
+        assert_eq!(chunk_end(70_001, 60_000, Some(10_000)), 130_000);
+        assert_eq!(chunk_end(5, 1_000, Some(10_000)), 10_000);
+    }
+}
@@ -0,0 +1,84 @@
+// 事件桥帧的零拷贝解析
+// 只读取 method/id，params 以 RawValue 借用原始字节切片，直接转发给前端而不构建 Value 树；
+// 需要检查内容的通知（metrics/state 等）再按需从切片解析。
+
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::borrow::Cow;
+
+#[derive(Debug, Deserialize)]
+pub struct Envelope<'a> {
+    /// 方法名一般不含转义，Cow 在含转义时才分配
+    #[serde(borrow, default, deserialize_with = "borrow_opt_str")]
+    pub method: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    pub id: Option<&'a RawValue>,
+    #[serde(borrow, default)]
+    pub params: Option<&'a RawValue>,
+}
+
+// serde 只对直接的 Cow<str> 字段做借用，包在 Option 里时需要手动转一层
+fn borrow_opt_str<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<Cow<'de, str>>, D::Error> {
+    #[derive(Deserialize)]
+    struct Borrowed<'a>(#[serde(borrow)] Cow<'a, str>);
+    Ok(Option::<Borrowed>::deserialize(d)?.map(|b| b.0))
+}
+
+impl Envelope<'_> {
+    /// 无 id 的带 method 帧为通知
+    pub fn notification(&self) -> Option<&str> {
+        match (&self.method, self.id) {
+            (Some(m), None) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_envelope(body: &[u8]) -> serde_json::Result<Envelope<'_>> {
+    serde_json::from_slice(body)
+}
+
+/// 兼容 StreamJsonRpc 的位置参数：单元素数组解包为该元素，其余原样返回
+pub fn unwrap_params(params: &RawValue) -> &RawValue {
+    if !params.get().starts_with('[') {
+        return params;
+    }
+    // 一元组只接受恰好一个元素的数组，多于或少于一个时解析失败，原样返回
+    match serde_json::from_str::<(&RawValue,)>(params.get()) {
+        Ok((inner,)) => inner,
+        Err(_) => params,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_method_and_borrows_params() {
+        let body = br#"{"jsonrpc":"2.0","method":"metrics","params":[{"ts":1,"cpu":{"usage_percent":3.5}}]}"#;
+        let env = parse_envelope(body).unwrap();
+        assert_eq!(env.notification(), Some("metrics"));
+        let payload = unwrap_params(env.params.unwrap());
+        assert_eq!(payload.get(), r#"{"ts":1,"cpu":{"usage_percent":3.5}}"#);
+        assert!(matches!(env.method, Some(Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn keeps_multi_element_and_object_params() {
+        let body = br#"{"method":"x","params":[1,2]}"#;
+        let env = parse_envelope(body).unwrap();
+        assert_eq!(unwrap_params(env.params.unwrap()).get(), "[1,2]");
+
+        let body = br#"{"method":"state","params":{"phase":"start"}}"#;
+        let env = parse_envelope(body).unwrap();
+        assert_eq!(unwrap_params(env.params.unwrap()).get(), r#"{"phase":"start"}"#);
+    }
+
+    #[test]
+    fn responses_are_not_notifications() {
+        let env = parse_envelope(br#"{"jsonrpc":"2.0","id":7,"result":{"ok":true}}"#).unwrap();
+        assert!(env.notification().is_none());
+        assert_eq!(env.id.unwrap().get(), "7");
+    }
+}
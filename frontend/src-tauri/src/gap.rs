@@ -0,0 +1,203 @@
+// metrics 序号断档检测与自动回填
+// 服务端每个连接的 seq 以连接建立时的毫秒时间戳为基数单调递增：
+// - 同一连接内 seq 跳号视为丢帧（gap）
+// - 重连或 seq 回退视为重置（reset），缺失条数未知，只能按时间区间回填
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+/// 可回填的最大时间跨度（超出则只发 metrics_gap，不再查询历史）
+pub const BACKFILL_WINDOW_MS: u64 = 10 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GapEvent {
+    /// "gap" | "reset"
+    pub kind: &'static str,
+    pub last_seq: i64,
+    pub seq: i64,
+    /// 丢失帧数；reset 时无法推算，为 None
+    pub missing: Option<i64>,
+    /// 断档前最后一帧的 ts
+    pub from_ts: u64,
+    /// 断档后第一帧的 ts
+    pub to_ts: u64,
+    /// 是否会通过 query_history 回填
+    pub backfill: bool,
+}
+
+pub struct GapDetector {
+    last: Option<(i64, u64)>,
+    reconnected: bool,
+}
+
+impl GapDetector {
+    pub const fn new() -> Self {
+        Self { last: None, reconnected: false }
+    }
+
+    /// 桥接重新握手后调用：下一帧的 seq 来自新的连接基数
+    pub fn mark_reconnect(&mut self) {
+        self.reconnected = true;
+    }
+
+    pub fn observe(&mut self, seq: i64, ts: u64) -> Option<GapEvent> {
+        let reconnected = std::mem::take(&mut self.reconnected);
+        let prev = self.last.replace((seq, ts));
+        let (last_seq, last_ts) = prev?;
+        let (kind, missing) = if reconnected || seq <= last_seq {
+            ("reset", None)
+        } else if seq > last_seq + 1 {
+            ("gap", Some(seq - last_seq - 1))
+        } else {
+            return None;
+        };
+        let span = ts.saturating_sub(last_ts);
+        Some(GapEvent {
+            kind,
+            last_seq,
+            seq,
+            missing,
+            from_ts: last_ts,
+            to_ts: ts,
+            // 开区间 (from_ts, to_ts) 内至少要有 1ms 才值得查询
+            backfill: span > 1 && span <= BACKFILL_WINDOW_MS,
+        })
+    }
+}
+
+static DETECTOR: Mutex<GapDetector> = Mutex::new(GapDetector::new());
+
+pub fn mark_reconnect() {
+    if let Ok(mut d) = DETECTOR.lock() {
+        d.mark_reconnect();
+    }
+}
+
+/// 回放开始/定位/结束时调用：前后两段帧不属于同一序列，丢弃上一帧且不视为断档
+pub fn reset() {
+    if let Ok(mut d) = DETECTOR.lock() {
+        *d = GapDetector::new();
+    }
+}
+
+/// 检查一帧 metrics；发现断档时发出 metrics_gap，并在窗口内异步回填 metrics_backfill
+pub fn observe_metrics(app: &tauri::AppHandle, payload: &Value) {
+    let seq = payload.get("seq").and_then(|v| v.as_i64());
+    let ts = payload.get("ts").and_then(|v| v.as_u64());
+    let (Some(seq), Some(ts)) = (seq, ts) else { return };
+    let gap = match DETECTOR.lock() {
+        Ok(mut d) => d.observe(seq, ts),
+        Err(_) => None,
+    };
+    let Some(gap) = gap else { return };
+    crate::log_line("WARN", &format!(
+        "metrics {} detected: seq {} -> {}, ts {} -> {}",
+        gap.kind, gap.last_seq, gap.seq, gap.from_ts, gap.to_ts
+    ));
+    let _ = app.emit("metrics_gap", &gap);
+    if !gap.backfill {
+        return;
+    }
+    // 回填模块与当前帧保持一致（跳过 ts/seq 与桥接追加的 derived）
+    let modules: Vec<String> = payload
+        .as_object()
+        .map(|m| m.keys().filter(|k| !matches!(k.as_str(), "ts" | "seq" | "derived")).cloned().collect())
+        .unwrap_or_default();
+    let app = app.clone();
+    std::thread::spawn(move || backfill(&app, &gap, modules));
+}
+
+fn backfill(app: &tauri::AppHandle, gap: &GapEvent, modules: Vec<String>) {
+    // query_history 为闭区间，这里只取断档两端之间的数据
+    let params = serde_json::json!({
+        "from_ts": gap.from_ts + 1,
+        "to_ts": gap.to_ts - 1,
+        "modules": modules,
+    });
+    match crate::call_over_named_pipe("query_history", Some(params)) {
+        Ok(result) => {
+            let items: Vec<Value> = result
+                .get("items")
+                .and_then(|v| v.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter(|it| {
+                            let t = it.get("ts").and_then(|t| t.as_u64()).unwrap_or(0);
+                            t > gap.from_ts && t < gap.to_ts
+                        })
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default();
+            // 与 rpc_call 的 query_history 相同的后处理，回填帧与实时帧字段一致
+            let mut result = serde_json::json!({ "ok": true, "items": items });
+            crate::postprocess_history(&mut result);
+            let _ = app.emit(
+                "metrics_backfill",
+                serde_json::json!({
+                    "kind": gap.kind,
+                    "from_ts": gap.from_ts,
+                    "to_ts": gap.to_ts,
+                    "items": result["items"].take(),
+                }),
+            );
+        }
+        Err(e) => {
+            crate::log_line("ERROR", &format!("metrics backfill failed: {}", e));
+            let _ = app.emit(
+                "bridge_error",
+                serde_json::json!({
+                    "stage": "backfill",
+                    "error": e.to_string()
+                }),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_frames_have_no_gap() {
+        let mut d = GapDetector::new();
+        assert_eq!(d.observe(100, 1_000), None);
+        assert_eq!(d.observe(101, 2_000), None);
+        assert_eq!(d.observe(102, 3_000), None);
+    }
+
+    #[test]
+    fn skipped_seq_is_a_gap() {
+        let mut d = GapDetector::new();
+        d.observe(100, 1_000);
+        let g = d.observe(104, 5_000).expect("gap");
+        assert_eq!(g.kind, "gap");
+        assert_eq!(g.missing, Some(3));
+        assert_eq!((g.from_ts, g.to_ts), (1_000, 5_000));
+        assert!(g.backfill);
+    }
+
+    #[test]
+    fn reconnect_or_rewind_is_a_reset() {
+        let mut d = GapDetector::new();
+        d.observe(100, 1_000);
+        d.mark_reconnect();
+        let g = d.observe(200_000, 3_000).expect("reset");
+        assert_eq!(g.kind, "reset");
+        assert_eq!(g.missing, None);
+
+        let g = d.observe(5, 4_000).expect("rewind");
+        assert_eq!(g.kind, "reset");
+    }
+
+    #[test]
+    fn long_outage_is_not_backfilled() {
+        let mut d = GapDetector::new();
+        d.observe(1, 1_000);
+        let g = d.observe(3, 1_000 + BACKFILL_WINDOW_MS + 1).expect("gap");
+        assert!(!g.backfill);
+    }
+}
@@ -0,0 +1,322 @@
+// query_history 区间合并缓存
+// 以 模块集合 + 聚合粒度（agg / step_ms / raw）为键，记录已覆盖的连续区间；重叠查询只向服务端补取缺失的头部/尾部并合并。
+// to_ts = 0 在桥接侧统一解析为当前时间后再转发。最近 SETTLE_MS 内的数据视为未稳定（采集/落盘滞后），
+// 不计入已覆盖区间，下次查询会重新补取并覆盖；分桶查询的覆盖边界对齐到桶边界，补取的桶不会与已缓存的桶交错。
+
+use crate::localstore::bucket_end;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const MAX_ENTRIES: usize = 8;
+const MAX_ITEMS_PER_ENTRY: usize = 20_000;
+const SETTLE_MS: i64 = 2_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Full,
+    Head,
+    Tail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub from: i64,
+    pub to: i64,
+    pub side: Side,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CacheStats {
+    pub requests: u64,
+    /// 完全命中，未访问服务端
+    pub hits: u64,
+    /// 部分命中，只补取头部/尾部
+    pub partial: u64,
+    pub misses: u64,
+    pub fetched_segments: u64,
+    pub fetched_items: u64,
+    pub served_items: u64,
+    /// 返回的点中来自缓存的数量
+    pub cached_items: u64,
+}
+
+struct Entry {
+    from: i64,
+    /// 已稳定覆盖的上界（含）；之后的缓存项在下次补尾时被替换
+    to: i64,
+    items: BTreeMap<i64, Value>,
+    last_used: u64,
+}
+
+pub struct HistoryCache {
+    entries: BTreeMap<String, Entry>,
+    stats: CacheStats,
+    tick: u64,
+}
+
+/// 缓存键与分桶粒度；agg 为 10s/1m 时优先，其次 step_ms，否则为原始
+pub fn cache_key(params: &Value) -> (String, Option<i64>) {
+    let mut modules: Vec<String> = params
+        .get("modules")
+        .and_then(|m| m.as_array())
+        .map(|a| a.iter().filter_map(|m| m.as_str().map(str::to_string)).collect())
+        .filter(|m: &Vec<String>| !m.is_empty())
+        .unwrap_or_else(|| vec!["cpu".into(), "memory".into()]);
+    modules.sort();
+    modules.dedup();
+    let agg = params.get("agg").and_then(|a| a.as_str()).map(|a| a.to_ascii_lowercase());
+    let step = params.get("step_ms").and_then(|s| s.as_i64()).filter(|s| *s > 0);
+    let (level, bucket) = match (agg.as_deref(), step) {
+        (Some("10s"), _) => ("agg=10s".to_string(), Some(10_000)),
+        (Some("1m"), _) => ("agg=1m".to_string(), Some(60_000)),
+        (_, Some(s)) => (format!("step={}", s), Some(s)),
+        _ => ("raw".to_string(), None),
+    };
+    (format!("{}|{}", modules.join(","), level), bucket)
+}
+
+/// 返回项 ts 的有效范围：分桶时 ts 为桶结束时间
+fn ts_range(from: i64, to: i64, bucket: Option<i64>) -> (i64, i64) {
+    match bucket {
+        Some(b) => (bucket_end(from, b), bucket_end(to, b)),
+        None => (from, to),
+    }
+}
+
+impl HistoryCache {
+    pub const fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            stats: CacheStats {
+                requests: 0,
+                hits: 0,
+                partial: 0,
+                misses: 0,
+                fetched_segments: 0,
+                fetched_items: 0,
+                served_items: 0,
+                cached_items: 0,
+            },
+            tick: 0,
+        }
+    }
+
+    /// 计算需要向服务端请求的区间
+    pub fn plan(&mut self, key: &str, from: i64, to: i64) -> Vec<Segment> {
+        self.stats.requests += 1;
+        let segs = match self.entries.get(key) {
+            Some(e) if from <= e.to.saturating_add(1) && to >= e.from => {
+                let mut segs = Vec::new();
+                if from < e.from {
+                    segs.push(Segment { from, to: e.from - 1, side: Side::Head });
+                }
+                if to > e.to {
+                    segs.push(Segment { from: e.to + 1, to, side: Side::Tail });
+                }
+                segs
+            }
+            _ => vec![Segment { from, to, side: Side::Full }],
+        };
+        match segs.as_slice() {
+            [] => self.stats.hits += 1,
+            [Segment { side: Side::Full, .. }] => self.stats.misses += 1,
+            _ => self.stats.partial += 1,
+        }
+        self.stats.fetched_segments += segs.len() as u64;
+        segs
+    }
+
+    /// 合并补取结果；settled 为已稳定的时间上界
+    pub fn merge(&mut self, key: &str, bucket: Option<i64>, settled: i64, fetched: Vec<(Segment, Vec<Value>)>) {
+        let settled = bucket.map_or(settled, |b| settled.div_euclid(b) * b);
+        for (seg, items) in fetched {
+            self.stats.fetched_items += items.len() as u64;
+            let (lo, hi) = ts_range(seg.from, seg.to, bucket);
+            let items = items
+                .into_iter()
+                .filter_map(|it| it.get("ts").and_then(|t| t.as_i64()).map(|ts| (ts, it)))
+                .filter(|(ts, _)| (lo..=hi).contains(ts));
+            let covered_to = seg.to.min(settled).max(seg.from - 1);
+            if seg.side == Side::Full || !self.entries.contains_key(key) {
+                self.entries.insert(
+                    key.to_string(),
+                    Entry { from: seg.from, to: covered_to, items: items.collect(), last_used: 0 },
+                );
+                continue;
+            }
+            let Some(e) = self.entries.get_mut(key) else { continue };
+            match seg.side {
+                Side::Head => {
+                    // 边界桶以已缓存的为准（包含更晚的记录）
+                    for (ts, it) in items {
+                        e.items.entry(ts).or_insert(it);
+                    }
+                    e.from = e.from.min(seg.from);
+                }
+                _ => {
+                    // 未稳定的尾部整体替换
+                    let _ = e.items.split_off(&(e.to + 1));
+                    e.items.extend(items);
+                    e.to = e.to.max(covered_to);
+                }
+            }
+        }
+        if let Some(e) = self.entries.get_mut(key) {
+            while e.items.len() > MAX_ITEMS_PER_ENTRY {
+                e.items.pop_first();
+            }
+            if e.items.len() == MAX_ITEMS_PER_ENTRY {
+                if let Some((&first, _)) = e.items.first_key_value() {
+                    e.from = e.from.max(first);
+                }
+            }
+        }
+        while self.entries.len() > MAX_ENTRIES {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else { break };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    /// 读取区间内的缓存项；fetched 为本次补取的项数，用于统计缓存贡献
+    pub fn read(&mut self, key: &str, bucket: Option<i64>, from: i64, to: i64, fetched: usize) -> Vec<Value> {
+        self.tick += 1;
+        let Some(e) = self.entries.get_mut(key) else { return Vec::new() };
+        e.last_used = self.tick;
+        let (lo, hi) = ts_range(from, to, bucket);
+        let items: Vec<Value> = e.items.range(lo..=hi).map(|(_, v)| v.clone()).collect();
+        self.stats.served_items += items.len() as u64;
+        self.stats.cached_items += items.len().saturating_sub(fetched) as u64;
+        items
+    }
+}
+
+static CACHE: Mutex<HistoryCache> = Mutex::new(HistoryCache::new());
+
+/// 经缓存执行 query_history；fetch 执行实际的服务端调用。服务端返回 ok=false 时原样返回且不缓存
+pub fn query_history<F>(params: Option<Value>, now: i64, fetch: F) -> anyhow::Result<Value>
+where
+    F: Fn(Value) -> anyhow::Result<Value>,
+{
+    let params = params.unwrap_or_else(|| serde_json::json!({}));
+    let from = params.get("from_ts").and_then(|v| v.as_i64()).unwrap_or(0);
+    let to = params.get("to_ts").and_then(|v| v.as_i64()).unwrap_or(0);
+    // 与服务端一致：to_ts 为 0 或早于 from_ts 时取当前时间
+    let to = if to <= 0 || to < from { now } else { to };
+    let (key, bucket) = cache_key(&params);
+
+    let segs = CACHE.lock().map_err(|_| anyhow::anyhow!("history cache lock poisoned"))?.plan(&key, from, to);
+    let mut fetched = Vec::with_capacity(segs.len());
+    let mut fetched_count = 0;
+    for seg in segs {
+        let mut p = params.clone();
+        if let Some(m) = p.as_object_mut() {
+            m.insert("from_ts".into(), serde_json::json!(seg.from));
+            m.insert("to_ts".into(), serde_json::json!(seg.to));
+        }
+        let mut res = fetch(p)?;
+        if res.get("ok").and_then(|v| v.as_bool()) == Some(false) {
+            return Ok(res);
+        }
+        let items = match res.get_mut("items") {
+            Some(Value::Array(items)) => std::mem::take(items),
+            _ => Vec::new(),
+        };
+        fetched_count += items.len();
+        fetched.push((seg, items));
+    }
+
+    let mut cache = CACHE.lock().map_err(|_| anyhow::anyhow!("history cache lock poisoned"))?;
+    cache.merge(&key, bucket, now - SETTLE_MS, fetched);
+    let items = cache.read(&key, bucket, from, to, fetched_count);
+    Ok(serde_json::json!({ "ok": true, "items": items }))
+}
+
+#[tauri::command]
+pub fn history_cache_stats() -> Result<Value, String> {
+    let c = CACHE.lock().map_err(|_| "history cache lock poisoned".to_string())?;
+    let ratio = |n: u64, d: u64| if d == 0 { 0.0 } else { n as f64 / d as f64 };
+    let entries: Vec<Value> = c
+        .entries
+        .iter()
+        .map(|(k, e)| serde_json::json!({ "key": k, "from_ts": e.from, "to_ts": e.to, "items": e.items.len() }))
+        .collect();
+    Ok(serde_json::json!({
+        "stats": c.stats,
+        "hit_rate": ratio(c.stats.hits, c.stats.requests),
+        "item_hit_rate": ratio(c.stats.cached_items, c.stats.served_items),
+        "entries": entries,
+    }))
+}
+
+#[tauri::command]
+pub fn history_cache_clear() -> Result<(), String> {
+    let mut c = CACHE.lock().map_err(|_| "history cache lock poisoned".to_string())?;
+    c.entries.clear();
+    c.stats = CacheStats::default();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn items(range: std::ops::RangeInclusive<i64>, step: i64) -> Vec<Value> {
+        range.step_by(step as usize).map(|ts| json!({ "ts": ts, "cpu": { "usage_percent": ts } })).collect()
+    }
+
+    #[test]
+    fn fetches_only_missing_head_and_tail() {
+        let mut c = HistoryCache::new();
+        let seg = c.plan("k", 10_000, 20_000);
+        assert_eq!(seg, vec![Segment { from: 10_000, to: 20_000, side: Side::Full }]);
+        c.merge("k", None, 30_000, vec![(seg[0], items(10_000..=20_000, 1_000))]);
+
+        assert!(c.plan("k", 12_000, 18_000).is_empty());
+        let segs = c.plan("k", 5_000, 25_000);
+        assert_eq!(
+            segs,
+            vec![Segment { from: 5_000, to: 9_999, side: Side::Head }, Segment { from: 20_001, to: 25_000, side: Side::Tail }]
+        );
+        c.merge("k", None, 30_000, vec![(segs[0], items(5_000..=9_000, 1_000)), (segs[1], items(21_000..=25_000, 1_000))]);
+        let got = c.read("k", None, 5_000, 25_000, 10);
+        assert_eq!(got.len(), 21);
+        assert_eq!((c.stats.hits, c.stats.partial, c.stats.misses), (1, 1, 1));
+        assert_eq!(c.stats.cached_items, 11);
+        // 不相交的区间重新全量获取
+        assert_eq!(c.plan("k", 100_000, 110_000)[0].side, Side::Full);
+    }
+
+    #[test]
+    fn unsettled_tail_is_refetched_and_replaced() {
+        let mut c = HistoryCache::new();
+        let seg = c.plan("k", 0, 60_000)[0];
+        // 稳定上界 45_000 对齐到 10s 桶 => 40_000
+        c.merge("k", Some(10_000), 45_000, vec![(seg, vec![json!({ "ts": 50_000, "v": "partial" }), json!({ "ts": 40_000, "v": 1 })])]);
+        let segs = c.plan("k", 0, 60_000);
+        assert_eq!(segs, vec![Segment { from: 40_001, to: 60_000, side: Side::Tail }]);
+        c.merge("k", Some(10_000), 70_000, vec![(segs[0], vec![json!({ "ts": 60_000, "v": 6 })])]);
+        let got = c.read("k", Some(10_000), 0, 60_000, 1);
+        assert_eq!(got, vec![json!({ "ts": 40_000, "v": 1 }), json!({ "ts": 60_000, "v": 6 })]);
+    }
+
+    #[test]
+    fn resolves_to_ts_zero_and_keys_by_level() {
+        let (k1, b1) = cache_key(&json!({ "modules": ["memory", "cpu"], "step_ms": 500 }));
+        let (k2, _) = cache_key(&json!({ "step_ms": 500 }));
+        assert_eq!((k1.as_str(), b1), ("cpu,memory|step=500", Some(500)));
+        assert_eq!(k1, k2);
+        assert_eq!(cache_key(&json!({ "agg": "1m", "step_ms": 500 })).1, Some(60_000));
+
+        let calls = Mutex::new(Vec::new());
+        let fetch = |p: Value| {
+            calls.lock().unwrap().push((p["from_ts"].as_i64().unwrap(), p["to_ts"].as_i64().unwrap()));
+            Ok::<_, anyhow::Error>(json!({ "ok": true, "items": [] }))
+        };
+        let params = json!({ "from_ts": 1_000, "to_ts": 0, "modules": ["gpu"] });
+        query_history(Some(params), 9_000, fetch).unwrap();
+        assert_eq!(calls.lock().unwrap().as_slice(), &[(1_000, 9_000)]);
+    }
+}
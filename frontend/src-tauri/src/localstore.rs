@@ -0,0 +1,633 @@
+// 应用内嵌的本地历史库（SQLite，位于应用数据目录 history.db）
+// 事件桥收到的 metrics 按 COLUMNS 表抽取为索引列，连同整帧 JSON（frame 列）由后台线程批量写入，
+// 服务端不可用或重装后 UI 仍有历史可查。
+// 原始查询的模块内容取自 frame 列，任意模块（network/gpu/温度/分卷 IO 等）都可查询；
+// 索引列供分级汇总（rollup.rs）与电池统计使用，旧库中无 frame 的行回退为按列还原。新增列在打开数据库时自动补齐。
+// local_query_history 与服务端 query_history 参数、返回形态一致。
+
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection, OpenFlags};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+const DB_FILE: &str = "history.db";
+/// 写入队列容量，满时丢弃新帧（写盘跟不上时不阻塞事件桥）
+const WRITE_QUEUE: usize = 4_096;
+const BATCH_MAX: usize = 500;
+const FLUSH_INTERVAL_MS: u64 = 1_000;
+/// 分级汇总与过期清理的周期
+const COMPACT_INTERVAL: Duration = Duration::from_secs(60);
+/// 未指定 modules 时与服务端一致，默认 cpu + memory
+const DEFAULT_MODULES: [&str; 2] = ["cpu", "memory"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Real,
+    Text,
+    Bool,
+}
+
+#[derive(Debug)]
+pub struct Column {
+    pub name: &'static str,
+    pub module: &'static str,
+    /// 模块内字段路径
+    pub path: &'static [&'static str],
+    /// 最后一级字段的别名（如 memory 的 total/total_mb）
+    pub alias: Option<&'static str>,
+    pub kind: Kind,
+}
+
+const fn col(name: &'static str, module: &'static str, path: &'static [&'static str], kind: Kind) -> Column {
+    Column { name, module, path, alias: None, kind }
+}
+
+pub const COLUMNS: &[Column] = &[
+    col("cpu_usage_percent", "cpu", &["usage_percent"], Kind::Real),
+    Column { alias: Some("total"), ..col("memory_total_mb", "memory", &["total_mb"], Kind::Real) },
+    Column { alias: Some("used"), ..col("memory_used_mb", "memory", &["used_mb"], Kind::Real) },
+    col("disk_read_bytes_per_sec", "disk", &["read_bytes_per_sec"], Kind::Real),
+    col("disk_write_bytes_per_sec", "disk", &["write_bytes_per_sec"], Kind::Real),
+    col("disk_queue_length", "disk", &["queue_length"], Kind::Real),
+    col("battery_percentage", "power", &["battery", "percentage"], Kind::Real),
+    col("battery_state", "power", &["battery", "state"], Kind::Text),
+    col("battery_ac_line_online", "power", &["battery", "ac_line_online"], Kind::Bool),
+    col("battery_power_w", "power", &["battery", "power_w"], Kind::Real),
+    col("battery_voltage_mv", "power", &["battery", "voltage_mv"], Kind::Real),
+    col("battery_current_ma", "power", &["battery", "current_ma"], Kind::Real),
+    col("battery_cycle_count", "power", &["battery", "cycle_count"], Kind::Real),
+    col("battery_full_charge_capacity_mah", "power", &["battery", "full_charge_capacity_mah"], Kind::Real),
+    col("battery_design_capacity_mah", "power", &["battery", "design_capacity_mah"], Kind::Real),
+    col("battery_condition", "power", &["battery", "condition"], Kind::Text),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub ts: i64,
+    pub seq: Option<i64>,
+    pub values: Vec<SqlValue>,
+    /// 整帧原始 JSON
+    pub frame: Option<String>,
+}
+
+fn lookup<'a>(frame: &'a Value, c: &Column) -> Option<&'a Value> {
+    let mut v = frame.get(c.module)?;
+    let (last, parents) = c.path.split_last()?;
+    for p in parents {
+        v = v.get(p)?;
+    }
+    v.get(last).or_else(|| c.alias.and_then(|a| v.get(a))).filter(|x| !x.is_null())
+}
+
+fn to_sql(v: &Value, kind: Kind) -> SqlValue {
+    let converted = match kind {
+        Kind::Real => v.as_f64().map(SqlValue::Real),
+        Kind::Text => v.as_str().map(|s| SqlValue::Text(s.to_string())),
+        Kind::Bool => v.as_bool().map(|b| SqlValue::Integer(b as i64)),
+    };
+    converted.unwrap_or(SqlValue::Null)
+}
+
+pub(crate) fn from_sql(v: ValueRef<'_>, kind: Kind) -> Value {
+    match (v, kind) {
+        (ValueRef::Integer(i), Kind::Bool) => Value::Bool(i != 0),
+        (ValueRef::Integer(i), _) => serde_json::json!(i),
+        (ValueRef::Real(f), _) => serde_json::json!(f),
+        (ValueRef::Text(t), _) => Value::String(String::from_utf8_lossy(t).into_owned()),
+        _ => Value::Null,
+    }
+}
+
+// 帧中是否有任一模块带数据（非 null、非预热占位）
+fn has_module_data(frame: &Value) -> bool {
+    let Some(obj) = frame.as_object() else { return false };
+    obj.iter().any(|(k, v)| {
+        !matches!(k.as_str(), "ts" | "seq" | "derived")
+            && !v.is_null()
+            && v.get("status").and_then(|s| s.as_str()) != Some("warming_up")
+    })
+}
+
+/// 抽取一帧；无 ts 或没有任何模块数据（如全部模块处于预热）时返回 None。frame 列由调用方填入原始 JSON
+pub fn extract(frame: &Value) -> Option<Row> {
+    let ts = frame.get("ts")?.as_i64()?;
+    let values: Vec<SqlValue> = COLUMNS
+        .iter()
+        .map(|c| lookup(frame, c).map(|v| to_sql(v, c.kind)).unwrap_or(SqlValue::Null))
+        .collect();
+    if values.iter().all(|v| *v == SqlValue::Null) && !has_module_data(frame) {
+        return None;
+    }
+    Some(Row { ts, seq: frame.get("seq").and_then(|s| s.as_i64()), values, frame: None })
+}
+
+fn insert_path(obj: &mut Map<String, Value>, path: &[&str], value: Value) {
+    let Some((last, parents)) = path.split_last() else { return };
+    let mut cur = obj;
+    for p in parents {
+        let next = cur.entry(p.to_string()).or_insert_with(|| Value::Object(Map::new()));
+        let Value::Object(m) = next else { return };
+        cur = m;
+    }
+    cur.insert(last.to_string(), value);
+}
+
+/// 按列还原为 query_history 的 item 形态；所请求但无数据的模块为 null
+pub fn row_to_item(ts: i64, values: &[Value], want: &HashSet<String>) -> Value {
+    let mut item = Map::new();
+    item.insert("ts".into(), serde_json::json!(ts));
+    for (c, v) in COLUMNS.iter().zip(values) {
+        if !want.contains(c.module) {
+            continue;
+        }
+        let slot = item.entry(c.module.to_string()).or_insert(Value::Null);
+        if v.is_null() {
+            continue;
+        }
+        if slot.is_null() {
+            *slot = Value::Object(Map::new());
+        }
+        if let Value::Object(m) = slot {
+            insert_path(m, c.path, v.clone());
+        }
+    }
+    Value::Object(item)
+}
+
+pub fn bucket_end(ts: i64, bucket_ms: i64) -> i64 {
+    ((ts - 1).div_euclid(bucket_ms) + 1) * bucket_ms
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct HistoryParams {
+    #[serde(default)]
+    pub from_ts: i64,
+    #[serde(default)]
+    pub to_ts: i64,
+    pub modules: Option<Vec<String>>,
+    pub step_ms: Option<i64>,
+    pub agg: Option<String>,
+    /// 汇总查询取值：min | max | avg | last（默认 last，与服务端每桶取最后一条一致）
+    pub value: Option<String>,
+    /// 图表降采样（见 downsample.rs）
+    pub downsample: Option<crate::downsample::DownsampleSpec>,
+}
+
+impl HistoryParams {
+    /// to_ts 为 0 或早于 from_ts 时取当前时间，与服务端一致
+    pub fn resolved_to(&self, now: i64) -> i64 {
+        if self.to_ts <= 0 || self.to_ts < self.from_ts {
+            now
+        } else {
+            self.to_ts
+        }
+    }
+
+    pub fn want(&self) -> HashSet<String> {
+        match &self.modules {
+            Some(m) if !m.is_empty() => m.iter().cloned().collect(),
+            _ => DEFAULT_MODULES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// 原始查询按 step_ms 分桶；agg 指定 10s/1m 时改查汇总表（见 rollup.rs）
+    pub fn bucket_ms(&self) -> Option<i64> {
+        self.step_ms.filter(|s| *s > 0)
+    }
+}
+
+pub struct Store {
+    pub(crate) conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::init(conn)
+    }
+
+    pub fn open_readonly(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+        Ok(Self { conn })
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS metrics (ts INTEGER NOT NULL, seq INTEGER NULL);
+             CREATE INDEX IF NOT EXISTS idx_metrics_ts ON metrics(ts);",
+        )?;
+        ensure_columns(&conn, "metrics", COLUMNS.iter().map(|c| (c.name, sql_type(c.kind))))?;
+        ensure_columns(&conn, "metrics", [("frame", "TEXT")])?;
+        crate::rollup::ensure_schema(&conn)?;
+        crate::batteryhealth::ensure_schema(&conn)?;
+        crate::batterysessions::ensure_schema(&conn)?;
+        Ok(Self { conn })
+    }
+
+    pub fn insert_batch(&mut self, rows: &[Row]) -> rusqlite::Result<()> {
+        let names: Vec<&str> = COLUMNS.iter().map(|c| c.name).collect();
+        let sql = format!(
+            "INSERT INTO metrics(ts, seq, frame, {}) VALUES(?1, ?2, ?3, {})",
+            names.join(", "),
+            (4..4 + names.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+        );
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(&sql)?;
+            for r in rows {
+                let head = [
+                    SqlValue::Integer(r.ts),
+                    r.seq.map(SqlValue::Integer).unwrap_or(SqlValue::Null),
+                    r.frame.clone().map(SqlValue::Text).unwrap_or(SqlValue::Null),
+                ];
+                stmt.execute(params_from_iter(head.iter().chain(r.values.iter())))?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// 原始行（升序），每行值按 COLUMNS 顺序转换为 JSON
+    pub fn query_rows(&self, from: i64, to: i64) -> rusqlite::Result<Vec<(i64, Vec<Value>)>> {
+        let names: Vec<&str> = COLUMNS.iter().map(|c| c.name).collect();
+        let sql = format!("SELECT ts, {} FROM metrics WHERE ts >= ?1 AND ts <= ?2 ORDER BY ts ASC", names.join(", "));
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([from, to], |r| {
+            let values = COLUMNS
+                .iter()
+                .enumerate()
+                .map(|(i, c)| r.get_ref(i + 1).map(|v| from_sql(v, c.kind)))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok((r.get::<_, i64>(0)?, values))
+        })?;
+        rows.collect()
+    }
+
+    /// 原始行还原为 item（升序）：所请求模块取自 frame 列，无 frame 的旧行按列还原
+    pub fn query_items(&self, from: i64, to: i64, want: &HashSet<String>) -> rusqlite::Result<Vec<(i64, Value)>> {
+        let names: Vec<&str> = COLUMNS.iter().map(|c| c.name).collect();
+        // 模块名作为 JSON 路径拼入，只接受标识符形式
+        let modules: Vec<&String> = want
+            .iter()
+            .filter(|m| !m.is_empty() && m.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_'))
+            .collect();
+        let extracts: Vec<String> = modules.iter().map(|m| format!("frame -> '$.{}'", m)).collect();
+        let sql = format!(
+            "SELECT ts, frame IS NOT NULL, {}{}{} FROM metrics WHERE ts >= ?1 AND ts <= ?2 ORDER BY ts ASC",
+            names.join(", "),
+            if extracts.is_empty() { "" } else { ", " },
+            extracts.join(", ")
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([from, to], |r| {
+            let ts: i64 = r.get(0)?;
+            let values = COLUMNS
+                .iter()
+                .enumerate()
+                .map(|(i, c)| r.get_ref(i + 2).map(|v| from_sql(v, c.kind)))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut item = row_to_item(ts, &values, want);
+            if r.get::<_, bool>(1)? {
+                let base = 2 + COLUMNS.len();
+                for (i, m) in modules.iter().enumerate() {
+                    let v = r
+                        .get::<_, Option<String>>(base + i)?
+                        .and_then(|t| serde_json::from_str::<Value>(&t).ok())
+                        .unwrap_or(Value::Null);
+                    item[m.as_str()] = v;
+                }
+            }
+            // 所请求但无数据（或无对应列）的模块为 null
+            if let Value::Object(obj) = &mut item {
+                for m in want {
+                    obj.entry(m.clone()).or_insert(Value::Null);
+                }
+            }
+            Ok((ts, item))
+        })?;
+        rows.collect()
+    }
+
+    pub fn query(&self, p: &HistoryParams, now: i64) -> anyhow::Result<Value> {
+        if let Some(tier) = crate::rollup::tier_for(p.agg.as_deref()) {
+            return self.query_rollup(p, tier, now);
+        }
+        let to = p.resolved_to(now);
+        let want = p.want();
+        let mut rows = self.query_items(p.from_ts, to, &want)?;
+        if let Some(bucket) = p.bucket_ms() {
+            // 每桶取最后一条，时间戳对齐到桶结束时间
+            let mut buckets: Vec<(i64, Value)> = Vec::new();
+            for (ts, item) in rows {
+                let end = bucket_end(ts, bucket);
+                match buckets.last_mut() {
+                    Some(last) if last.0 == end => last.1 = item,
+                    _ => buckets.push((end, item)),
+                }
+            }
+            rows = buckets;
+        }
+        let items: Vec<Value> = rows
+            .into_iter()
+            .map(|(ts, mut item)| {
+                item["ts"] = serde_json::json!(ts);
+                item
+            })
+            .collect();
+        Ok(serde_json::json!({ "ok": true, "items": items }))
+    }
+}
+
+fn sql_type(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Real => "REAL",
+        Kind::Text => "TEXT",
+        Kind::Bool => "INTEGER",
+    }
+}
+
+/// 补齐表中缺失的列（DTO 新增字段时向前兼容旧库）
+pub fn ensure_columns<'a, I>(conn: &Connection, table: &str, columns: I) -> rusqlite::Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let existing: HashSet<String> = conn
+        .prepare(&format!("PRAGMA table_info({})", table))?
+        .query_map([], |r| r.get::<_, String>(1))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (name, ty) in columns {
+        if !existing.contains(name) {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {} NULL", table, name, ty))?;
+        }
+    }
+    Ok(())
+}
+
+static DB_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+/// 写入线程的消息：实时帧按批写入；其余写操作（如电池会话）在同一连接上按顺序执行
+type Job = Box<dyn FnOnce(&mut Store) + Send>;
+
+enum Write {
+    Row(Row),
+    Job(Job),
+}
+
+static WRITER: Mutex<Option<SyncSender<Write>>> = Mutex::new(None);
+static COMPACT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 请求写入线程尽快执行一次整理（最迟 FLUSH_INTERVAL_MS 后）
+pub fn request_compaction() {
+    COMPACT_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+fn compact(store: &mut Store) {
+    // 电池健康日志需在原始行过期清理之前记录
+    if let Err(e) = store.log_battery_health() {
+        crate::log_line("ERROR", &format!("battery health log failed: {}", e));
+    }
+    let retention = crate::rollup::RETENTION.lock().map(|r| r.clone()).unwrap_or(crate::rollup::Retention::new());
+    match store.compact(crate::now_millis() as i64, &retention) {
+        Ok(report) => {
+            if let Ok(mut last) = crate::rollup::LAST_COMPACTION.lock() {
+                *last = Some(report);
+            }
+        }
+        Err(e) => crate::log_line("ERROR", &format!("local store compaction failed: {}", e)),
+    }
+}
+
+/// 应用启动时调用：打开（或创建）本地库并启动写入线程
+pub fn init(app: &tauri::AppHandle) {
+    let dir = match app.path().app_data_dir() {
+        Ok(d) => d,
+        Err(e) => {
+            crate::log_line("ERROR", &format!("local store: app data dir unavailable: {}", e));
+            return;
+        }
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join(DB_FILE);
+    let mut store = match Store::open(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            crate::log_line("ERROR", &format!("local store open failed: {}", e));
+            return;
+        }
+    };
+    if let Ok(mut r) = crate::rollup::RETENTION.lock() {
+        *r = crate::rollup::load_retention(&store.conn);
+    }
+    crate::batterysessions::restore(&store, crate::now_millis() as i64);
+    let (tx, rx) = mpsc::sync_channel::<Write>(WRITE_QUEUE);
+    if let Ok(mut p) = DB_PATH.lock() {
+        *p = Some(path);
+    }
+    if let Ok(mut w) = WRITER.lock() {
+        *w = Some(tx);
+    }
+    std::thread::spawn(move || {
+        let mut batch = Vec::with_capacity(BATCH_MAX);
+        let mut last_compact = Instant::now();
+        loop {
+            let mut job = None;
+            match rx.recv_timeout(Duration::from_millis(FLUSH_INTERVAL_MS)) {
+                Ok(Write::Row(row)) => {
+                    batch.push(row);
+                    if batch.len() < BATCH_MAX {
+                        continue;
+                    }
+                }
+                // 先写完已排队的帧，保持写入顺序
+                Ok(Write::Job(j)) => job = Some(j),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            if !batch.is_empty() {
+                if let Err(e) = store.insert_batch(&batch) {
+                    crate::log_line("ERROR", &format!("local store insert failed: {}", e));
+                }
+                batch.clear();
+            }
+            if let Some(job) = job {
+                job(&mut store);
+            }
+            // 汇总/清理与写入在同一线程，避免与批量写入争用连接
+            if last_compact.elapsed() >= COMPACT_INTERVAL || COMPACT_REQUESTED.swap(false, Ordering::SeqCst) {
+                compact(&mut store);
+                last_compact = Instant::now();
+            }
+        }
+    });
+}
+
+/// 本地库已打开、正在落盘实时帧
+pub fn is_recording() -> bool {
+    WRITER.lock().map(|w| w.is_some()).unwrap_or(false)
+}
+
+/// 由 dispatch_notification 对每帧 metrics 调用；raw 为服务端原始负载，整帧写入 frame 列
+pub fn record(frame: &Value, raw: &RawValue) {
+    let Some(mut row) = extract(frame) else { return };
+    row.frame = Some(raw.get().to_owned());
+    if let Ok(w) = WRITER.lock() {
+        if let Some(tx) = w.as_ref() {
+            let _ = tx.try_send(Write::Row(row));
+        }
+    }
+}
+
+/// 在写入线程上执行一次写操作（与批量写入共用连接）；本地库未打开或队列已满时返回 false
+pub fn submit(job: impl FnOnce(&mut Store) + Send + 'static) -> bool {
+    let Ok(w) = WRITER.lock() else { return false };
+    w.as_ref().is_some_and(|tx| tx.try_send(Write::Job(Box::new(job))).is_ok())
+}
+
+pub(crate) fn db_path() -> Result<PathBuf, String> {
+    DB_PATH
+        .lock()
+        .ok()
+        .and_then(|p| p.clone())
+        .ok_or_else(|| "local store not initialized".to_string())
+}
+
+#[tauri::command]
+pub async fn local_query_history(params: HistoryParams) -> Result<Value, String> {
+    let path = db_path()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = Store::open_readonly(&path).map_err(|e| e.to_string())?;
+        let mut result = store.query(&params, crate::now_millis() as i64).map_err(|e| e.to_string())?;
+        crate::postprocess_history(&mut result);
+        if let Some(spec) = &params.downsample {
+            crate::downsample::apply_result(&mut result, spec);
+        }
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))?
+}
+
+/// 存储占用：文件大小、页统计、各表行数与时间范围、保留配置与最近一次整理结果
+#[tauri::command]
+pub async fn local_storage_report() -> Result<Value, String> {
+    let path = db_path()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let store = Store::open_readonly(&path).map_err(|e| e.to_string())?;
+        let mut report = store.storage_report().map_err(|e| e.to_string())?;
+        let file_len = |p: PathBuf| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+        let wal = PathBuf::from(format!("{}-wal", path.display()));
+        if let Value::Object(m) = &mut report {
+            m.insert("path".into(), Value::String(path.display().to_string()));
+            m.insert("file_bytes".into(), serde_json::json!(file_len(path.clone())));
+            m.insert("wal_bytes".into(), serde_json::json!(file_len(wal)));
+            m.insert("retention".into(), serde_json::json!(*crate::rollup::RETENTION.lock().map_err(|e| e.to_string())?));
+            m.insert("last_compaction".into(), serde_json::json!(*crate::rollup::LAST_COMPACTION.lock().map_err(|e| e.to_string())?));
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn frame(ts: i64, cpu: f64) -> Value {
+        json!({
+            "ts": ts, "seq": ts,
+            "cpu": { "usage_percent": cpu },
+            "memory": { "total": 16000, "used": 4000 },
+            "disk": { "status": "warming_up" },
+            "power": { "battery": { "state": "charging", "percentage": 50.0, "ac_line_online": true } }
+        })
+    }
+
+    #[test]
+    fn extracts_and_rebuilds_items() {
+        let row = extract(&frame(1_000, 12.5)).unwrap();
+        assert_eq!(row.seq, Some(1_000));
+        assert!(extract(&json!({ "ts": 1, "disk": { "status": "warming_up" } })).is_none());
+
+        let mut s = Store::open_in_memory().unwrap();
+        s.insert_batch(&[row]).unwrap();
+        let p = HistoryParams { modules: Some(vec!["cpu".into(), "memory".into(), "disk".into(), "power".into()]), ..Default::default() };
+        let items = s.query(&p, 2_000).unwrap()["items"].clone();
+        assert_eq!(
+            items,
+            json!([{
+                "ts": 1_000,
+                "cpu": { "usage_percent": 12.5 },
+                "memory": { "total_mb": 16000.0, "used_mb": 4000.0 },
+                "disk": null,
+                "power": { "battery": { "percentage": 50.0, "state": "charging", "ac_line_online": true } }
+            }])
+        );
+    }
+
+    #[test]
+    fn buckets_keep_last_per_step() {
+        let mut s = Store::open_in_memory().unwrap();
+        let rows: Vec<Row> = [1_000, 4_000, 10_000, 12_000, 25_000].iter().map(|ts| extract(&frame(*ts, *ts as f64)).unwrap()).collect();
+        s.insert_batch(&rows).unwrap();
+        let p = HistoryParams { from_ts: 0, to_ts: 20_000, modules: Some(vec!["cpu".into()]), agg: Some("10s".into()), ..Default::default() };
+        let items = s.query(&p, 0).unwrap()["items"].clone();
+        assert_eq!(
+            items,
+            json!([
+                { "ts": 10_000, "cpu": { "usage_percent": 10_000.0 } },
+                { "ts": 20_000, "cpu": { "usage_percent": 12_000.0 } }
+            ])
+        );
+        assert_eq!(HistoryParams { to_ts: 0, ..Default::default() }.resolved_to(99), 99);
+    }
+
+    #[test]
+    fn adds_missing_columns_to_old_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE metrics (ts INTEGER NOT NULL, seq INTEGER NULL, cpu_usage_percent REAL NULL)").unwrap();
+        let s = Store::init(conn).unwrap();
+        let n: i64 = s.conn.query_row("SELECT COUNT(*) FROM pragma_table_info('metrics')", [], |r| r.get(0)).unwrap();
+        assert_eq!(n as usize, 3 + COLUMNS.len());
+    }
+
+    #[test]
+    fn raw_queries_return_full_modules_from_frame_column() {
+        let full = json!({
+            "ts": 5_000, "seq": 5,
+            "cpu": { "usage_percent": 7.0, "per_core": [1.0, 2.0] },
+            "network": { "interfaces": [{ "name": "eth0", "rx_bytes_per_sec": 10 }] }
+        });
+        let mut with_frame = extract(&full).unwrap();
+        with_frame.frame = Some(full.to_string());
+        // 旧行没有 frame，按列还原
+        let old = extract(&frame(1_000, 3.0)).unwrap();
+        let mut s = Store::open_in_memory().unwrap();
+        s.insert_batch(&[old, with_frame]).unwrap();
+        let p = HistoryParams { modules: Some(vec!["cpu".into(), "network".into()]), ..Default::default() };
+        let items = s.query(&p, 9_000).unwrap()["items"].clone();
+        assert_eq!(
+            items,
+            json!([
+                { "ts": 1_000, "cpu": { "usage_percent": 3.0 }, "network": null },
+                { "ts": 5_000, "cpu": full["cpu"], "network": full["network"] }
+            ])
+        );
+        // 只有非列模块的帧也会入库
+        assert!(extract(&json!({ "ts": 1, "gpu": { "adapters": [] } })).is_some());
+    }
+}
@@ -2,17 +2,46 @@
 
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use serde_json::Value;
 use std::fs::OpenOptions;
 use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::time::Instant;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
 use std::time::Duration;
 use tauri::Emitter;
 use tauri::async_runtime;
 
+mod alertjournal;
+mod alertrules;
+mod alerts;
+mod anomaly;
+mod autopause;
+mod batteryhealth;
+mod batterysessions;
+mod burst;
+mod decode;
+mod delta;
+mod derived;
+mod downsample;
+mod export;
+mod dto;
+mod frame;
+mod gap;
+mod histcache;
+mod localstore;
+mod notifications;
+mod recent;
+mod replay;
+mod rollup;
+mod session;
+mod stream;
+mod telemetry;
+mod units;
+
 const PIPE_PATH: &str = r"\\.\pipe\sys_sensor_v3.rpc";
 
 fn resolve_repo_root() -> PathBuf {
@@ -31,14 +60,18 @@ fn resolve_repo_root() -> PathBuf {
     std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
 }
 
-fn log_line(level: &str, msg: &str) {
+pub(crate) fn log_dir() -> PathBuf {
     // Prefer SYS_SENSOR_LOG_DIR override
-    let log_dir = if let Ok(env_dir) = std::env::var("SYS_SENSOR_LOG_DIR") {
+    if let Ok(env_dir) = std::env::var("SYS_SENSOR_LOG_DIR") {
         std::path::PathBuf::from(env_dir)
     } else {
         let root = resolve_repo_root();
         root.join("logs")
-    };
+    }
+}
+
+pub(crate) fn log_line(level: &str, msg: &str) {
+    let log_dir = log_dir();
     let _ = std::fs::create_dir_all(&log_dir);
     let path = log_dir.join("frontend.log");
     if let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) {
@@ -67,7 +100,9 @@ fn call_hello_over_pipe(file: &mut std::fs::File) -> Result<()> {
     let (payload, _id) = build_request("hello", Some(params));
     file.write_all(&payload)?;
     file.flush()?;
+    telemetry::record_out("hello", payload.len());
     let resp = read_response(file)?;
+    telemetry::record_in("hello", resp.frame_len);
     if resp.error.is_some() { return Err(anyhow!("hello error")); }
     Ok(())
 }
@@ -78,6 +113,16 @@ fn call_hello_over_pipe(file: &mut std::fs::File) -> Result<()> {
 fn bridge_set_subscribe(enable: bool) -> Result<(), String> {
     WANT_SUBSCRIBE.store(enable, Ordering::SeqCst);
     SUBSCRIBE_DIRTY.store(true, Ordering::SeqCst);
+    BRIDGE_WAKE.notify_all();
+    Ok(())
+}
+
+// 逐帧 bridge_rx 调试事件默认关闭，计数改由 bridge_stats 提供
+static BRIDGE_RX_DEBUG: AtomicBool = AtomicBool::new(false);
+
+#[tauri::command]
+fn bridge_set_debug_rx(enable: bool) -> Result<(), String> {
+    BRIDGE_RX_DEBUG.store(enable, Ordering::SeqCst);
     Ok(())
 }
 
@@ -91,17 +136,27 @@ struct JsonRpcResponse {
     result: Option<Value>,
     #[serde(default)]
     error: Option<Value>,
+    // 整帧字节数（头 + 体），仅用于遥测
+    #[serde(skip)]
+    frame_len: usize,
 }
 
-fn now_millis() -> u64 {
+pub(crate) fn now_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis() as u64
 }
 
+static LAST_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
 fn build_request(method: &str, params: Option<Value>) -> (Vec<u8>, u64) {
-    let id = now_millis();
+    // 以毫秒时间戳为基数，同一毫秒内的多次请求顺延，保证事件桥上按 id 匹配响应时不冲突
+    let now = now_millis();
+    let prev = LAST_REQUEST_ID
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(now.max(last + 1)))
+        .unwrap_or(now);
+    let id = now.max(prev + 1);
     // 兼容 StreamJsonRpc：当服务端方法签名为单个 DTO 参数（e.g. hello(HelloParams p)）时，
     // 需要使用位置参数形式传递，即 [ { ... } ]；若直接传对象会被视为多个同名参数，导致 "hello/4" 等错误。
     let wrapped_params = match params {
@@ -133,6 +188,39 @@ fn read_exact_until(stream: &mut std::fs::File, pattern: &[u8]) -> Result<Vec<u8
     Ok(buf)
 }
 
+// 命名管道以同步句柄打开，阻塞中的 ReadFile 会挡住同一句柄上的 WriteFile，
+// 因此事件桥先用 PeekNamedPipe 查看是否有可读数据，无数据时才有机会写出排队的请求
+#[cfg(windows)]
+fn pipe_has_data(file: &std::fs::File) -> std::io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn PeekNamedPipe(
+            pipe: *mut std::ffi::c_void,
+            buffer: *mut std::ffi::c_void,
+            buffer_size: u32,
+            bytes_read: *mut u32,
+            total_avail: *mut u32,
+            bytes_left: *mut u32,
+        ) -> i32;
+    }
+    let mut avail: u32 = 0;
+    // SAFETY: 句柄在 file 存活期间有效，不读取数据，仅输出可读字节数
+    let ok = unsafe {
+        PeekNamedPipe(file.as_raw_handle(), std::ptr::null_mut(), 0, std::ptr::null_mut(), &mut avail, std::ptr::null_mut())
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(avail > 0)
+}
+
+// 非 Windows 平台没有该管道，保持阻塞读
+#[cfg(not(windows))]
+fn pipe_has_data(_file: &std::fs::File) -> std::io::Result<bool> {
+    Ok(true)
+}
+
 fn read_response(stream: &mut std::fs::File) -> Result<JsonRpcResponse> {
     // 读取直到 \r\n\r\n
     let header = read_exact_until(stream, b"\r\n\r\n")?;
@@ -157,7 +245,7 @@ fn read_response(stream: &mut std::fs::File) -> Result<JsonRpcResponse> {
         if n == 0 { return Err(anyhow!("connection closed while reading body")); }
         read += n;
     }
-    let resp: JsonRpcResponse = match serde_json::from_slice(&body) {
+    let mut resp: JsonRpcResponse = match serde_json::from_slice(&body) {
         Ok(v) => v,
         Err(e) => {
             // 尝试提供更多上下文，便于定位问题
@@ -173,6 +261,7 @@ fn read_response(stream: &mut std::fs::File) -> Result<JsonRpcResponse> {
             return Err(err);
         }
     };
+    resp.frame_len = header.len() + content_length;
     Ok(resp)
 }
 
@@ -184,7 +273,7 @@ fn open_pipe_with_retry(timeout: Duration) -> Result<std::fs::File> {
             Err(e) => {
                 let err = anyhow!(e).context(format!("open named pipe {}", PIPE_PATH));
                 if start.elapsed() >= timeout {
-                    return Err(err.into());
+                    return Err(err);
                 }
                 // 稍快一些的轮询，提升抢占成功率
                 std::thread::sleep(Duration::from_millis(80));
@@ -193,20 +282,27 @@ fn open_pipe_with_retry(timeout: Duration) -> Result<std::fs::File> {
     }
 }
 
-fn call_over_named_pipe(method: &str, params: Option<Value>) -> Result<Value> {
+pub(crate) fn call_over_named_pipe(method: &str, params: Option<Value>) -> Result<Value> {
     // 连接命名管道，带重试（最多 3 秒）
     // 延长到 10 秒，避免事件桥刚建立后，服务端尚未创建下一监听实例导致的短暂不可用
-    let mut file = open_pipe_with_retry(Duration::from_secs(10))?;
+    let started = Instant::now();
+    let res = (|| {
+        let mut file = open_pipe_with_retry(Duration::from_secs(10))?;
 
-    let (payload, _id) = build_request(method, params);
-    file.write_all(&payload)?;
-    file.flush()?;
+        let (payload, _id) = build_request(method, params);
+        file.write_all(&payload)?;
+        file.flush()?;
+        telemetry::record_rpc_out(method, payload.len());
 
-    let resp = read_response(&mut file)?;
-    if let Some(err) = resp.error {
-        return Err(anyhow!("rpc error: {}", err));
-    }
-    resp.result.ok_or_else(|| anyhow!("rpc response missing result"))
+        let resp = read_response(&mut file)?;
+        telemetry::record_rpc_in(method, resp.frame_len);
+        if let Some(err) = resp.error {
+            return Err(anyhow!("rpc error: {}", err));
+        }
+        resp.result.ok_or_else(|| anyhow!("rpc response missing result"))
+    })();
+    telemetry::record_rpc(method, started.elapsed().as_millis() as u64, res.is_ok());
+    res
 }
 
 static EVENT_BRIDGE_STARTED: AtomicBool = AtomicBool::new(false);
@@ -214,6 +310,104 @@ static EVENT_BRIDGE_STARTED: AtomicBool = AtomicBool::new(false);
 static WANT_SUBSCRIBE: AtomicBool = AtomicBool::new(false);
 static SUBSCRIBE_DIRTY: AtomicBool = AtomicBool::new(false);
 
+// 事件桥连接上的请求队列：服务端部分状态（如 burst）按连接维护，必须在事件桥连接上发送。
+// 读循环在管道无可读数据时写出请求，所有带 id 的响应帧（含 subscribe_metrics 的 ack）按 id 回传给等待方。
+static BRIDGE_CONNECTED: AtomicBool = AtomicBool::new(false);
+static BRIDGE_OUTBOX: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+// 有请求入队或订阅变更时唤醒空闲中的读循环
+static BRIDGE_WAKE: Condvar = Condvar::new();
+// 订阅 ack 失败时请求读循环断开重连
+static BRIDGE_RECONNECT: AtomicBool = AtomicBool::new(false);
+type BridgeReply = Box<dyn FnOnce(Result<Value, String>) + Send>;
+type PendingCall = (u64, String, BridgeReply);
+static BRIDGE_PENDING: Mutex<Vec<PendingCall>> = Mutex::new(Vec::new());
+// 读循环空闲时的最长等待，决定无请求时检查新帧的间隔
+const BRIDGE_IDLE_WAIT: Duration = Duration::from_millis(10);
+
+fn register_bridge_reply(id: u64, method: &str, reply: BridgeReply) -> Result<()> {
+    BRIDGE_PENDING.lock().map_err(|_| anyhow!("bridge pending lock poisoned"))?.push((id, method.to_string(), reply));
+    Ok(())
+}
+
+pub(crate) fn call_over_bridge(method: &str, params: Option<Value>, timeout: Duration) -> Result<Value> {
+    if !BRIDGE_CONNECTED.load(Ordering::SeqCst) {
+        return Err(anyhow!("event bridge not connected"));
+    }
+    let started = Instant::now();
+    let (payload, id) = build_request(method, params);
+    let (tx, rx) = mpsc::channel();
+    telemetry::record_out(method, payload.len());
+    register_bridge_reply(id, method, Box::new(move |res| { let _ = tx.send(res); }))?;
+    BRIDGE_OUTBOX.lock().map_err(|_| anyhow!("bridge outbox lock poisoned"))?.push(payload);
+    BRIDGE_WAKE.notify_all();
+    let res = rx.recv_timeout(timeout);
+    if let Ok(mut pending) = BRIDGE_PENDING.lock() {
+        pending.retain(|(pid, _, _)| *pid != id);
+    }
+    let res = match res {
+        Ok(r) => r.map_err(|e| anyhow!("rpc error: {}", e)),
+        Err(_) => Err(anyhow!("bridge call {} timed out", method)),
+    };
+    telemetry::record_rpc(method, started.elapsed().as_millis() as u64, res.is_ok());
+    res
+}
+
+// 返回该响应对应的方法名；无匹配的等待方时返回 None
+fn complete_bridge_call(frame: &Value) -> Option<String> {
+    let id = frame.get("id").and_then(|v| v.as_u64())?;
+    let (_, method, reply) = {
+        let mut pending = BRIDGE_PENDING.lock().ok()?;
+        let pos = pending.iter().position(|(pid, _, _)| *pid == id)?;
+        pending.swap_remove(pos)
+    };
+    let res = match frame.get("error") {
+        Some(err) if !err.is_null() => Err(err.to_string()),
+        _ => Ok(frame.get("result").cloned().unwrap_or(Value::Null)),
+    };
+    reply(res);
+    Some(method)
+}
+
+fn reset_bridge_calls() {
+    BRIDGE_CONNECTED.store(false, Ordering::SeqCst);
+    if let Ok(mut outbox) = BRIDGE_OUTBOX.lock() {
+        outbox.clear();
+    }
+    let pending: Vec<PendingCall> = BRIDGE_PENDING.lock().map(|mut p| p.drain(..).collect()).unwrap_or_default();
+    for (_, _, reply) in pending {
+        reply(Err("bridge disconnected".to_string()));
+    }
+}
+
+// 读循环空闲等待：有排队请求或订阅变更时立即返回
+fn wait_bridge_work(timeout: Duration) {
+    let Ok(outbox) = BRIDGE_OUTBOX.lock() else { return };
+    let _ = BRIDGE_WAKE.wait_timeout_while(outbox, timeout, |q| q.is_empty() && !SUBSCRIBE_DIRTY.load(Ordering::SeqCst));
+}
+
+// 在事件桥连接上发送 subscribe_metrics，ack 经等待表异步回传；init 阶段失败时断开重连
+fn send_subscribe(file: &mut std::fs::File, app: &tauri::AppHandle, stage: &'static str, enable: bool) -> std::io::Result<()> {
+    let (buf, id) = build_request("subscribe_metrics", Some(serde_json::json!({ "enable": enable })));
+    let app = app.clone();
+    let reply: BridgeReply = Box::new(move |res| {
+        let ok = res.is_ok();
+        let _ = app.emit("bridge_subscribe_ack", serde_json::json!({"stage": stage, "enable": enable, "ok": ok}));
+        log_line("INFO", &format!("bridge subscribe({} enable={}) ack ok={}", stage, enable, ok));
+        if let (Err(e), "init") = (res, stage) {
+            log_line("ERROR", &format!("bridge subscribe(init) failed: {}", e));
+            let _ = app.emit("bridge_error", serde_json::json!({"stage": "init_subscribe", "error": e}));
+            BRIDGE_RECONNECT.store(true, Ordering::SeqCst);
+        }
+    });
+    if register_bridge_reply(id, "subscribe_metrics", reply).is_err() {
+        return Err(std::io::Error::other("bridge pending lock poisoned"));
+    }
+    file.write_all(&buf)?;
+    file.flush()?;
+    telemetry::record_out("subscribe_metrics", buf.len());
+    Ok(())
+}
+
 #[tauri::command]
 fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
     if EVENT_BRIDGE_STARTED.swap(true, Ordering::SeqCst) {
@@ -226,6 +420,7 @@ fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
                 Ok(mut file) => {
                     // 建立事件桥握手：hello(capabilities: ["metrics_stream"]) -> 订阅
                     let _ = app.emit("bridge_handshake", serde_json::json!({"stage":"hello"}));
+                    let handshake_started = Instant::now();
                     if let Err(e) = call_hello_over_pipe(&mut file) {
                         log_line("ERROR", &format!("bridge hello failed: {}", e));
                         let _ = app.emit(
@@ -239,16 +434,19 @@ fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
                         continue;
                     }
                     log_line("INFO", "bridge hello ok");
-                    // 初始订阅状态
-                    let _ = app.emit("bridge_subscribe", serde_json::json!({"stage":"init","enable": WANT_SUBSCRIBE.load(Ordering::SeqCst)}));
-                    let sub_params = serde_json::json!({ "enable": WANT_SUBSCRIBE.load(Ordering::SeqCst) });
-                    let (sub_payload, _id) = build_request("subscribe_metrics", Some(sub_params));
-                    let _ = file.write_all(&sub_payload);
-                    let _ = file.flush();
-                    let init_resp = read_response(&mut file);
-                    let _ = app.emit("bridge_subscribe_ack", serde_json::json!({"stage":"init","ok": init_resp.is_ok()}));
-                    log_line("INFO", &format!("bridge subscribe(init) ack ok={}", init_resp.is_ok()));
-                    if let Err(e) = init_resp {
+                    telemetry::record_connect(handshake_started.elapsed().as_millis() as u64);
+                    // 新连接的 seq 基数与上一连接无关，交由断档检测按 reset 处理
+                    gap::mark_reconnect();
+                    // 服务端 burst 状态随连接丢失，租约仍存活时立即续订
+                    burst::force_renew();
+                    BRIDGE_RECONNECT.store(false, Ordering::SeqCst);
+                    BRIDGE_CONNECTED.store(true, Ordering::SeqCst);
+                    // 初始订阅状态；ack 与其他响应一样在读循环中按 id 回传。自动暂停中连接时保持退订
+                    let mut paused = autopause::is_paused();
+                    let enable = WANT_SUBSCRIBE.load(Ordering::SeqCst) && !paused;
+                    SUBSCRIBE_DIRTY.store(false, Ordering::SeqCst);
+                    let _ = app.emit("bridge_subscribe", serde_json::json!({"stage":"init","enable": enable}));
+                    if let Err(e) = send_subscribe(&mut file, &app, "init", enable) {
                         log_line("ERROR", &format!("bridge subscribe(init) failed: {}", e));
                         let _ = app.emit(
                             "bridge_error",
@@ -258,22 +456,62 @@ fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
                             }),
                         );
                         // 订阅失败，断开并重连
+                        reset_bridge_calls();
                         continue;
                     }
 
-                    // 持续读取通知帧（HeaderDelimited + JSON）
+                    // 重放 UI 先前设定的会话状态（start/set_config 等），完成后发出 session_restored
+                    session::restore_after_hello(&app);
+
+                    // 持续读取通知帧（HeaderDelimited + JSON）；body 缓冲跨帧复用
+                    let mut body: Vec<u8> = Vec::new();
                     loop {
-                        // 若收到订阅变更指令，则在同一连接上发送
-                        if SUBSCRIBE_DIRTY.swap(false, Ordering::SeqCst) {
+                        if BRIDGE_RECONNECT.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        // 自动暂停只在本连接上退订，连接保持，经桥的调用与 burst 续订不受影响；恢复时按 UI 设定重新订阅
+                        if autopause::is_paused() != paused {
+                            paused = !paused;
+                            let stage = if paused { "pause" } else { "resume" };
+                            let enable = WANT_SUBSCRIBE.load(Ordering::SeqCst) && !paused;
+                            let _ = app.emit("bridge_subscribe", serde_json::json!({"stage": stage, "enable": enable}));
+                            if let Err(e) = send_subscribe(&mut file, &app, stage, enable) {
+                                log_line("ERROR", &format!("bridge subscribe({}) write failed: {}", stage, e));
+                            }
+                        }
+                        // 若收到订阅变更指令，则在同一连接上发送（暂停期间只记下，恢复时生效）
+                        if SUBSCRIBE_DIRTY.swap(false, Ordering::SeqCst) && !paused {
                             let enable = WANT_SUBSCRIBE.load(Ordering::SeqCst);
-                            let params = serde_json::json!({ "enable": enable });
-                            let (buf, _id) = build_request("subscribe_metrics", Some(params));
                             let _ = app.emit("bridge_subscribe", serde_json::json!({"stage":"toggle","enable": enable}));
-                            let _ = file.write_all(&buf);
-                            let _ = file.flush();
-                            let resp = read_response(&mut file);
-                            let _ = app.emit("bridge_subscribe_ack", serde_json::json!({"stage":"toggle","ok": resp.is_ok()}));
-                            log_line("INFO", &format!("bridge subscribe(toggle enable={}) ack ok={}", enable, resp.is_ok()));
+                            if let Err(e) = send_subscribe(&mut file, &app, "toggle", enable) {
+                                log_line("ERROR", &format!("bridge subscribe(toggle) write failed: {}", e));
+                            }
+                        }
+                        // 发送其他模块排队的桥接请求，响应在下方分发时按 id 回传
+                        let queued: Vec<Vec<u8>> = BRIDGE_OUTBOX.lock().map(|mut q| q.drain(..).collect()).unwrap_or_default();
+                        for buf in queued {
+                            if let Err(e) = file.write_all(&buf).and_then(|_| file.flush()) {
+                                log_line("ERROR", &format!("bridge outbox write failed: {}", e));
+                            }
+                        }
+                        // 无可读数据时空闲等待，期间入队的请求无需等下一帧即可写出
+                        match pipe_has_data(&file) {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                wait_bridge_work(BRIDGE_IDLE_WAIT);
+                                continue;
+                            }
+                            Err(e) => {
+                                let _ = app.emit(
+                                    "bridge_error",
+                                    serde_json::json!({
+                                        "stage": "peek",
+                                        "error": e.to_string()
+                                    }),
+                                );
+                                log_line("ERROR", &format!("bridge peek failed: {}", e));
+                                break;
+                            }
                         }
                         // 读取直到空行
                         let header = match read_exact_until(&mut file, b"\r\n\r\n") {
@@ -311,7 +549,8 @@ fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
                             log_line("ERROR", "bridge parse_header: missing or zero content-length");
                             break;
                         }
-                        let mut body = vec![0u8; content_length];
+                        body.clear();
+                        body.resize(content_length, 0);
                         let mut read = 0;
                         while read < content_length {
                             match file.read(&mut body[read..]) {
@@ -340,27 +579,33 @@ fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
                                 }
                             }
                         }
-                        // 解析 JSON 并分发
-                        if let Ok(v) = serde_json::from_slice::<serde_json::Value>(&body) {
-                            let method = v.get("method").and_then(|m| m.as_str());
-                            let has_id = v.get("id").is_some();
-                            if method.is_some() && !has_id {
-                                let event = method.unwrap();
-                                // 先发一条桥接调试事件，便于前端观测是否有通知到达
-                                let _ = app.emit(
-                                    "bridge_rx",
-                                    serde_json::json!({
-                                        "method": event,
-                                        "has_id": has_id
-                                    }),
-                                );
-                                // 兼容 StreamJsonRpc 的位置参数：如果 params 是单元素数组，则解包为该元素
-                                let raw_params = v.get("params").cloned().unwrap_or(Value::Null);
-                                let payload = match &raw_params {
-                                    Value::Array(arr) if arr.len() == 1 => arr[0].clone(),
-                                    _ => raw_params,
-                                };
-                                let _ = app.emit(event, payload);
+                        // 只解析 method/id，通知的 params 以原始切片转发
+                        let frame_len = header.len() + content_length;
+                        if let Ok(env) = frame::parse_envelope(&body) {
+                            if let Some(event) = env.notification() {
+                                telemetry::record_in(event, frame_len);
+                                // 按需发出桥接调试事件（bridge_set_debug_rx 开启），便于前端观测是否有通知到达
+                                if BRIDGE_RX_DEBUG.load(Ordering::Relaxed) {
+                                    let _ = app.emit(
+                                        "bridge_rx",
+                                        serde_json::json!({
+                                            "method": event,
+                                            "has_id": false
+                                        }),
+                                    );
+                                }
+                                let payload = env.params.map(frame::unwrap_params).unwrap_or(RawValue::NULL);
+                                // 离线回放期间实时通知不分发（见 replay.rs）
+                                if !replay::is_active() {
+                                    dispatch_notification(&app, event, payload);
+                                }
+                            } else if env.id.is_some() {
+                                // 响应帧较少，仍完整解析以取出 result/error
+                                let method = serde_json::from_slice::<Value>(&body)
+                                    .ok()
+                                    .and_then(|v| complete_bridge_call(&v))
+                                    .unwrap_or_else(|| "response".to_string());
+                                telemetry::record_in(&method, frame_len);
                             }
                         } else {
                             // JSON 解析失败，发出错误事件，包含部分 body 预览
@@ -373,9 +618,11 @@ fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
                                     "body_preview": preview
                                 }),
                             );
+                            telemetry::record_json_decode_error();
                             log_line("ERROR", "bridge decode_json failed");
                         }
                     }
+                    reset_bridge_calls();
                 }
                 Err(e) => {
                     // 未连接上服务端，稍后重试
@@ -395,14 +642,113 @@ fn start_event_bridge(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+// 分发服务端通知：metrics 先经过断档检测与类型校验（均基于原始帧），state/alert/ping/update_ready 更新本地状态，
+// 仅这些通知从原始切片解析为 Value；转发给前端时始终原样输出原始字节
+fn dispatch_notification(app: &tauri::AppHandle, event: &str, payload: &RawValue) {
+    match event {
+        "metrics" => {
+            if let Ok(mut v) = serde_json::from_str::<Value>(payload.get()) {
+                // 契约校验、断档检测与本地落盘按服务端原始帧进行
+                decode::check_metrics(app, &v);
+                // 回放帧的 seq 与实时连接无关，不做断档检测与回填
+                if !replay::is_active() {
+                    gap::observe_metrics(app, &v);
+                    localstore::record(&v, payload);
+                }
+                // 告警与异常检测可引用 derived.*，在单位换算前评估
+                let with_derived = derived::apply_live(&mut v);
+                if !replay::is_active() {
+                    alerts::evaluate(app, &v);
+                    anomaly::evaluate(app, &v);
+                    batterysessions::observe(&v);
+                }
+                // 单位换算改写键名时整帧重新序列化；只有 derived 时追加到原始字节后
+                let raw = if units::apply_live(&mut v) {
+                    serde_json::value::to_raw_value(&v).ok()
+                } else if with_derived {
+                    derived::splice_raw(payload, &v["derived"])
+                } else {
+                    None
+                };
+                let payload = raw.as_deref().unwrap_or(payload);
+                stream::publish(&v);
+                // 近期帧缓冲只保留实时帧，回放帧不混入
+                if !replay::is_active() {
+                    recent::record(&v, payload);
+                }
+                // 已开启增量模式的窗口改收 metrics_delta，整帧只发给其余目标
+                let delta_windows = delta::publish(app, &v);
+                if !delta_windows.is_empty() {
+                    let _ = app.emit_filter(event, payload, |t| !delta::targets_any(t, &delta_windows));
+                    return;
+                }
+                let _ = app.emit(event, payload);
+                return;
+            }
+        }
+        "alert" => {
+            if let Ok(v) = serde_json::from_str::<Value>(payload.get()) {
+                notifications::observe(event, &v);
+                // 已确认或静音中的告警只记入日志，不再转发
+                if !alertjournal::on_alert(app, &v, alertjournal::Origin::Service) {
+                    return;
+                }
+            }
+        }
+        "state" | "ping" | "update_ready" => {
+            if let Ok(v) = serde_json::from_str::<Value>(payload.get()) {
+                notifications::observe(event, &v);
+            }
+        }
+        _ => {}
+    }
+    let _ = app.emit(event, payload);
+}
+
+/// 服务端 query_history 结果（{ ok, items }）与实时帧保持一致：补充 derived，按设置做单位换算。
+/// rpc_call、断档回填与历史导出共用
+pub(crate) fn postprocess_history(v: &mut Value) {
+    derived::apply_history(v);
+    units::apply_result(v);
+}
+
 #[tauri::command]
-async fn rpc_call(method: String, params: Option<Value>) -> Result<Value, String> {
+async fn rpc_call(method: String, mut params: Option<Value>) -> Result<Value, String> {
+    // query_history 的 downsample 由桥接处理，不转发给服务端
+    let downsample = if method == "query_history" { downsample::take_spec(&mut params)? } else { None };
+    let post = |v: &mut Value| {
+        match method.as_str() {
+            "query_history" => postprocess_history(v),
+            "snapshot" => units::apply_result(v),
+            _ => {}
+        }
+        if let Some(spec) = &downsample {
+            downsample::apply_result(v, spec);
+        }
+    };
+    if let Some(res) = replay::handle_rpc(&method, params.as_ref()) {
+        let mut v = res?;
+        post(&mut v);
+        return Ok(v);
+    }
     // 将阻塞的命名管道调用放到后台线程，避免阻塞 UI/事件循环
     // 注意：避免 move 后再次使用 method，先克隆一份给闭包使用
     let method_for_task = method.clone();
-    let task = async_runtime::spawn_blocking(move || call_over_named_pipe(&method_for_task, params));
+    let params_for_session = params.clone();
+    let task = async_runtime::spawn_blocking(move || {
+        if method_for_task == "query_history" {
+            // 经区间合并缓存，只向服务端补取缺失部分
+            return histcache::query_history(params, now_millis() as i64, |p| call_over_named_pipe("query_history", Some(p)));
+        }
+        call_over_named_pipe(&method_for_task, params)
+    });
     match task.await {
-        Ok(Ok(v)) => Ok(v),
+        Ok(Ok(mut v)) => {
+            // 记录会话状态，供事件桥重连后重放
+            session::record(&method, params_for_session.as_ref());
+            post(&mut v);
+            Ok(v)
+        }
         Ok(Err(e)) => { log_line("ERROR", &format!("rpc_call {} failed: {}", method, e)); Err(e.to_string()) },
         Err(join_err) => { log_line("ERROR", &format!("rpc task join error: {}", join_err)); Err(format!("rpc task join error: {}", join_err)) },
     }
@@ -410,11 +756,91 @@ async fn rpc_call(method: String, params: Option<Value>) -> Result<Value, String
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![rpc_call, start_event_bridge, bridge_set_subscribe])
+        .invoke_handler(tauri::generate_handler![
+            rpc_call,
+            start_event_bridge,
+            bridge_set_subscribe,
+            bridge_set_debug_rx,
+            telemetry::bridge_stats,
+            burst::burst_acquire,
+            burst::burst_release,
+            burst::burst_status,
+            decode::metrics_decode_stats,
+            notifications::get_service_state,
+            notifications::list_service_alerts,
+            notifications::clear_service_alerts,
+            notifications::get_service_liveness,
+            notifications::get_update_ready,
+            delta::metrics_delta_enable,
+            delta::metrics_request_keyframe,
+            delta::metrics_delta_stats,
+            stream::subscribe_stream,
+            stream::stream_ack,
+            stream::unsubscribe_stream,
+            stream::stream_stats,
+            autopause::set_auto_pause,
+            autopause::get_auto_pause,
+            recent::get_recent_metrics,
+            localstore::local_query_history,
+            downsample::downsample_history,
+            histcache::history_cache_stats,
+            histcache::history_cache_clear,
+            export::export_history,
+            export::pick_export_path,
+            derived::get_derived_metrics,
+            derived::set_derived_metrics,
+            units::set_unit_normalization,
+            units::get_unit_normalization,
+            alerts::get_alert_states,
+            alertrules::list_alert_rules,
+            alertrules::upsert_alert_rule,
+            alertrules::delete_alert_rule,
+            alertrules::test_alert_rule,
+            alertjournal::list_alert_journal,
+            alertjournal::ack_alert,
+            alertjournal::snooze_alert,
+            anomaly::get_anomaly_config,
+            anomaly::set_anomaly_config,
+            anomaly::get_anomaly_state,
+            batteryhealth::battery_health_report,
+            batterysessions::battery_sessions,
+            replay::replay_open,
+            replay::replay_close,
+            replay::replay_play,
+            replay::replay_pause,
+            replay::replay_seek,
+            replay::replay_set_speed,
+            replay::replay_status,
+            localstore::local_storage_report,
+            rollup::set_local_retention
+        ])
+        .on_page_load(|webview, payload| {
+            // 导航/重载后旧页面的 Channel 与按窗口状态全部失效，视同窗口关闭
+            if payload.event() == tauri::webview::PageLoadEvent::Started {
+                burst::release_window(webview.label());
+                delta::remove_window(webview.label());
+                stream::remove_window(webview.label());
+            }
+        })
+        .on_window_event(|window, event| {
+            // 任一窗口事件都可能改变可见性，唤醒自动暂停的检查
+            autopause::nudge();
+            if let tauri::WindowEvent::Destroyed = event {
+                // 窗口关闭即释放其持有的 burst 租约、增量推送状态与 Channel 订阅
+                burst::release_window(window.label());
+                delta::remove_window(window.label());
+                stream::remove_window(window.label());
+            }
+        })
         .setup(|app| {
             // 默认订阅仍然开启，确保前端启动即可接收 metrics
             WANT_SUBSCRIBE.store(true, std::sync::atomic::Ordering::SeqCst);
+            // 本地历史库需先于事件桥就绪，避免丢失首批帧
+            localstore::init(app.handle());
+            alertjournal::init(app.handle());
+            alertrules::init(app.handle());
             let _ = start_event_bridge(app.handle().clone());
+            autopause::start_watcher(app.handle().clone());
             // 开发流程完成后，停止冗余的 snapshot 轮询与日志打印（保留为注释）
             // std::thread::spawn(|| {
             //     loop {
@@ -433,8 +859,6 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     fn split_header_body(buf: &[u8]) -> (String, Vec<u8>) {
         let sep = b"\r\n\r\n";
         let pos = buf
@@ -474,4 +898,17 @@ mod tests {
         assert!(v.get("id").is_some());
         assert!(v.get("params").is_some());
     }
+
+    #[test]
+    fn test_responses_route_to_pending_reply_by_id() {
+        let (_buf, id) = super::build_request("subscribe_metrics", None);
+        let (tx, rx) = std::sync::mpsc::channel();
+        super::register_bridge_reply(id, "subscribe_metrics", Box::new(move |res| { let _ = tx.send(res); })).unwrap();
+        // 其他 id 的响应不会被当作该请求的 ack
+        let other = serde_json::json!({"jsonrpc":"2.0","id": id + 1_000_000,"result":{}});
+        assert_eq!(super::complete_bridge_call(&other), None);
+        let frame = serde_json::json!({"jsonrpc":"2.0","id": id,"result":{"ok":true}});
+        assert_eq!(super::complete_bridge_call(&frame).as_deref(), Some("subscribe_metrics"));
+        assert_eq!(rx.try_recv().unwrap(), Ok(serde_json::json!({"ok":true})));
+    }
 }
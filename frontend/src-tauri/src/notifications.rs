@@ -0,0 +1,254 @@
+// 服务端通知的状态跟踪：state / alert / ping / update_ready
+// 各通知仍原样转发给前端；这里额外维护当前状态，供前端按需查询，而无需从原始事件流重建。
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Mutex;
+
+/// 活动告警列表上限，超出时丢弃最早的
+const MAX_ACTIVE_ALERTS: usize = 200;
+/// 超过该时长未收到 ping 视为失活
+const PING_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServiceState {
+    /// "start" | "stop" | "burst"；尚未收到 state 时为 None
+    pub phase: Option<String>,
+    pub reason: Option<String>,
+    pub extra: Option<Value>,
+    pub ts: Option<u64>,
+    /// 进入当前 phase 的时间（同一 phase 的重复通知不更新）
+    pub since: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActiveAlert {
+    /// rule_id 优先，缺省时以 metric 作为去重键
+    pub key: String,
+    pub level: String,
+    pub metric: String,
+    pub value: Option<f64>,
+    pub threshold: Option<f64>,
+    pub rule_id: Option<String>,
+    pub message: String,
+    pub ts: u64,
+    pub first_ts: u64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateReady {
+    pub component: String,
+    pub version: String,
+    pub ts: u64,
+}
+
+pub struct Notifications {
+    state: ServiceState,
+    alerts: Vec<ActiveAlert>,
+    last_ping_at: Option<u64>,
+    ping_count: u64,
+    updates: Vec<UpdateReady>,
+}
+
+fn str_field(v: &Value, key: &str) -> Option<String> {
+    v.get(key).and_then(|x| x.as_str()).map(|s| s.to_string())
+}
+
+impl Notifications {
+    pub const fn new() -> Self {
+        Self {
+            state: ServiceState { phase: None, reason: None, extra: None, ts: None, since: None },
+            alerts: Vec::new(),
+            last_ping_at: None,
+            ping_count: 0,
+            updates: Vec::new(),
+        }
+    }
+
+    pub fn on_state(&mut self, payload: &Value, now: u64) {
+        let Some(phase) = str_field(payload, "phase") else { return };
+        let ts = payload.get("ts").and_then(|t| t.as_u64()).unwrap_or(now);
+        if self.state.phase.as_deref() != Some(phase.as_str()) {
+            self.state.since = Some(ts);
+        }
+        self.state.phase = Some(phase);
+        self.state.reason = str_field(payload, "reason");
+        self.state.extra = payload.get("extra").filter(|e| !e.is_null()).cloned();
+        self.state.ts = Some(ts);
+    }
+
+    /// 当前状态；burst 超过 expires_at 后视为已回落到 start
+    pub fn state(&self, now: u64) -> ServiceState {
+        let mut s = self.state.clone();
+        let expired = s.phase.as_deref() == Some("burst")
+            && s.extra
+                .as_ref()
+                .and_then(|e| e.get("expires_at"))
+                .and_then(|t| t.as_u64())
+                .is_some_and(|t| t <= now);
+        if expired {
+            s.phase = Some("start".to_string());
+            s.reason = Some("burst_expired".to_string());
+        }
+        s
+    }
+
+    pub fn on_alert(&mut self, payload: &Value, now: u64) {
+        let metric = str_field(payload, "metric").unwrap_or_default();
+        let rule_id = str_field(payload, "rule_id");
+        let key = rule_id.clone().unwrap_or_else(|| metric.clone());
+        let ts = payload.get("ts").and_then(|t| t.as_u64()).unwrap_or(now);
+        let alert = ActiveAlert {
+            key: key.clone(),
+            level: str_field(payload, "level").unwrap_or_else(|| "info".to_string()),
+            metric,
+            value: payload.get("value").and_then(|v| v.as_f64()),
+            threshold: payload.get("threshold").and_then(|v| v.as_f64()),
+            rule_id,
+            message: str_field(payload, "message").unwrap_or_default(),
+            ts,
+            first_ts: ts,
+            count: 1,
+        };
+        if let Some(existing) = self.alerts.iter_mut().find(|a| a.key == key) {
+            let (first_ts, count) = (existing.first_ts, existing.count + 1);
+            *existing = ActiveAlert { first_ts, count, ..alert };
+        } else {
+            self.alerts.push(alert);
+            if self.alerts.len() > MAX_ACTIVE_ALERTS {
+                self.alerts.remove(0);
+            }
+        }
+    }
+
+    /// key 为 None 时清空全部；返回移除的条数
+    pub fn clear_alerts(&mut self, key: Option<&str>) -> usize {
+        let before = self.alerts.len();
+        match key {
+            Some(k) => self.alerts.retain(|a| a.key != k),
+            None => self.alerts.clear(),
+        }
+        before - self.alerts.len()
+    }
+
+    pub fn on_ping(&mut self, now: u64) {
+        self.last_ping_at = Some(now);
+        self.ping_count += 1;
+    }
+
+    pub fn liveness(&self, now: u64) -> Value {
+        let age = self.last_ping_at.map(|t| now.saturating_sub(t));
+        serde_json::json!({
+            "last_ping_at": self.last_ping_at,
+            "last_ping_age_ms": age,
+            "ping_count": self.ping_count,
+            // 从未收到 ping 时无法判断（服务端心跳为可选），返回 null
+            "alive": age.map(|a| a <= PING_TIMEOUT_MS),
+            "timeout_ms": PING_TIMEOUT_MS,
+        })
+    }
+
+    pub fn on_update_ready(&mut self, payload: &Value, now: u64) {
+        let (Some(component), Some(version)) = (str_field(payload, "component"), str_field(payload, "version")) else { return };
+        self.updates.retain(|u| u.component != component);
+        self.updates.push(UpdateReady { component, version, ts: now });
+    }
+}
+
+static NOTIFICATIONS: Mutex<Notifications> = Mutex::new(Notifications::new());
+
+/// 由 dispatch_notification 调用；非上述四类通知直接忽略
+pub fn observe(event: &str, payload: &Value) {
+    let now = crate::now_millis();
+    let Ok(mut n) = NOTIFICATIONS.lock() else { return };
+    match event {
+        "state" => n.on_state(payload, now),
+        "alert" => n.on_alert(payload, now),
+        "ping" => n.on_ping(now),
+        "update_ready" => n.on_update_ready(payload, now),
+        _ => {}
+    }
+}
+
+/// 桥接侧告警规则解除时移除对应的活动告警
+pub fn resolve_alert(key: &str) {
+    if let Ok(mut n) = NOTIFICATIONS.lock() {
+        n.clear_alerts(Some(key));
+    }
+}
+
+fn lock() -> Result<std::sync::MutexGuard<'static, Notifications>, String> {
+    NOTIFICATIONS.lock().map_err(|_| "notifications lock poisoned".to_string())
+}
+
+#[tauri::command]
+pub fn get_service_state() -> Result<ServiceState, String> {
+    Ok(lock()?.state(crate::now_millis()))
+}
+
+#[tauri::command]
+pub fn list_service_alerts() -> Result<Vec<ActiveAlert>, String> {
+    Ok(lock()?.alerts.clone())
+}
+
+#[tauri::command]
+pub fn clear_service_alerts(key: Option<String>) -> Result<usize, String> {
+    Ok(lock()?.clear_alerts(key.as_deref()))
+}
+
+#[tauri::command]
+pub fn get_service_liveness() -> Result<Value, String> {
+    Ok(lock()?.liveness(crate::now_millis()))
+}
+
+#[tauri::command]
+pub fn get_update_ready() -> Result<Vec<UpdateReady>, String> {
+    Ok(lock()?.updates.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tracks_phase_and_burst_expiry() {
+        let mut n = Notifications::new();
+        n.on_state(&json!({ "ts": 10, "phase": "start" }), 10);
+        n.on_state(&json!({ "ts": 20, "phase": "start" }), 20);
+        assert_eq!(n.state(20).since, Some(10));
+
+        n.on_state(&json!({ "ts": 30, "phase": "burst", "extra": { "expires_at": 100 } }), 30);
+        assert_eq!(n.state(50).phase.as_deref(), Some("burst"));
+        assert_eq!(n.state(100).phase.as_deref(), Some("start"));
+    }
+
+    #[test]
+    fn dedupes_alerts_by_rule_or_metric() {
+        let mut n = Notifications::new();
+        let a = json!({ "level": "warn", "metric": "cpu.usage_percent", "value": 91.0, "rule_id": "cpu_hot", "message": "hot", "ts": 1 });
+        n.on_alert(&a, 1);
+        n.on_alert(&json!({ "level": "error", "metric": "cpu.usage_percent", "value": 99.0, "rule_id": "cpu_hot", "message": "hotter", "ts": 2 }), 2);
+        n.on_alert(&json!({ "level": "info", "metric": "memory", "value": 1.0, "message": "m", "ts": 3 }), 3);
+        assert_eq!(n.alerts.len(), 2);
+        assert_eq!(n.alerts[0].level, "error");
+        assert_eq!((n.alerts[0].first_ts, n.alerts[0].count), (1, 2));
+        assert_eq!(n.clear_alerts(Some("memory")), 1);
+        assert_eq!(n.clear_alerts(None), 1);
+    }
+
+    #[test]
+    fn liveness_and_updates() {
+        let mut n = Notifications::new();
+        assert!(n.liveness(0)["alive"].is_null());
+        n.on_ping(1_000);
+        assert_eq!(n.liveness(2_000)["alive"], json!(true));
+        assert_eq!(n.liveness(1_000 + PING_TIMEOUT_MS + 1)["alive"], json!(false));
+
+        n.on_update_ready(&json!({ "component": "service", "version": "1.0.1" }), 5);
+        n.on_update_ready(&json!({ "component": "service", "version": "1.0.2" }), 6);
+        assert_eq!(n.updates.len(), 1);
+        assert_eq!(n.updates[0].version, "1.0.2");
+    }
+}
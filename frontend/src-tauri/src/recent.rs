@@ -0,0 +1,115 @@
+// 进程内 metrics 环形缓冲
+// 事件桥收到的每帧实时 metrics（即转发给前端的负载）以原始 JSON 字符串追加到缓冲，
+// 按帧数与内存预算（字符串字节数）双重限制，查询时才解析；回放期间的帧不记录。
+// 新窗口或 webview 重载后可经 get_recent_metrics 立即回填图表，无需 query_history 往返。
+
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+const MAX_FRAMES: usize = 3_600;
+const MAX_BYTES: usize = 32 * 1024 * 1024;
+
+pub struct RecentBuffer {
+    frames: VecDeque<(u64, Box<str>)>,
+    bytes: usize,
+    max_frames: usize,
+    max_bytes: usize,
+}
+
+impl RecentBuffer {
+    pub const fn new(max_frames: usize, max_bytes: usize) -> Self {
+        Self { frames: VecDeque::new(), bytes: 0, max_frames, max_bytes }
+    }
+
+    pub fn push(&mut self, ts: u64, raw: &str) {
+        // 时间戳回退（服务端重启/时钟调整）时清空，保证缓冲按 ts 单调
+        if self.frames.back().is_some_and(|(last, _)| ts < *last) {
+            self.frames.clear();
+            self.bytes = 0;
+        }
+        self.frames.push_back((ts, raw.into()));
+        self.bytes += raw.len();
+        while self.frames.len() > self.max_frames || (self.bytes > self.max_bytes && self.frames.len() > 1) {
+            if let Some((_, f)) = self.frames.pop_front() {
+                self.bytes -= f.len();
+            }
+        }
+    }
+
+    /// ts > since_ts 的帧，按模块过滤后取最新的 max_points 帧，按时间升序返回
+    pub fn query(&self, since_ts: u64, modules: Option<&[String]>, max_points: usize) -> Vec<Value> {
+        let start = self.frames.partition_point(|(ts, _)| *ts <= since_ts);
+        let mut items: Vec<Value> = self
+            .frames
+            .range(start..)
+            .rev()
+            .filter_map(|(_, f)| serde_json::from_str::<Value>(f).ok())
+            .filter_map(|f| crate::stream::filter_frame(&f, modules))
+            .take(max_points)
+            .collect();
+        items.reverse();
+        items
+    }
+}
+
+static RECENT: Mutex<RecentBuffer> = Mutex::new(RecentBuffer::new(MAX_FRAMES, MAX_BYTES));
+
+/// 由 dispatch_notification 对每帧实时 metrics 调用；payload 为转发给前端的负载
+pub fn record(frame: &Value, payload: &RawValue) {
+    let ts = frame.get("ts").and_then(|t| t.as_u64()).unwrap_or_else(crate::now_millis);
+    if let Ok(mut r) = RECENT.lock() {
+        r.push(ts, payload.get());
+    }
+}
+
+#[tauri::command]
+pub fn get_recent_metrics(
+    since_ts: Option<u64>,
+    modules: Option<Vec<String>>,
+    max_points: Option<usize>,
+    downsample: Option<crate::downsample::DownsampleSpec>,
+) -> Result<Value, String> {
+    let modules = modules.filter(|m| !m.is_empty());
+    let r = RECENT.lock().map_err(|_| "recent buffer lock poisoned".to_string())?;
+    let mut items = r.query(since_ts.unwrap_or(0), modules.as_deref(), max_points.unwrap_or(usize::MAX));
+    drop(r);
+    if let Some(spec) = &downsample {
+        items = crate::downsample::apply(items, spec);
+    }
+    Ok(serde_json::json!({ "ok": true, "items": items }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bounded_by_count_and_bytes() {
+        let mut r = RecentBuffer::new(3, 30);
+        for ts in 1..=5 {
+            r.push(ts, &format!("{{\"ts\":{}}}", ts)); // 8 字节
+        }
+        assert_eq!(r.frames.len(), 3);
+        let big = r#"{"ts":6,"cpu":{"x":1}}"#; // 22 字节
+        r.push(6, big);
+        assert_eq!(r.frames.iter().map(|f| f.0).collect::<Vec<_>>(), vec![5, 6]);
+        assert_eq!(r.bytes, 30);
+        r.push(1, r#"{"ts":1}"#);
+        assert_eq!(r.frames.len(), 1);
+    }
+
+    #[test]
+    fn queries_latest_points_after_since() {
+        let mut r = RecentBuffer::new(10, usize::MAX);
+        for ts in 1..=6 {
+            r.push(ts, &json!({ "ts": ts, "cpu": { "usage_percent": ts }, "disk": {} }).to_string());
+        }
+        let mods = vec!["cpu".to_string()];
+        let items = r.query(2, Some(&mods), 2);
+        assert_eq!(items, vec![json!({ "ts": 5, "cpu": { "usage_percent": 5 } }), json!({ "ts": 6, "cpu": { "usage_percent": 6 } })]);
+        assert_eq!(r.query(0, None, usize::MAX).len(), 6);
+    }
+}
@@ -0,0 +1,434 @@
+// 离线回放：把导出的 NDJSON 历史或抓包文件作为数据源
+// 每行为一项带 ts 的 metrics 帧（export_history 的 NDJSON 输出），或一条 JSON-RPC 通知 {"method", "params"}（事件桥抓包）。
+// 回放期间事件桥收到的实时通知不再分发，播放线程按虚拟时钟（起点 + 流逝时间 × 倍速）经 dispatch_notification
+// 投递文件中的帧，因此 gap/stream/delta/recent 等下游与实时数据路径一致（本地历史库不写入回放帧）；
+// rpc_call 的 snapshot / query_history 由文件在当前回放位置的数据应答，其余方法照常转发服务端。
+
+use crate::localstore::{bucket_end, HistoryParams};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::Emitter;
+
+/// 单次最多投递的帧数，避免高倍速时长时间持锁
+const MAX_BATCH: usize = 256;
+const IDLE_WAIT_MS: u64 = 500;
+const MAX_WAIT_MS: u64 = 200;
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 1_000.0;
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub ts: i64,
+    pub method: String,
+    pub payload: Value,
+}
+
+/// 解析回放文件；无法解析的行报告行号
+pub fn parse(text: &str) -> Result<Vec<Frame>, String> {
+    let mut frames = Vec::new();
+    let mut last_ts = None;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let v: Value = serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        let (method, payload) = match v.get("method").and_then(|m| m.as_str()) {
+            Some(m) => {
+                let payload = match v.get("params") {
+                    Some(Value::Array(a)) => a.first().cloned().unwrap_or(Value::Null),
+                    Some(p) => p.clone(),
+                    None => Value::Null,
+                };
+                (m.to_string(), payload)
+            }
+            None => ("metrics".to_string(), v),
+        };
+        // 非 metrics 通知通常不带 ts，沿用前一帧的 ts
+        let ts = match payload.get("ts").and_then(|t| t.as_i64()).or(last_ts) {
+            Some(ts) => ts,
+            None if method == "metrics" => return Err(format!("line {}: metrics frame without ts", i + 1)),
+            None => continue,
+        };
+        last_ts = Some(ts);
+        frames.push(Frame { ts, method, payload });
+    }
+    if frames.is_empty() {
+        return Err("no frames".into());
+    }
+    frames.sort_by_key(|f| f.ts);
+    Ok(frames)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayStatus {
+    pub active: bool,
+    pub path: String,
+    pub playing: bool,
+    pub speed: f64,
+    pub position_ts: i64,
+    pub from_ts: i64,
+    pub to_ts: i64,
+    pub index: usize,
+    pub frames: usize,
+}
+
+pub struct Replay {
+    path: String,
+    frames: Vec<Frame>,
+    /// 下一帧待投递的下标
+    pos: usize,
+    playing: bool,
+    speed: f64,
+    anchor_ts: i64,
+    anchor_wall: u64,
+    generation: u64,
+}
+
+impl Replay {
+    pub fn new(path: String, frames: Vec<Frame>) -> Self {
+        let anchor_ts = frames.first().map(|f| f.ts).unwrap_or(0);
+        Self { path, frames, pos: 0, playing: false, speed: 1.0, anchor_ts, anchor_wall: 0, generation: 0 }
+    }
+
+    pub fn cursor_ts(&self, wall: u64) -> i64 {
+        if !self.playing {
+            return self.anchor_ts;
+        }
+        self.anchor_ts + (wall.saturating_sub(self.anchor_wall) as f64 * self.speed) as i64
+    }
+
+    fn reanchor(&mut self, wall: u64) {
+        self.anchor_ts = self.cursor_ts(wall);
+        self.anchor_wall = wall;
+    }
+
+    pub fn play(&mut self, wall: u64) {
+        if self.pos >= self.frames.len() {
+            self.seek(self.frames[0].ts, wall);
+        }
+        self.reanchor(wall);
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self, wall: u64) {
+        self.reanchor(wall);
+        self.playing = false;
+    }
+
+    pub fn set_speed(&mut self, speed: f64, wall: u64) {
+        self.reanchor(wall);
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// 定位到 ts；返回应立即投递的最近一帧 metrics，使界面即时反映新位置
+    pub fn seek(&mut self, ts: i64, wall: u64) -> Option<usize> {
+        self.pos = self.frames.partition_point(|f| f.ts <= ts);
+        self.anchor_ts = ts;
+        self.anchor_wall = wall;
+        self.frames[..self.pos].iter().rposition(|f| f.method == "metrics")
+    }
+
+    /// 取出到当前虚拟时间为止待投递的帧；到达末尾时自动暂停
+    pub fn due(&mut self, wall: u64) -> std::ops::Range<usize> {
+        let start = self.pos;
+        if !self.playing {
+            return start..start;
+        }
+        let cursor = self.cursor_ts(wall);
+        while self.pos < self.frames.len() && self.frames[self.pos].ts <= cursor && self.pos - start < MAX_BATCH {
+            self.pos += 1;
+        }
+        if self.pos >= self.frames.len() {
+            self.anchor_ts = self.frames[self.frames.len() - 1].ts;
+            self.anchor_wall = wall;
+            self.playing = false;
+        }
+        start..self.pos
+    }
+
+    /// 距下一帧的真实等待时间
+    pub fn next_wait_ms(&self, wall: u64) -> u64 {
+        match (self.playing, self.frames.get(self.pos)) {
+            (true, Some(f)) => ((f.ts - self.cursor_ts(wall)).max(0) as f64 / self.speed) as u64,
+            _ => IDLE_WAIT_MS,
+        }
+    }
+
+    fn played_metrics(&self) -> impl DoubleEndedIterator<Item = &Frame> {
+        self.frames[..self.pos].iter().filter(|f| f.method == "metrics")
+    }
+
+    /// 当前位置之前最近的一帧 metrics
+    pub fn snapshot(&self) -> Option<Value> {
+        self.played_metrics().next_back().map(|f| f.payload.clone())
+    }
+
+    /// 与服务端 query_history 形态一致；只返回当前位置之前的数据，to_ts = 0 取当前位置
+    pub fn query_history(&self, p: &HistoryParams, wall: u64) -> Value {
+        let cursor = self.cursor_ts(wall);
+        let to = p.resolved_to(cursor).min(cursor);
+        let want: Vec<String> = p.want().into_iter().collect();
+        let bucket = crate::rollup::tier_for(p.agg.as_deref()).map(|t| t.bucket_ms).or(p.bucket_ms());
+        let mut items: Vec<Value> = Vec::new();
+        let mut last_bucket = None;
+        for f in self.played_metrics().filter(|f| f.ts >= p.from_ts && f.ts <= to) {
+            let Some(mut item) = crate::stream::filter_frame(&f.payload, Some(&want)) else { continue };
+            if let Some(b) = bucket {
+                // 每桶保留最后一条，ts 对齐到桶结束时间
+                let end = bucket_end(f.ts, b);
+                item["ts"] = serde_json::json!(end);
+                if last_bucket == Some(end) {
+                    items.pop();
+                }
+                last_bucket = Some(end);
+            }
+            if let Some(m) = item.as_object_mut() {
+                m.remove("seq");
+            }
+            items.push(item);
+        }
+        serde_json::json!({ "ok": true, "items": items })
+    }
+
+    pub fn status(&self, wall: u64) -> ReplayStatus {
+        ReplayStatus {
+            active: true,
+            path: self.path.clone(),
+            playing: self.playing,
+            speed: self.speed,
+            position_ts: self.cursor_ts(wall),
+            from_ts: self.frames.first().map(|f| f.ts).unwrap_or(0),
+            to_ts: self.frames.last().map(|f| f.ts).unwrap_or(0),
+            index: self.pos,
+            frames: self.frames.len(),
+        }
+    }
+}
+
+static REPLAY: Mutex<Option<Replay>> = Mutex::new(None);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static WAKE: Condvar = Condvar::new();
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 回放期间事件桥不分发实时通知，本地历史库不写入
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+fn emit_status(app: &tauri::AppHandle, status: Option<ReplayStatus>) {
+    let payload = match status {
+        Some(s) => serde_json::json!(s),
+        None => serde_json::json!({ "active": false }),
+    };
+    let _ = app.emit("replay_state", payload);
+}
+
+fn dispatch(app: &tauri::AppHandle, frames: &[Frame]) {
+    for f in frames {
+        if let Ok(raw) = serde_json::value::to_raw_value(&f.payload) {
+            crate::dispatch_notification(app, &f.method, &raw);
+        }
+    }
+}
+
+fn spawn_player(app: tauri::AppHandle, generation: u64) {
+    std::thread::spawn(move || loop {
+        let (batch, wait, ended) = {
+            let Ok(mut guard) = REPLAY.lock() else { return };
+            let Some(r) = guard.as_mut().filter(|r| r.generation == generation) else { return };
+            let wall = crate::now_millis();
+            let was_playing = r.playing;
+            let range = r.due(wall);
+            let batch = r.frames[range].to_vec();
+            let ended = was_playing && !r.playing;
+            (batch, r.next_wait_ms(wall).min(MAX_WAIT_MS), ended.then(|| r.status(wall)))
+        };
+        dispatch(&app, &batch);
+        if let Some(status) = ended {
+            emit_status(&app, Some(status));
+        }
+        if let Ok(guard) = REPLAY.lock() {
+            let _ = WAKE.wait_timeout(guard, Duration::from_millis(wait.max(1)));
+        }
+    });
+}
+
+fn with_replay<T>(f: impl FnOnce(&mut Replay, u64) -> T) -> Result<T, String> {
+    let mut guard = REPLAY.lock().map_err(|_| "replay lock poisoned".to_string())?;
+    let r = guard.as_mut().ok_or_else(|| "no replay loaded".to_string())?;
+    let out = f(r, crate::now_millis());
+    drop(guard);
+    WAKE.notify_all();
+    Ok(out)
+}
+
+/// 回放期间由 rpc_call 调用：snapshot / query_history 由文件应答，其余返回 None 照常转发
+pub fn handle_rpc(method: &str, params: Option<&Value>) -> Option<Result<Value, String>> {
+    if !is_active() || !matches!(method, "snapshot" | "query_history") {
+        return None;
+    }
+    let result = with_replay(|r, wall| match method {
+        "snapshot" => Ok(r.snapshot().unwrap_or(Value::Null)),
+        _ => {
+            let p: HistoryParams = serde_json::from_value(params.cloned().unwrap_or(Value::Null))
+                .map_err(|e| format!("invalid query_history params: {}", e))?;
+            Ok(r.query_history(&p, wall))
+        }
+    });
+    Some(result.and_then(|r| r))
+}
+
+// 进入/离开回放或跳转后，帧序列不再连续：断档检测与派生指标的状态都要重新起算
+fn reset_live_state() {
+    crate::gap::reset();
+    crate::derived::reset_live();
+}
+
+#[tauri::command]
+pub async fn replay_open(app: tauri::AppHandle, path: String) -> Result<ReplayStatus, String> {
+    let text = tauri::async_runtime::spawn_blocking({
+        let path = path.clone();
+        move || std::fs::read_to_string(&path)
+    })
+    .await
+    .map_err(|e| format!("join error: {}", e))?
+    .map_err(|e| format!("read {}: {}", path, e))?;
+    let frames = parse(&text)?;
+
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut replay = Replay::new(path, frames);
+    replay.generation = generation;
+    let wall = crate::now_millis();
+    let first = replay.seek(replay.frames[0].ts, wall).map(|i| replay.frames[i].clone());
+    let status = replay.status(wall);
+    *REPLAY.lock().map_err(|_| "replay lock poisoned".to_string())? = Some(replay);
+    ACTIVE.store(true, Ordering::SeqCst);
+    WAKE.notify_all();
+    reset_live_state();
+    crate::log_line("INFO", &format!("replay opened: {} ({} frames)", status.path, status.frames));
+    dispatch(&app, first.as_slice());
+    spawn_player(app.clone(), generation);
+    emit_status(&app, Some(status.clone()));
+    Ok(status)
+}
+
+/// 关闭回放，恢复实时数据
+#[tauri::command]
+pub fn replay_close(app: tauri::AppHandle) -> Result<bool, String> {
+    let closed = REPLAY.lock().map_err(|_| "replay lock poisoned".to_string())?.take().is_some();
+    ACTIVE.store(false, Ordering::SeqCst);
+    WAKE.notify_all();
+    reset_live_state();
+    emit_status(&app, None);
+    Ok(closed)
+}
+
+#[tauri::command]
+pub fn replay_play(app: tauri::AppHandle) -> Result<ReplayStatus, String> {
+    let status = with_replay(|r, wall| {
+        r.play(wall);
+        r.status(wall)
+    })?;
+    emit_status(&app, Some(status.clone()));
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn replay_pause(app: tauri::AppHandle) -> Result<ReplayStatus, String> {
+    let status = with_replay(|r, wall| {
+        r.pause(wall);
+        r.status(wall)
+    })?;
+    emit_status(&app, Some(status.clone()));
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn replay_seek(app: tauri::AppHandle, ts: i64) -> Result<ReplayStatus, String> {
+    let (frame, status) = with_replay(|r, wall| {
+        let frame = r.seek(ts, wall).map(|i| r.frames[i].clone());
+        (frame, r.status(wall))
+    })?;
+    reset_live_state();
+    dispatch(&app, frame.as_slice());
+    emit_status(&app, Some(status.clone()));
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn replay_set_speed(app: tauri::AppHandle, speed: f64) -> Result<ReplayStatus, String> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(format!("invalid speed: {}", speed));
+    }
+    let status = with_replay(|r, wall| {
+        r.set_speed(speed, wall);
+        r.status(wall)
+    })?;
+    emit_status(&app, Some(status.clone()));
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn replay_status() -> Result<Option<ReplayStatus>, String> {
+    let guard = REPLAY.lock().map_err(|_| "replay lock poisoned".to_string())?;
+    Ok(guard.as_ref().map(|r| r.status(crate::now_millis())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FILE: &str = r#"{"ts":1000,"seq":1,"cpu":{"usage_percent":1},"memory":{"total":8,"used":2}}
+{"method":"state","params":[{"phase":"running"}]}
+{"ts":2000,"seq":2,"cpu":{"usage_percent":2},"memory":{"total":8,"used":3}}
+
+{"method":"metrics","params":[{"ts":4000,"seq":3,"cpu":{"usage_percent":4}}]}
+"#;
+
+    #[test]
+    fn parses_items_and_captured_notifications() {
+        let frames = parse(FILE).unwrap();
+        assert_eq!(
+            frames.iter().map(|f| (f.ts, f.method.as_str())).collect::<Vec<_>>(),
+            vec![(1000, "metrics"), (1000, "state"), (2000, "metrics"), (4000, "metrics")]
+        );
+        assert!(parse("{\"ts\":1}\nnot json").unwrap_err().starts_with("line 2"));
+    }
+
+    #[test]
+    fn plays_on_virtual_clock_with_speed_and_seek() {
+        let mut r = Replay::new("f".into(), parse(FILE).unwrap());
+        assert_eq!(r.seek(1000, 0), Some(0));
+        assert_eq!(r.pos, 2);
+        r.play(10_000);
+        assert!(r.due(10_500).is_empty());
+        assert_eq!(r.due(11_000), 2..3);
+        r.set_speed(2.0, 11_000);
+        assert_eq!(r.next_wait_ms(11_000), 1_000);
+        assert_eq!(r.due(12_000), 3..4);
+        // 到达末尾自动暂停，再次播放从头开始
+        assert!(!r.playing);
+        r.play(20_000);
+        assert_eq!(r.cursor_ts(20_000), 1000);
+        assert_eq!(r.seek(3_999, 20_000), Some(2));
+    }
+
+    #[test]
+    fn serves_snapshot_and_history_up_to_cursor() {
+        let mut r = Replay::new("f".into(), parse(FILE).unwrap());
+        r.seek(2500, 0);
+        assert_eq!(r.snapshot().unwrap()["seq"], json!(2));
+        let p = HistoryParams { modules: Some(vec!["cpu".into()]), ..Default::default() };
+        assert_eq!(
+            r.query_history(&p, 0)["items"],
+            json!([{ "ts": 1000, "cpu": { "usage_percent": 1 } }, { "ts": 2000, "cpu": { "usage_percent": 2 } }])
+        );
+        let p = HistoryParams { step_ms: Some(5_000), ..Default::default() };
+        assert_eq!(r.query_history(&p, 0)["items"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,444 @@
+// 本地历史库的分级保留与汇总
+// 原始帧（metrics）汇总为 metrics_10s / metrics_1m（与服务端表名一致），数值列保留 min/max/avg/last，
+// 文本/布尔列保留 last。汇总与过期清理由写入线程周期执行，汇总进度（水位）记录在 meta 表。
+// 原始行只有在两级汇总都覆盖后才会被清理。
+
+use crate::localstore::{bucket_end, ensure_columns, row_to_item, HistoryParams, Kind, Store, COLUMNS};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::Instant;
+
+const HOUR_MS: i64 = 3_600_000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+/// 原始数据至少保留 1 小时，保证 1 分钟汇总总能从原始行计算
+const MIN_RAW_MS: i64 = HOUR_MS;
+/// 汇总滞后量，等待写入线程批量落盘
+const ROLLUP_LAG_MS: i64 = 5_000;
+/// 空闲页超过总页数 1/4 且距上次 VACUUM 超过一天时整理
+const VACUUM_MIN_PAGES: i64 = 1_024;
+const VACUUM_INTERVAL_MS: i64 = DAY_MS;
+
+pub struct Tier {
+    pub table: &'static str,
+    pub bucket_ms: i64,
+    meta_key: &'static str,
+}
+
+pub const TIERS: [Tier; 2] = [
+    Tier { table: "metrics_10s", bucket_ms: 10_000, meta_key: "rollup_10s_until" },
+    Tier { table: "metrics_1m", bucket_ms: 60_000, meta_key: "rollup_1m_until" },
+];
+
+pub fn tier_for(agg: Option<&str>) -> Option<&'static Tier> {
+    match agg.map(|a| a.to_ascii_lowercase()).as_deref() {
+        Some("10s") => Some(&TIERS[0]),
+        Some("1m") => Some(&TIERS[1]),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Retention {
+    pub raw_ms: i64,
+    pub rollup_10s_ms: i64,
+    pub rollup_1m_ms: i64,
+}
+
+impl Retention {
+    pub const fn new() -> Self {
+        Self { raw_ms: DAY_MS, rollup_10s_ms: 7 * DAY_MS, rollup_1m_ms: 90 * DAY_MS }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CompactionReport {
+    pub ts: i64,
+    pub rolled_10s: usize,
+    pub rolled_1m: usize,
+    pub deleted_raw: usize,
+    pub deleted_10s: usize,
+    pub deleted_1m: usize,
+    pub vacuumed: bool,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Min,
+    Max,
+    Avg,
+    Last,
+}
+
+impl Variant {
+    pub fn parse(s: Option<&str>) -> Result<Self, String> {
+        match s.unwrap_or("last") {
+            "min" => Ok(Variant::Min),
+            "max" => Ok(Variant::Max),
+            "avg" => Ok(Variant::Avg),
+            "last" => Ok(Variant::Last),
+            other => Err(format!("unknown value variant: {}", other)),
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Variant::Min => "min",
+            Variant::Max => "max",
+            Variant::Avg => "avg",
+            Variant::Last => "last",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ColAgg {
+    min: Option<f64>,
+    max: Option<f64>,
+    sum: f64,
+    n: u64,
+    last: Value,
+}
+
+impl ColAgg {
+    fn add(&mut self, v: &Value, kind: Kind) {
+        if v.is_null() {
+            return;
+        }
+        if let (Kind::Real, Some(f)) = (kind, v.as_f64()) {
+            self.min = Some(self.min.map_or(f, |m| m.min(f)));
+            self.max = Some(self.max.map_or(f, |m| m.max(f)));
+            self.sum += f;
+            self.n += 1;
+        }
+        self.last = v.clone();
+    }
+
+    /// 非数值列只有 last
+    pub fn get(&self, variant: Variant, kind: Kind) -> Value {
+        let f = match (kind, variant) {
+            (Kind::Real, Variant::Min) => self.min,
+            (Kind::Real, Variant::Max) => self.max,
+            (Kind::Real, Variant::Avg) => (self.n > 0).then(|| self.sum / self.n as f64),
+            _ => return self.last.clone(),
+        };
+        f.map(|f| serde_json::json!(f)).unwrap_or(Value::Null)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bucket {
+    pub ts: i64,
+    pub samples: i64,
+    pub cols: Vec<ColAgg>,
+}
+
+/// 按桶结束时间汇总升序原始行
+pub fn rollup(rows: &[(i64, Vec<Value>)], bucket_ms: i64) -> Vec<Bucket> {
+    let mut out: Vec<Bucket> = Vec::new();
+    for (ts, values) in rows {
+        let end = bucket_end(*ts, bucket_ms);
+        if out.last().map(|b| b.ts) != Some(end) {
+            out.push(Bucket { ts: end, samples: 0, cols: vec![ColAgg::default(); COLUMNS.len()] });
+        }
+        let Some(b) = out.last_mut() else { continue };
+        b.samples += 1;
+        for ((agg, v), c) in b.cols.iter_mut().zip(values).zip(COLUMNS) {
+            agg.add(v, c.kind);
+        }
+    }
+    out
+}
+
+fn rollup_columns() -> Vec<(String, &'static str)> {
+    let mut cols = Vec::new();
+    for c in COLUMNS {
+        match c.kind {
+            Kind::Real => {
+                for v in [Variant::Min, Variant::Max, Variant::Avg, Variant::Last] {
+                    cols.push((format!("{}_{}", c.name, v.suffix()), "REAL"));
+                }
+            }
+            Kind::Text => cols.push((format!("{}_last", c.name), "TEXT")),
+            Kind::Bool => cols.push((format!("{}_last", c.name), "INTEGER")),
+        }
+    }
+    cols
+}
+
+fn to_sql(v: &Value, kind: Kind) -> SqlValue {
+    match (v, kind) {
+        (Value::Number(n), _) => n.as_f64().map(SqlValue::Real).unwrap_or(SqlValue::Null),
+        (Value::String(s), _) => SqlValue::Text(s.clone()),
+        (Value::Bool(b), Kind::Bool) => SqlValue::Integer(*b as i64),
+        _ => SqlValue::Null,
+    }
+}
+
+fn bucket_values(b: &Bucket) -> Vec<SqlValue> {
+    let mut vals = vec![SqlValue::Integer(b.ts), SqlValue::Integer(b.samples)];
+    for (agg, c) in b.cols.iter().zip(COLUMNS) {
+        match c.kind {
+            Kind::Real => {
+                for v in [Variant::Min, Variant::Max, Variant::Avg, Variant::Last] {
+                    vals.push(to_sql(&agg.get(v, c.kind), c.kind));
+                }
+            }
+            _ => vals.push(to_sql(&agg.last, c.kind)),
+        }
+    }
+    vals
+}
+
+pub fn ensure_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("CREATE TABLE IF NOT EXISTS meta (key TEXT NOT NULL PRIMARY KEY, value TEXT NULL);")?;
+    let cols = rollup_columns();
+    for t in &TIERS {
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (ts INTEGER NOT NULL PRIMARY KEY, samples INTEGER NOT NULL DEFAULT 0);",
+            t.table
+        ))?;
+        ensure_columns(conn, t.table, cols.iter().map(|(n, ty)| (n.as_str(), *ty)))?;
+    }
+    Ok(())
+}
+
+pub fn meta_get(conn: &Connection, key: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |r| r.get::<_, Option<String>>(0))
+        .optional()
+        .map(Option::flatten)
+}
+
+pub fn meta_set(conn: &Connection, key: &str, value: &str) -> rusqlite::Result<()> {
+    conn.execute("INSERT OR REPLACE INTO meta(key, value) VALUES(?1, ?2)", [key, value]).map(|_| ())
+}
+
+fn watermark(conn: &Connection, tier: &Tier) -> rusqlite::Result<Option<i64>> {
+    Ok(meta_get(conn, tier.meta_key)?.and_then(|v| v.parse().ok()))
+}
+
+/// 从 meta 读取保留配置，缺省时使用默认值
+pub fn load_retention(conn: &Connection) -> Retention {
+    meta_get(conn, "retention")
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or(Retention::new())
+}
+
+impl Store {
+    fn roll_tier(&mut self, tier: &Tier, now: i64) -> rusqlite::Result<usize> {
+        let b = tier.bucket_ms;
+        let cutoff = (now - ROLLUP_LAG_MS).div_euclid(b) * b;
+        let wm = match watermark(&self.conn, tier)? {
+            Some(wm) => wm,
+            None => {
+                let oldest: Option<i64> = self.conn.query_row("SELECT MIN(ts) FROM metrics", [], |r| r.get(0))?;
+                match oldest {
+                    Some(t) => (t - 1).div_euclid(b) * b,
+                    None => return Ok(0),
+                }
+            }
+        };
+        if cutoff <= wm {
+            return Ok(0);
+        }
+        let buckets = rollup(&self.query_rows(wm + 1, cutoff)?, b);
+        let cols = rollup_columns();
+        let sql = format!(
+            "INSERT OR REPLACE INTO {}(ts, samples, {}) VALUES({})",
+            tier.table,
+            cols.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(", "),
+            (1..=cols.len() + 2).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+        );
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(&sql)?;
+            for bucket in &buckets {
+                stmt.execute(params_from_iter(bucket_values(bucket)))?;
+            }
+        }
+        meta_set(&tx, tier.meta_key, &cutoff.to_string())?;
+        tx.commit()?;
+        Ok(buckets.len())
+    }
+
+    /// 汇总已结束的桶、清理过期数据，必要时 VACUUM
+    pub fn compact(&mut self, now: i64, retention: &Retention) -> rusqlite::Result<CompactionReport> {
+        let started = Instant::now();
+        let mut report = CompactionReport { ts: now, ..Default::default() };
+        report.rolled_10s = self.roll_tier(&TIERS[0], now)?;
+        report.rolled_1m = self.roll_tier(&TIERS[1], now)?;
+        meta_set(&self.conn, "retention", &serde_json::to_string(retention).unwrap_or_default())?;
+
+        // 原始行需同时被两级汇总覆盖后才可删除
+        let mut raw_before = now - retention.raw_ms.max(MIN_RAW_MS);
+        for t in &TIERS {
+            raw_before = raw_before.min(watermark(&self.conn, t)?.unwrap_or(i64::MIN));
+        }
+        report.deleted_raw = self.conn.execute("DELETE FROM metrics WHERE ts <= ?1", [raw_before])?;
+        report.deleted_10s = self
+            .conn
+            .execute("DELETE FROM metrics_10s WHERE ts < ?1", [now - retention.rollup_10s_ms])?;
+        report.deleted_1m = self
+            .conn
+            .execute("DELETE FROM metrics_1m WHERE ts < ?1", [now - retention.rollup_1m_ms])?;
+
+        let pages: i64 = self.conn.query_row("PRAGMA page_count", [], |r| r.get(0))?;
+        let free: i64 = self.conn.query_row("PRAGMA freelist_count", [], |r| r.get(0))?;
+        let last_vacuum: i64 = meta_get(&self.conn, "last_vacuum")?.and_then(|v| v.parse().ok()).unwrap_or(0);
+        if pages >= VACUUM_MIN_PAGES && free * 4 > pages && now - last_vacuum >= VACUUM_INTERVAL_MS {
+            self.conn.execute_batch("VACUUM;")?;
+            meta_set(&self.conn, "last_vacuum", &now.to_string())?;
+            report.vacuumed = true;
+        }
+        report.elapsed_ms = started.elapsed().as_millis() as u64;
+        Ok(report)
+    }
+
+    /// 查询汇总表；水位之后尚未汇总的部分由原始行即时汇总补齐
+    pub fn query_rollup(&self, p: &HistoryParams, tier: &Tier, now: i64) -> anyhow::Result<Value> {
+        let variant = Variant::parse(p.value.as_deref()).map_err(anyhow::Error::msg)?;
+        let to = p.resolved_to(now);
+        let want = p.want();
+        let wm = watermark(&self.conn, tier)?.unwrap_or(i64::MIN);
+
+        let names: Vec<String> = COLUMNS
+            .iter()
+            .map(|c| match c.kind {
+                Kind::Real => format!("{}_{}", c.name, variant.suffix()),
+                _ => format!("{}_last", c.name),
+            })
+            .collect();
+        let sql = format!(
+            "SELECT ts, {} FROM {} WHERE ts >= ?1 AND ts <= ?2 ORDER BY ts ASC",
+            names.join(", "),
+            tier.table
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut items: Vec<Value> = stmt
+            .query_map([p.from_ts, to.min(wm)], |r| {
+                let values = COLUMNS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| r.get_ref(i + 1).map(|v| crate::localstore::from_sql(v, c.kind)))
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(row_to_item(r.get(0)?, &values, &want))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        if to > wm {
+            let tail = rollup(&self.query_rows(p.from_ts.max(wm.saturating_add(1)), to)?, tier.bucket_ms);
+            for b in tail {
+                let values: Vec<Value> = b.cols.iter().zip(COLUMNS).map(|(a, c)| a.get(variant, c.kind)).collect();
+                items.push(row_to_item(b.ts, &values, &want));
+            }
+        }
+        Ok(serde_json::json!({ "ok": true, "items": items }))
+    }
+
+    pub fn storage_report(&self) -> rusqlite::Result<Value> {
+        let pragma = |name: &str| -> rusqlite::Result<i64> { self.conn.query_row(&format!("PRAGMA {}", name), [], |r| r.get(0)) };
+        let mut tables = serde_json::Map::new();
+        for table in ["metrics", TIERS[0].table, TIERS[1].table] {
+            let (rows, oldest, newest): (i64, Option<i64>, Option<i64>) = self.conn.query_row(
+                &format!("SELECT COUNT(*), MIN(ts), MAX(ts) FROM {}", table),
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )?;
+            tables.insert(table.to_string(), serde_json::json!({ "rows": rows, "oldest_ts": oldest, "newest_ts": newest }));
+        }
+        let mut watermarks = serde_json::Map::new();
+        for t in &TIERS {
+            watermarks.insert(t.table.to_string(), serde_json::json!(watermark(&self.conn, t)?));
+        }
+        Ok(serde_json::json!({
+            "page_size": pragma("page_size")?,
+            "page_count": pragma("page_count")?,
+            "freelist_count": pragma("freelist_count")?,
+            "tables": tables,
+            "rollup_until": watermarks,
+            "last_vacuum": meta_get(&self.conn, "last_vacuum")?.and_then(|v| v.parse::<i64>().ok()),
+        }))
+    }
+}
+
+pub static RETENTION: Mutex<Retention> = Mutex::new(Retention::new());
+pub static LAST_COMPACTION: Mutex<Option<CompactionReport>> = Mutex::new(None);
+
+#[tauri::command]
+pub fn set_local_retention(raw_ms: Option<i64>, rollup_10s_ms: Option<i64>, rollup_1m_ms: Option<i64>) -> Result<Retention, String> {
+    let mut r = RETENTION.lock().map_err(|_| "retention lock poisoned".to_string())?;
+    if let Some(v) = raw_ms {
+        r.raw_ms = v.max(MIN_RAW_MS);
+    }
+    if let Some(v) = rollup_10s_ms {
+        r.rollup_10s_ms = v.max(0);
+    }
+    if let Some(v) = rollup_1m_ms {
+        r.rollup_1m_ms = v.max(0);
+    }
+    // 新配置在下一次整理时写入 meta 并生效，这里顺便请求尽快整理
+    crate::localstore::request_compaction();
+    Ok(r.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::localstore::extract;
+    use serde_json::json;
+
+    fn frame(ts: i64, cpu: f64) -> Value {
+        json!({ "ts": ts, "cpu": { "usage_percent": cpu }, "power": { "battery": { "state": if cpu > 2.0 { "charging" } else { "discharging" } } } })
+    }
+
+    #[test]
+    fn aggregates_min_max_avg_last() {
+        let rows: Vec<(i64, Vec<Value>)> = [(1_000, 1.0), (5_000, 3.0), (9_000, 2.0), (11_000, 7.0)]
+            .iter()
+            .map(|(ts, v)| {
+                let r = extract(&frame(*ts, *v)).unwrap();
+                (r.ts, r.values.iter().zip(COLUMNS).map(|(v, c)| crate::localstore::from_sql(v.into(), c.kind)).collect())
+            })
+            .collect();
+        let b = rollup(&rows, 10_000);
+        assert_eq!(b.len(), 2);
+        assert_eq!((b[0].ts, b[0].samples), (10_000, 3));
+        let cpu = &b[0].cols[0];
+        assert_eq!(
+            [Variant::Min, Variant::Max, Variant::Avg, Variant::Last].map(|v| cpu.get(v, Kind::Real)),
+            [json!(1.0), json!(3.0), json!(2.0), json!(2.0)]
+        );
+        let state = COLUMNS.iter().position(|c| c.name == "battery_state").unwrap();
+        assert_eq!(b[0].cols[state].get(Variant::Avg, Kind::Text), json!("discharging"));
+    }
+
+    #[test]
+    fn compacts_rolls_up_and_expires() {
+        let mut s = Store::open_in_memory().unwrap();
+        let rows: Vec<_> = (0..600).map(|i| extract(&frame(1 + i * 1_000, i as f64)).unwrap()).collect();
+        s.insert_batch(&rows).unwrap();
+        let retention = Retention { raw_ms: 0, rollup_10s_ms: 7 * DAY_MS, rollup_1m_ms: 90 * DAY_MS };
+
+        // raw_ms 不低于 1 小时：刚写入的数据只汇总不删除
+        let now = 600_000 + ROLLUP_LAG_MS;
+        let r = s.compact(now, &retention).unwrap();
+        assert_eq!((r.rolled_10s, r.rolled_1m, r.deleted_raw), (60, 10, 0));
+
+        // 汇总后的查询与尚未汇总的尾部拼接
+        let p = HistoryParams { from_ts: 0, to_ts: now, modules: Some(vec!["cpu".into()]), agg: Some("1m".into()), value: Some("max".into()), ..Default::default() };
+        let items = s.query(&p, now).unwrap()["items"].as_array().unwrap().clone();
+        assert_eq!(items.len(), 10);
+        assert_eq!(items[0], json!({ "ts": 60_000, "cpu": { "usage_percent": 59.0 } }));
+
+        let later = now + 2 * HOUR_MS;
+        let r = s.compact(later, &retention).unwrap();
+        assert_eq!(r.deleted_raw, 600);
+        let p = HistoryParams { value: None, ..p };
+        assert_eq!(s.query(&p, later).unwrap()["items"].as_array().unwrap().len(), 10);
+    }
+}
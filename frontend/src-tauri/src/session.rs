@@ -0,0 +1,155 @@
+// 会话状态保持与重连恢复
+// 记录 UI 经 rpc_call 设定的会话状态（start/stop 的模块、未持久化的 set_config 各字段的最后取值），
+// 事件桥每次 hello 成功后按序幂等重放，并发出 session_restored 事件。
+// burst 租约由 burst 模块自行在重连后续订，这里只负责汇报。
+
+use serde_json::{Map, Value};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tauri::Emitter;
+
+/// 服务端在桥接 hello 后约 500ms 自动 start 默认模块，重放需晚于它，否则会被覆盖
+const RESTORE_DELAY_MS: u64 = 1_000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Collect {
+    /// modules 为 None 表示按服务端默认模块启动
+    Started(Option<Value>),
+    Stopped,
+}
+
+pub struct SessionState {
+    collect: Option<Collect>,
+    config: Map<String, Value>,
+}
+
+impl SessionState {
+    pub fn new() -> Self {
+        Self { collect: None, config: Map::new() }
+    }
+
+    /// 记录一次成功的 rpc_call；与会话状态无关的方法忽略
+    pub fn record(&mut self, method: &str, params: Option<&Value>) {
+        match method {
+            "start" => {
+                let modules = params.and_then(|p| p.get("modules")).filter(|m| !m.is_null()).cloned();
+                self.collect = Some(Collect::Started(modules));
+            }
+            "stop" => self.collect = Some(Collect::Stopped),
+            "set_config" => {
+                let Some(obj) = params.and_then(|p| p.as_object()) else { return };
+                let persist = obj.get("persist").and_then(|v| v.as_bool()).unwrap_or(false);
+                for (k, v) in obj {
+                    if k == "persist" {
+                        continue;
+                    }
+                    if persist {
+                        // 已持久化的字段由服务端自行恢复，不再重放旧值
+                        self.config.remove(k);
+                        continue;
+                    }
+                    // 与服务端一致按字段整体替换（module_intervals 也是整表替换）
+                    self.config.insert(k.clone(), v.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 需要重放的调用，顺序为 start/stop -> set_config：
+    /// 服务端 start 会清空 module_intervals 并把模块重置为基础间隔，set_config 必须在其后
+    pub fn replay_plan(&self) -> Vec<(&'static str, Option<Value>)> {
+        let mut calls = Vec::new();
+        match &self.collect {
+            Some(Collect::Started(Some(modules))) => {
+                calls.push(("start", Some(serde_json::json!({ "modules": modules }))));
+            }
+            Some(Collect::Started(None)) => calls.push(("start", Some(serde_json::json!({})))),
+            // stop 为无参方法，不能传 params（否则被识别为 stop/1）
+            Some(Collect::Stopped) => calls.push(("stop", None)),
+            None => {}
+        }
+        if !self.config.is_empty() {
+            let mut p = self.config.clone();
+            p.insert("persist".into(), Value::Bool(false));
+            calls.push(("set_config", Some(Value::Object(p))));
+        }
+        calls
+    }
+}
+
+static SESSION: LazyLock<Mutex<SessionState>> = LazyLock::new(|| Mutex::new(SessionState::new()));
+
+pub fn record(method: &str, params: Option<&Value>) {
+    if let Ok(mut s) = SESSION.lock() {
+        s.record(method, params);
+    }
+}
+
+/// 事件桥 hello 成功后调用：延迟重放会话状态并发出 session_restored
+pub fn restore_after_hello(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(RESTORE_DELAY_MS));
+        let plan = SESSION.lock().map(|s| s.replay_plan()).unwrap_or_default();
+        let mut replayed = Vec::new();
+        let mut errors = Vec::new();
+        for (method, params) in plan {
+            match crate::call_over_named_pipe(method, params) {
+                Ok(_) => replayed.push(method.to_string()),
+                Err(e) => {
+                    crate::log_line("WARN", &format!("session restore {} failed: {}", method, e));
+                    errors.push(serde_json::json!({ "method": method, "error": e.to_string() }));
+                }
+            }
+        }
+        if crate::burst::has_leases() {
+            replayed.push("burst".to_string());
+        }
+        crate::log_line("INFO", &format!("session restored: [{}]", replayed.join(",")));
+        let _ = app.emit(
+            "session_restored",
+            serde_json::json!({
+                "ts": crate::now_millis(),
+                "replayed": replayed,
+                "errors": errors,
+            }),
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn empty_session_replays_nothing() {
+        assert!(SessionState::new().replay_plan().is_empty());
+    }
+
+    #[test]
+    fn keeps_last_config_and_start_and_replays_start_first() {
+        let mut s = SessionState::new();
+        s.record("set_config", Some(&json!({ "module_intervals": { "cpu": 300 }, "persist": false })));
+        s.record("set_config", Some(&json!({ "module_intervals": { "memory": 1200 } })));
+        s.record("set_config", Some(&json!({ "base_interval_ms": 2000, "persist": true })));
+        s.record("start", Some(&json!({ "modules": ["cpu"] })));
+        s.record("start", Some(&json!({ "modules": ["cpu", "disk"] })));
+        s.record("snapshot", Some(&json!({})));
+
+        let plan = s.replay_plan();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0], ("start", Some(json!({ "modules": ["cpu", "disk"] }))));
+        // 服务端整表替换 module_intervals，只保留最后一次
+        assert_eq!(plan[1], ("set_config", Some(json!({ "module_intervals": { "memory": 1200 }, "persist": false }))));
+    }
+
+    #[test]
+    fn stop_replays_without_params() {
+        let mut s = SessionState::new();
+        s.record("start", None);
+        s.record("stop", None);
+        assert_eq!(s.replay_plan(), vec![("stop", None)]);
+    }
+}
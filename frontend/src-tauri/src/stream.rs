@@ -0,0 +1,329 @@
+// 基于 tauri::ipc::Channel 的 metrics 专用推送
+// 每个订阅独立的有界队列 + 发送线程：同一订阅内严格按到达顺序发送；队列满时丢弃最旧的帧并计数，
+// 在下一条消息中通过 dropped 告知前端。
+// Channel::send 只在 eval 失败时报错，前端通道释放或页面重载后仍会“成功”，因此：
+// - 前端处理完消息后以 stream_ack 回报 seq，未确认的消息达到 max_in_flight 即暂停发送（积压转为丢弃最旧帧）；
+//   窗口满且超过 STALL_MS 无确认视为前端已失联，结束订阅；
+// - 所属 webview 开始加载新页面（导航/重载）或窗口关闭时结束订阅。
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tauri::ipc::Channel;
+
+const DEFAULT_MAX_QUEUE: usize = 64;
+const MAX_QUEUE_LIMIT: usize = 4_096;
+const DEFAULT_MAX_IN_FLIGHT: usize = 8;
+const MAX_IN_FLIGHT_LIMIT: usize = 256;
+/// 在途窗口满后无确认的最长时间
+const STALL_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamMessage {
+    /// 订阅内递增编号（含被丢弃的帧），前端可据此判断连续性
+    pub seq: u64,
+    /// 自上一条消息以来因积压被丢弃的帧数
+    pub dropped: u64,
+    pub frame: Value,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct StreamStats {
+    pub id: u64,
+    pub window: String,
+    pub modules: Option<Vec<String>>,
+    pub queued: usize,
+    pub in_flight: usize,
+    pub sent: u64,
+    pub dropped: u64,
+}
+
+struct StreamQueue {
+    items: VecDeque<(u64, Value)>,
+    cap: usize,
+    next_seq: u64,
+    dropped_pending: u64,
+    dropped_total: u64,
+    sent: u64,
+    /// 已发送未确认的 seq，按发送顺序
+    in_flight: VecDeque<u64>,
+    max_in_flight: usize,
+    /// 最近一次确认推进（或窗口由空转为非空）的时间
+    last_progress: u64,
+    closed: bool,
+}
+
+impl StreamQueue {
+    fn new(cap: usize, max_in_flight: usize) -> Self {
+        Self {
+            items: VecDeque::new(),
+            cap: cap.clamp(1, MAX_QUEUE_LIMIT),
+            next_seq: 0,
+            dropped_pending: 0,
+            dropped_total: 0,
+            sent: 0,
+            in_flight: VecDeque::new(),
+            max_in_flight: max_in_flight.clamp(1, MAX_IN_FLIGHT_LIMIT),
+            last_progress: 0,
+            closed: false,
+        }
+    }
+
+    /// 有待发帧且在途窗口未满
+    fn ready(&self) -> bool {
+        !self.items.is_empty() && self.in_flight.len() < self.max_in_flight
+    }
+
+    /// 窗口已满且长时间没有确认
+    fn stalled(&self, now: u64) -> bool {
+        self.in_flight.len() >= self.max_in_flight && now.saturating_sub(self.last_progress) >= STALL_MS
+    }
+
+    /// 累计确认：seq 及之前的消息全部出窗
+    fn ack(&mut self, seq: u64, now: u64) {
+        let before = self.in_flight.len();
+        while self.in_flight.front().is_some_and(|s| *s <= seq) {
+            self.in_flight.pop_front();
+        }
+        if self.in_flight.len() != before {
+            self.last_progress = now;
+        }
+    }
+
+    fn push(&mut self, frame: Value) {
+        self.next_seq += 1;
+        if self.items.len() >= self.cap {
+            self.items.pop_front();
+            self.dropped_pending += 1;
+            self.dropped_total += 1;
+        }
+        self.items.push_back((self.next_seq, frame));
+    }
+
+    fn pop(&mut self, now: u64) -> Option<StreamMessage> {
+        let (seq, frame) = self.items.pop_front()?;
+        let dropped = std::mem::take(&mut self.dropped_pending);
+        self.sent += 1;
+        if self.in_flight.is_empty() {
+            self.last_progress = now;
+        }
+        self.in_flight.push_back(seq);
+        Some(StreamMessage { seq, dropped, frame })
+    }
+}
+
+struct Subscription {
+    id: u64,
+    window: String,
+    modules: Option<Vec<String>>,
+    shared: Arc<(Mutex<StreamQueue>, Condvar)>,
+}
+
+impl Subscription {
+    fn close(&self) {
+        let (queue, cv) = &*self.shared;
+        if let Ok(mut q) = queue.lock() {
+            q.closed = true;
+        }
+        cv.notify_all();
+    }
+}
+
+/// 按模块过滤：保留 ts/seq/derived 与所选模块；帧中不含任何所选模块时返回 None
+pub fn filter_frame(frame: &Value, modules: Option<&[String]>) -> Option<Value> {
+    let Some(modules) = modules else { return Some(frame.clone()) };
+    let obj = frame.as_object()?;
+    let mut out = Map::new();
+    for key in ["ts", "seq", "derived"] {
+        if let Some(v) = obj.get(key) {
+            out.insert(key.to_string(), v.clone());
+        }
+    }
+    let mut hit = false;
+    for m in modules {
+        if let Some(v) = obj.get(m) {
+            out.insert(m.clone(), v.clone());
+            hit = true;
+        }
+    }
+    hit.then_some(Value::Object(out))
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static STREAMS: Mutex<Vec<Subscription>> = Mutex::new(Vec::new());
+
+fn remove_where<F: Fn(&Subscription) -> bool>(pred: F) -> usize {
+    let Ok(mut streams) = STREAMS.lock() else { return 0 };
+    let before = streams.len();
+    streams.retain(|s| {
+        if pred(s) {
+            s.close();
+            false
+        } else {
+            true
+        }
+    });
+    before - streams.len()
+}
+
+/// 由 dispatch_notification 对每帧 metrics 调用
+pub fn publish(frame: &Value) {
+    let Ok(streams) = STREAMS.lock() else { return };
+    for s in streams.iter() {
+        let Some(filtered) = filter_frame(frame, s.modules.as_deref()) else { continue };
+        let (queue, cv) = &*s.shared;
+        if let Ok(mut q) = queue.lock() {
+            q.push(filtered);
+        }
+        cv.notify_one();
+    }
+}
+
+/// 窗口关闭或其 webview 开始加载新页面时调用：旧页面的 Channel 已失效
+pub fn remove_window(label: &str) {
+    remove_where(|s| s.window == label);
+}
+
+fn spawn_sender(id: u64, channel: Channel<StreamMessage>, shared: Arc<(Mutex<StreamQueue>, Condvar)>) {
+    std::thread::spawn(move || {
+        let (queue, cv) = &*shared;
+        loop {
+            let msg = {
+                let Ok(mut q) = queue.lock() else { return };
+                while !q.ready() && !q.closed {
+                    if q.stalled(crate::now_millis()) {
+                        drop(q);
+                        crate::log_line("INFO", &format!("stream {} ended: no ack for {}ms", id, STALL_MS));
+                        remove_where(|s| s.id == id);
+                        return;
+                    }
+                    // 定时醒来检查是否停滞
+                    q = match cv.wait_timeout(q, Duration::from_millis(1_000)) {
+                        Ok((q, _)) => q,
+                        Err(_) => return,
+                    };
+                }
+                if q.closed {
+                    return;
+                }
+                q.pop(crate::now_millis())
+            };
+            let Some(msg) = msg else { continue };
+            if let Err(e) = channel.send(msg) {
+                crate::log_line("INFO", &format!("stream {} ended: {}", id, e));
+                remove_where(|s| s.id == id);
+                return;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn subscribe_stream(
+    window: tauri::Window,
+    channel: Channel<StreamMessage>,
+    modules: Option<Vec<String>>,
+    max_queue: Option<usize>,
+    max_in_flight: Option<usize>,
+) -> Result<u64, String> {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    let queue = StreamQueue::new(max_queue.unwrap_or(DEFAULT_MAX_QUEUE), max_in_flight.unwrap_or(DEFAULT_MAX_IN_FLIGHT));
+    let shared = Arc::new((Mutex::new(queue), Condvar::new()));
+    let modules = modules.filter(|m| !m.is_empty());
+    STREAMS
+        .lock()
+        .map_err(|_| "stream lock poisoned".to_string())?
+        .push(Subscription { id, window: window.label().to_string(), modules, shared: shared.clone() });
+    spawn_sender(id, channel, shared);
+    Ok(id)
+}
+
+/// 前端处理完 seq（含）之前的消息后确认，腾出在途窗口
+#[tauri::command]
+pub fn stream_ack(stream_id: u64, seq: u64) -> bool {
+    let Ok(streams) = STREAMS.lock() else { return false };
+    let Some(s) = streams.iter().find(|s| s.id == stream_id) else { return false };
+    let (queue, cv) = &*s.shared;
+    if let Ok(mut q) = queue.lock() {
+        q.ack(seq, crate::now_millis());
+    }
+    cv.notify_one();
+    true
+}
+
+#[tauri::command]
+pub fn unsubscribe_stream(stream_id: u64) -> bool {
+    remove_where(|s| s.id == stream_id) > 0
+}
+
+#[tauri::command]
+pub fn stream_stats() -> Result<Vec<StreamStats>, String> {
+    let streams = STREAMS.lock().map_err(|_| "stream lock poisoned".to_string())?;
+    Ok(streams
+        .iter()
+        .map(|s| {
+            let (queued, in_flight, sent, dropped) = s
+                .shared
+                .0
+                .lock()
+                .map(|q| (q.items.len(), q.in_flight.len(), q.sent, q.dropped_total))
+                .unwrap_or_default();
+            StreamStats { id: s.id, window: s.window.clone(), modules: s.modules.clone(), queued, in_flight, sent, dropped }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn drops_oldest_and_reports_gap() {
+        let mut q = StreamQueue::new(2, 8);
+        for i in 1..=4 {
+            q.push(json!(i));
+        }
+        let m = q.pop(0).unwrap();
+        assert_eq!((m.seq, m.dropped, m.frame), (3, 2, json!(3)));
+        let m = q.pop(0).unwrap();
+        assert_eq!((m.seq, m.dropped), (4, 0));
+        assert!(q.pop(0).is_none());
+        assert_eq!((q.sent, q.dropped_total), (2, 2));
+    }
+
+    #[test]
+    fn filters_modules() {
+        let frame = json!({ "ts": 1, "seq": 2, "cpu": { "usage_percent": 1.0 }, "disk": {} });
+        let mods = vec!["cpu".to_string()];
+        assert_eq!(
+            filter_frame(&frame, Some(&mods)),
+            Some(json!({ "ts": 1, "seq": 2, "cpu": { "usage_percent": 1.0 } }))
+        );
+        assert_eq!(filter_frame(&frame, Some(&["gpu".to_string()])), None);
+        assert_eq!(filter_frame(&frame, None), Some(frame.clone()));
+    }
+
+    #[test]
+    fn in_flight_window_pauses_until_ack_and_detects_stall() {
+        let mut q = StreamQueue::new(8, 2);
+        for i in 1..=3 {
+            q.push(json!(i));
+        }
+        q.pop(1_000).unwrap();
+        q.pop(1_000).unwrap();
+        // 窗口已满，第 3 帧等待确认
+        assert!(!q.ready());
+        assert!(!q.stalled(1_000 + STALL_MS - 1));
+        assert!(q.stalled(1_000 + STALL_MS));
+        q.ack(1, 2_000);
+        assert!(q.ready());
+        assert!(!q.stalled(2_000 + STALL_MS - 1));
+        // 重复或过期的确认不推进
+        q.ack(1, 9_000);
+        assert_eq!(q.last_progress, 2_000);
+    }
+}
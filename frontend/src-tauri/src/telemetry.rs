@@ -0,0 +1,263 @@
+// 桥接遥测计数：按方法统计收发帧数/字节数、解码错误、重连次数、握手耗时与 RPC 延迟分布。
+// 事件桥连接与 rpc_call 短连接的帧分开计数，frames_in_total / last_frame_age_ms 只反映事件桥。
+// 由 bridge_stats 命令一次性返回，替代前端逐帧监听 bridge_rx 自行累加。
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// 延迟直方图桶上界（毫秒），最后一个计数桶收纳超出上界的调用
+const LATENCY_BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct MethodCounters {
+    pub frames_in: u64,
+    pub bytes_in: u64,
+    pub frames_out: u64,
+    pub bytes_out: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyHistogram {
+    pub buckets_ms: &'static [u64],
+    /// 长度为 buckets_ms.len() + 1
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub errors: u64,
+    pub sum_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyHistogram {
+    fn new() -> Self {
+        Self {
+            buckets_ms: &LATENCY_BUCKETS_MS,
+            counts: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            errors: 0,
+            sum_ms: 0,
+            max_ms: 0,
+        }
+    }
+
+    fn observe(&mut self, elapsed_ms: u64, ok: bool) {
+        let idx = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|b| elapsed_ms <= *b)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[idx] += 1;
+        self.count += 1;
+        self.sum_ms += elapsed_ms;
+        self.max_ms = self.max_ms.max(elapsed_ms);
+        if !ok {
+            self.errors += 1;
+        }
+    }
+}
+
+pub struct Telemetry {
+    methods: BTreeMap<String, MethodCounters>,
+    rpc_methods: BTreeMap<String, MethodCounters>,
+    rpc_latency: BTreeMap<String, LatencyHistogram>,
+    json_decode_errors: u64,
+    connects: u64,
+    last_frame_at: Option<u64>,
+    last_handshake_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeStats {
+    pub ts: u64,
+    pub frames_in_total: u64,
+    pub bytes_in_total: u64,
+    pub frames_out_total: u64,
+    pub bytes_out_total: u64,
+    pub methods: BTreeMap<String, MethodCounters>,
+    /// 短连接（rpc_call 等）上的收发，不计入上面的事件桥总数
+    pub rpc_frames_in_total: u64,
+    pub rpc_bytes_in_total: u64,
+    pub rpc_frames_out_total: u64,
+    pub rpc_bytes_out_total: u64,
+    pub rpc_methods: BTreeMap<String, MethodCounters>,
+    /// 整帧 JSON 解析失败次数
+    pub json_decode_errors: u64,
+    /// metrics 模块级类型解码失败次数（见 metrics_decode_stats）
+    pub metrics_decode_errors: u64,
+    pub connects: u64,
+    pub reconnects: u64,
+    pub last_frame_age_ms: Option<u64>,
+    pub last_handshake_ms: Option<u64>,
+    pub rpc_latency: BTreeMap<String, LatencyHistogram>,
+}
+
+impl Telemetry {
+    pub const fn new() -> Self {
+        Self {
+            methods: BTreeMap::new(),
+            rpc_methods: BTreeMap::new(),
+            rpc_latency: BTreeMap::new(),
+            json_decode_errors: 0,
+            connects: 0,
+            last_frame_at: None,
+            last_handshake_ms: None,
+        }
+    }
+
+    pub fn record_in(&mut self, method: &str, bytes: usize, now: u64) {
+        let c = self.methods.entry(method.to_string()).or_default();
+        c.frames_in += 1;
+        c.bytes_in += bytes as u64;
+        self.last_frame_at = Some(now);
+    }
+
+    pub fn record_out(&mut self, method: &str, bytes: usize) {
+        let c = self.methods.entry(method.to_string()).or_default();
+        c.frames_out += 1;
+        c.bytes_out += bytes as u64;
+    }
+
+    pub fn record_rpc_in(&mut self, method: &str, bytes: usize) {
+        let c = self.rpc_methods.entry(method.to_string()).or_default();
+        c.frames_in += 1;
+        c.bytes_in += bytes as u64;
+    }
+
+    pub fn record_rpc_out(&mut self, method: &str, bytes: usize) {
+        let c = self.rpc_methods.entry(method.to_string()).or_default();
+        c.frames_out += 1;
+        c.bytes_out += bytes as u64;
+    }
+
+    pub fn record_rpc(&mut self, method: &str, elapsed_ms: u64, ok: bool) {
+        self.rpc_latency
+            .entry(method.to_string())
+            .or_insert_with(LatencyHistogram::new)
+            .observe(elapsed_ms, ok);
+    }
+
+    pub fn snapshot(&self, now: u64, metrics_decode_errors: u64) -> BridgeStats {
+        let sum = |f: fn(&MethodCounters) -> u64| self.methods.values().map(f).sum::<u64>();
+        let rpc_sum = |f: fn(&MethodCounters) -> u64| self.rpc_methods.values().map(f).sum::<u64>();
+        BridgeStats {
+            ts: now,
+            frames_in_total: sum(|c| c.frames_in),
+            bytes_in_total: sum(|c| c.bytes_in),
+            frames_out_total: sum(|c| c.frames_out),
+            bytes_out_total: sum(|c| c.bytes_out),
+            methods: self.methods.clone(),
+            rpc_frames_in_total: rpc_sum(|c| c.frames_in),
+            rpc_bytes_in_total: rpc_sum(|c| c.bytes_in),
+            rpc_frames_out_total: rpc_sum(|c| c.frames_out),
+            rpc_bytes_out_total: rpc_sum(|c| c.bytes_out),
+            rpc_methods: self.rpc_methods.clone(),
+            json_decode_errors: self.json_decode_errors,
+            metrics_decode_errors,
+            connects: self.connects,
+            reconnects: self.connects.saturating_sub(1),
+            last_frame_age_ms: self.last_frame_at.map(|t| now.saturating_sub(t)),
+            last_handshake_ms: self.last_handshake_ms,
+            rpc_latency: self.rpc_latency.clone(),
+        }
+    }
+}
+
+static TELEMETRY: Mutex<Telemetry> = Mutex::new(Telemetry::new());
+
+fn with<F: FnOnce(&mut Telemetry)>(f: F) {
+    if let Ok(mut t) = TELEMETRY.lock() {
+        f(&mut t);
+    }
+}
+
+pub fn record_in(method: &str, bytes: usize) {
+    with(|t| t.record_in(method, bytes, crate::now_millis()));
+}
+
+pub fn record_out(method: &str, bytes: usize) {
+    with(|t| t.record_out(method, bytes));
+}
+
+pub fn record_rpc_in(method: &str, bytes: usize) {
+    with(|t| t.record_rpc_in(method, bytes));
+}
+
+pub fn record_rpc_out(method: &str, bytes: usize) {
+    with(|t| t.record_rpc_out(method, bytes));
+}
+
+pub fn record_rpc(method: &str, elapsed_ms: u64, ok: bool) {
+    with(|t| t.record_rpc(method, elapsed_ms, ok));
+}
+
+pub fn record_json_decode_error() {
+    with(|t| t.json_decode_errors += 1);
+}
+
+pub fn record_connect(handshake_ms: u64) {
+    with(|t| {
+        t.connects += 1;
+        t.last_handshake_ms = Some(handshake_ms);
+    });
+}
+
+#[tauri::command]
+pub fn bridge_stats() -> Result<BridgeStats, String> {
+    let decode_errors = crate::decode::decode_error_count();
+    TELEMETRY
+        .lock()
+        .map(|t| t.snapshot(crate::now_millis(), decode_errors))
+        .map_err(|_| "telemetry lock poisoned".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_counters_and_latency() {
+        let mut t = Telemetry::new();
+        t.record_in("metrics", 100, 1_000);
+        t.record_in("metrics", 150, 2_000);
+        t.record_out("hello", 80);
+        t.record_rpc("snapshot", 3, true);
+        t.record_rpc("snapshot", 9_000, false);
+
+        let s = t.snapshot(2_500, 4);
+        assert_eq!(s.frames_in_total, 2);
+        assert_eq!(s.bytes_in_total, 250);
+        assert_eq!(s.methods["hello"].bytes_out, 80);
+        assert_eq!(s.last_frame_age_ms, Some(500));
+        assert_eq!(s.metrics_decode_errors, 4);
+
+        let h = &s.rpc_latency["snapshot"];
+        assert_eq!(h.count, 2);
+        assert_eq!(h.errors, 1);
+        assert_eq!(h.counts[2], 1); // 3ms 落入 <=5ms 桶
+        assert_eq!(h.counts[LATENCY_BUCKETS_MS.len()], 1);
+        assert_eq!(h.max_ms, 9_000);
+    }
+
+    #[test]
+    fn reconnects_exclude_first_connect() {
+        let mut t = Telemetry::new();
+        assert_eq!(t.snapshot(0, 0).reconnects, 0);
+        t.connects = 3;
+        assert_eq!(t.snapshot(0, 0).reconnects, 2);
+    }
+
+    #[test]
+    fn short_connection_frames_do_not_count_as_bridge_traffic() {
+        let mut t = Telemetry::new();
+        t.record_in("metrics", 100, 1_000);
+        t.record_rpc_out("snapshot", 40);
+        t.record_rpc_in("snapshot", 300);
+
+        let s = t.snapshot(5_000, 0);
+        assert_eq!(s.frames_in_total, 1);
+        assert_eq!(s.bytes_in_total, 100);
+        assert_eq!(s.last_frame_age_ms, Some(4_000));
+        assert_eq!(s.rpc_frames_in_total, 1);
+        assert_eq!(s.rpc_bytes_out_total, 40);
+        assert!(!s.methods.contains_key("snapshot"));
+    }
+}
@@ -0,0 +1,304 @@
+// 单位归一化
+// 各契约的单位并不一致（query_history 文档写 memory.total_mb/used_mb，SnapshotResult 为 memory.total/used，
+// power 中 mAh/mV/mA/W 混用）。按元数据表把已知数值字段换算为统一单位，并把键名改为对应的单位后缀：
+// 字节 _bytes、字节每秒 _bytes_per_sec、比特每秒 _bits_per_sec、次每秒 _per_sec、百分比 _percent、伏 _v、安 _a、
+// 安时 _ah、瓦 _w、摄氏度 _c、秒 _s、赫兹 _hz。
+// 元数据先按完整路径/字段名精确匹配（无单位后缀的字段），再按后缀匹配；都不匹配的数值字段记入 units.unknown。
+// 纯数值数组（如 per_core_mhz、core_temps_c）按字段名整体换算。
+// 默认关闭（键名变化会影响现有前端），开启后作用于 metrics 事件与 snapshot / query_history 结果。
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Bytes,
+    Kibibytes,
+    Mebibytes,
+    BytesPerSec,
+    BitsPerSec,
+    MegabitsPerSec,
+    OpsPerSec,
+    Percent,
+    Volt,
+    Millivolt,
+    Ampere,
+    Milliampere,
+    AmpereHour,
+    MilliampereHour,
+    Watt,
+    Celsius,
+    Second,
+    Millisecond,
+    Minute,
+    Hour,
+    Hertz,
+    Megahertz,
+    Rpm,
+    Count,
+    TimestampMs,
+}
+
+impl Unit {
+    /// (统一单位, 换算系数)
+    pub fn canonical(self) -> (Unit, f64) {
+        match self {
+            Unit::Kibibytes => (Unit::Bytes, 1_024.0),
+            Unit::Mebibytes => (Unit::Bytes, 1_048_576.0),
+            Unit::MegabitsPerSec => (Unit::BitsPerSec, 1e6),
+            Unit::Millivolt => (Unit::Volt, 1e-3),
+            Unit::Milliampere => (Unit::Ampere, 1e-3),
+            Unit::MilliampereHour => (Unit::AmpereHour, 1e-3),
+            Unit::Millisecond => (Unit::Second, 1e-3),
+            Unit::Minute => (Unit::Second, 60.0),
+            Unit::Hour => (Unit::Second, 3_600.0),
+            Unit::Megahertz => (Unit::Hertz, 1e6),
+            u => (u, 1.0),
+        }
+    }
+}
+
+pub struct Rule {
+    /// 精确规则为完整路径（含 .）或字段名；后缀规则为字段名后缀
+    pub pattern: &'static str,
+    pub unit: Unit,
+    /// 换算后的键名或后缀；None 表示键名不变
+    pub rename: Option<&'static str>,
+}
+
+const fn rule(pattern: &'static str, unit: Unit, rename: Option<&'static str>) -> Rule {
+    Rule { pattern, unit, rename }
+}
+
+pub const EXACT: &[Rule] = &[
+    rule("ts", Unit::TimestampMs, None),
+    rule("seq", Unit::Count, None),
+    rule("memory.total", Unit::Mebibytes, Some("total_bytes")),
+    rule("memory.used", Unit::Mebibytes, Some("used_bytes")),
+    rule("percentage", Unit::Percent, None),
+    rule("nvme_percentage_used", Unit::Percent, None),
+    rule("queue_length", Unit::Count, None),
+    rule("partitions", Unit::Count, None),
+    rule("pid", Unit::Count, None),
+];
+
+/// 按顺序匹配，较长的后缀在前
+pub const SUFFIXES: &[Rule] = &[
+    rule("_bytes_per_sec", Unit::BytesPerSec, None),
+    rule("_bits_per_sec", Unit::BitsPerSec, None),
+    // 其余 *_per_sec 为次数速率（包、错误、系统调用等），须排在 _sec 之前
+    rule("_per_sec", Unit::OpsPerSec, None),
+    rule("_mbps", Unit::MegabitsPerSec, Some("_bits_per_sec")),
+    rule("_bytes", Unit::Bytes, None),
+    rule("_kb", Unit::Kibibytes, Some("_bytes")),
+    rule("_mb", Unit::Mebibytes, Some("_bytes")),
+    rule("_iops", Unit::OpsPerSec, None),
+    rule("_percent", Unit::Percent, None),
+    rule("_mah", Unit::MilliampereHour, Some("_ah")),
+    rule("_ah", Unit::AmpereHour, None),
+    rule("_mv", Unit::Millivolt, Some("_v")),
+    rule("_v", Unit::Volt, None),
+    rule("_ma", Unit::Milliampere, Some("_a")),
+    rule("_a", Unit::Ampere, None),
+    rule("_watts", Unit::Watt, Some("_w")),
+    rule("_w", Unit::Watt, None),
+    rule("_c", Unit::Celsius, None),
+    rule("_hours", Unit::Hour, Some("_s")),
+    rule("_min", Unit::Minute, Some("_s")),
+    rule("_sec", Unit::Second, Some("_s")),
+    rule("_ms", Unit::Millisecond, Some("_s")),
+    rule("_s", Unit::Second, None),
+    rule("_mhz", Unit::Megahertz, Some("_hz")),
+    rule("_hz", Unit::Hertz, None),
+    rule("_rpm", Unit::Rpm, None),
+    rule("_count", Unit::Count, None),
+    rule("_ts", Unit::TimestampMs, None),
+];
+
+/// 查找字段的单位与换算后的键名；path 为不含数组下标的点分路径
+pub fn lookup(path: &str, key: &str) -> Option<(Unit, String)> {
+    if let Some(r) = EXACT.iter().find(|r| r.pattern == path || (!r.pattern.contains('.') && r.pattern == key)) {
+        return Some((r.unit, r.rename.unwrap_or(key).to_string()));
+    }
+    let r = SUFFIXES.iter().find(|r| key.ends_with(r.pattern))?;
+    let name = match r.rename {
+        Some(suffix) => format!("{}{}", &key[..key.len() - r.pattern.len()], suffix),
+        None => key.to_string(),
+    };
+    Some((r.unit, name))
+}
+
+/// 数值、null 或非空的纯数值数组按字段名换算
+fn is_numeric(v: &Value) -> bool {
+    match v {
+        Value::Number(_) | Value::Null => true,
+        Value::Array(items) => !items.is_empty() && items.iter().all(|i| i.is_number() || i.is_null()),
+        _ => false,
+    }
+}
+
+fn scale(v: &mut Value, factor: f64) {
+    match v {
+        Value::Array(items) => items.iter_mut().for_each(|i| scale(i, factor)),
+        Value::Number(n) if factor != 1.0 => {
+            if let Some(f) = n.as_f64() {
+                *v = serde_json::json!(f * factor);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn walk(obj: &mut Map<String, Value>, prefix: &str, unknown: &mut Vec<String>) {
+    let keys: Vec<String> = obj.keys().cloned().collect();
+    for key in keys {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        // 派生指标与单位信息本身不参与换算
+        if prefix.is_empty() && (key == "derived" || key == "units") {
+            continue;
+        }
+        match obj.get_mut(&key) {
+            Some(Value::Object(child)) => walk(child, &path, unknown),
+            Some(v) if is_numeric(v) => match lookup(&path, &key) {
+                Some((unit, name)) => {
+                    let (_, factor) = unit.canonical();
+                    let mut converted = v.take();
+                    scale(&mut converted, factor);
+                    if name != key {
+                        obj.remove(&key);
+                    }
+                    obj.insert(name, converted);
+                }
+                None if !v.is_null() && !unknown.contains(&path) => unknown.push(path),
+                None => {}
+            },
+            Some(Value::Array(items)) => {
+                for item in items.iter_mut() {
+                    if let Value::Object(child) = item {
+                        walk(child, &path, unknown);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 就地换算一帧/一项；写入 units.unknown（未知单位的数值字段路径，数组元素不含下标）
+pub fn normalize(frame: &mut Value) {
+    let Value::Object(obj) = frame else { return };
+    let mut unknown = Vec::new();
+    walk(obj, "", &mut unknown);
+    obj.insert("units".into(), serde_json::json!({ "unknown": unknown }));
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 由 dispatch_notification 对每帧实时 metrics 调用；换算了返回 true
+pub fn apply_live(frame: &mut Value) -> bool {
+    if !enabled() {
+        return false;
+    }
+    normalize(frame);
+    true
+}
+
+/// snapshot 结果为单帧，query_history 类结果为 { ok, items }
+pub fn apply_result(result: &mut Value) {
+    if !enabled() {
+        return;
+    }
+    match result.get_mut("items") {
+        Some(Value::Array(items)) => items.iter_mut().for_each(normalize),
+        _ => normalize(result),
+    }
+}
+
+#[tauri::command]
+pub fn set_unit_normalization(enabled: bool) -> bool {
+    ENABLED.swap(enabled, Ordering::SeqCst)
+}
+
+#[tauri::command]
+pub fn get_unit_normalization() -> Value {
+    let describe = |r: &Rule| {
+        let (canonical, factor) = r.unit.canonical();
+        serde_json::json!({ "match": r.pattern, "unit": r.unit, "canonical": canonical, "factor": factor, "rename": r.rename })
+    };
+    serde_json::json!({
+        "enabled": enabled(),
+        "exact": EXACT.iter().map(describe).collect::<Vec<_>>(),
+        "suffixes": SUFFIXES.iter().map(describe).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn converts_and_renames_known_fields() {
+        let mut frame = json!({
+            "ts": 1, "seq": 2,
+            "memory": { "total_mb": 2, "used": 1 },
+            "power": { "battery": { "percentage": 50, "voltage_mv": 12_000, "current_ma": -500, "full_charge_capacity_mah": 4_000,
+                                    "power_w": 6.0, "time_remaining_min": 90, "temperature_c": null } },
+            "derived": { "whatever": 1 }
+        });
+        normalize(&mut frame);
+        assert_eq!(frame["memory"], json!({ "total_bytes": 2_097_152.0, "used_bytes": 1_048_576.0 }));
+        assert_eq!(
+            frame["power"]["battery"],
+            json!({ "percentage": 50, "voltage_v": 12.0, "current_a": -0.5, "full_charge_capacity_ah": 4.0,
+                    "power_w": 6.0, "time_remaining_s": 5_400.0, "temperature_c": null })
+        );
+        assert_eq!(frame["units"], json!({ "unknown": [] }));
+        assert_eq!(frame["derived"], json!({ "whatever": 1 }));
+    }
+
+    #[test]
+    fn flags_unknown_fields_once_across_arrays() {
+        let mut frame = json!({ "disk": { "per_volume_io": [{ "volume_id": "C:", "mystery": 1, "read_iops": 3 }, { "mystery": 2 }] }, "gpu": { "fan": 1200 } });
+        normalize(&mut frame);
+        assert_eq!(frame["units"]["unknown"], json!(["disk.per_volume_io.mystery", "gpu.fan"]));
+        assert_eq!(frame["disk"]["per_volume_io"][0]["read_iops"], json!(3));
+    }
+
+    #[test]
+    fn rates_clocks_links_and_numeric_arrays() {
+        let mut frame = json!({
+            "network": { "interfaces": [{ "rx_packets_per_sec": 10, "tx_errors_per_sec": 0, "link_speed_mbps": 1000, "tx_phy_rate_mbps": 866.5 }] },
+            "system": { "syscalls_per_sec": 5, "pages_out_per_sec": 1 },
+            "cpu": { "max_clock_speed_mhz": 3000, "per_core_mhz": [3000, null], "core_temps_c": [40, 42], "l2_cache_size_kb": 512 },
+            "sensors": { "voltages_v": [1.2], "mystery_list": [1, 2] }
+        });
+        normalize(&mut frame);
+        // *_per_sec 为次数速率，不能被 _sec 当作秒换算/改名
+        assert_eq!(frame["network"]["interfaces"][0], json!({
+            "rx_packets_per_sec": 10, "tx_errors_per_sec": 0, "link_speed_bits_per_sec": 1e9, "tx_phy_rate_bits_per_sec": 866.5e6
+        }));
+        assert_eq!(frame["system"], json!({ "syscalls_per_sec": 5, "pages_out_per_sec": 1 }));
+        assert_eq!(frame["cpu"], json!({
+            "max_clock_speed_hz": 3e9, "per_core_hz": [3e9, null], "core_temps_c": [40, 42], "l2_cache_size_bytes": 524_288.0
+        }));
+        assert_eq!(frame["sensors"]["voltages_v"], json!([1.2]));
+        assert_eq!(frame["units"]["unknown"], json!(["sensors.mystery_list"]));
+    }
+
+    #[test]
+    fn normalization_is_idempotent() {
+        let mut once = json!({ "memory": { "total": 1 }, "power": { "adapter": { "rated_watts": 65, "voltage_mv": 20_000 } } });
+        normalize(&mut once);
+        let mut twice = once.clone();
+        normalize(&mut twice);
+        assert_eq!(once, twice);
+        assert_eq!(once["power"]["adapter"], json!({ "rated_w": 65, "voltage_v": 20.0 }));
+    }
+}
@@ -0,0 +1,19 @@
+import { describe, it, expect } from 'vitest';
+import { applyMergePatch, createDeltaDecoder } from '../metricsDelta';
+
+describe('metrics delta', () => {
+  it('applies merge patch per RFC 7396', () => {
+    const a = { ts: 1, cpu: { usage_percent: 10 }, disk: { per_volume: [1, 2] }, gpu: 1 };
+    const p = { ts: 2, disk: { per_volume: [1, 3] }, gpu: null };
+    expect(applyMergePatch(a, p)).toEqual({ ts: 2, cpu: { usage_percent: 10 }, disk: { per_volume: [1, 3] } });
+    expect(a.gpu).toBe(1);
+  });
+
+  it('rebuilds frames and detects broken chains', () => {
+    const decode = createDeltaDecoder();
+    expect(decode({ kind: 'delta', id: 1, base_id: 0, data: {} })).toBeNull();
+    expect(decode({ kind: 'key', id: 2, base_id: null, data: { ts: 1, cpu: 1 } })).toEqual({ ts: 1, cpu: 1 });
+    expect(decode({ kind: 'delta', id: 3, base_id: 2, data: { ts: 2 } })).toEqual({ ts: 2, cpu: 1 });
+    expect(decode({ kind: 'delta', id: 5, base_id: 4, data: { ts: 3 } })).toBeNull();
+  });
+});
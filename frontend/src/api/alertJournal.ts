@@ -0,0 +1,38 @@
+// 告警日志（Rust 侧 alertjournal.rs）：告警收件箱，记录触发、解除、确认与静音区间
+
+export type JournalEntry = {
+  id: number;
+  key: string;
+  rule_id: string | null;
+  level: string;
+  metric: string;
+  message: string;
+  threshold: number | null;
+  value: number | null;
+  fired_ts: number;
+  last_ts: number;
+  count: number;
+  resolved_ts: number | null;
+  acked_ts: number | null;
+  snoozes: { from: number; until: number }[];
+  suppressed: number;
+};
+
+async function call<T>(cmd: string, args?: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<T>(cmd, args);
+}
+
+export const alertJournal = {
+  list: (openOnly?: boolean, limit?: number) => call<JournalEntry[]>('list_alert_journal', { openOnly, limit }),
+  ack: (id: number) => call<JournalEntry>('ack_alert', { id }),
+  snooze: (id: number, durationMs: number) => call<JournalEntry>('snooze_alert', { id, durationMs }),
+  onChange(listener: (e: JournalEntry) => void) {
+    let unlisten: (() => void) | null = null;
+    import('@tauri-apps/api/event')
+      .then(({ listen }) => listen('alert_journal', (evt: any) => listener(evt?.payload)))
+      .then((fn) => { unlisten = fn; })
+      .catch(() => { /* 非 Tauri 环境忽略 */ });
+    return () => { if (unlisten) unlisten(); };
+  },
+};
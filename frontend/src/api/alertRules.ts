@@ -0,0 +1,50 @@
+// 告警规则（Rust 侧 alerts.rs / alertrules.rs）：规则保存在应用配置目录的 alert_rules.json，外部编辑会自动重新加载
+
+export type AlertRule = {
+  id: string;
+  enabled?: boolean;
+  expr?: string;
+  metric?: string;
+  op?: '>' | '>=' | '<' | '<=' | '==' | '!=';
+  threshold?: number;
+  for_ms?: number;
+  while?: { metric: string; op: string; value: unknown };
+  hysteresis?: number;
+  cooldown_ms?: number;
+  level?: 'info' | 'warn' | 'error';
+  message?: string;
+};
+
+export type RulesError = { line: number; column: number; message: string };
+
+export type RulesStatus = { path: string | null; rules: AlertRule[]; error: RulesError | null };
+
+export type RuleState = {
+  rule_id: string;
+  active: boolean;
+  pending_since: number | null;
+  last_raised: number | null;
+  last_value: number | null;
+};
+
+async function call<T>(cmd: string, args?: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<T>(cmd, args);
+}
+
+export const alertRules = {
+  list: () => call<RulesStatus>('list_alert_rules'),
+  upsert: (rule: AlertRule) => call<boolean>('upsert_alert_rule', { rule }),
+  remove: (id: string) => call<boolean>('delete_alert_rule', { id }),
+  test: (rule: AlertRule, frames?: unknown[]) =>
+    call<{ frames: number; transitions: { event: string; payload: unknown }[]; state: RuleState }>('test_alert_rule', { rule, frames }),
+  states: () => call<RuleState[]>('get_alert_states'),
+  onReloaded(listener: (r: { ok: boolean; count: number | null; error: RulesError | null }) => void) {
+    let unlisten: (() => void) | null = null;
+    import('@tauri-apps/api/event')
+      .then(({ listen }) => listen('alert_rules_reloaded', (evt: any) => listener(evt?.payload)))
+      .then((fn) => { unlisten = fn; })
+      .catch(() => { /* 非 Tauri 环境忽略 */ });
+    return () => { if (unlisten) unlisten(); };
+  },
+};
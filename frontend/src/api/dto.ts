@@ -127,6 +127,8 @@ export type TopProcessDisk = {
 
 export type SnapshotResult = {
   ts: number;
+  // 桥接侧派生指标（derived.rs），如 memory_used_percent
+  derived?: Record<string, number | null>;
   cpu?: { usage_percent: number };
   memory?: { total: number; used: number };
   disk?: {
@@ -230,10 +232,22 @@ export type QueryHistoryParams = {
   modules?: string[];
   step_ms?: number | null;
   agg?: 'raw' | '10s' | '1m';
+  // 仅 Tauri 桥接支持：按图表宽度降采样，不转发给服务端
+  downsample?: DownsampleSpec | null;
+};
+
+export type DownsampleSpec = {
+  points: number;
+  method?: 'lttb' | 'minmax' | 'avg';
+  field?: string;
+  // 多条序列时各自选点，点数预算均分，结果取并集
+  fields?: string[];
 };
 
 export type QueryHistoryItem = {
   ts: number;
+  // 桥接侧派生指标（derived.rs），如 memory_used_percent
+  derived?: Record<string, number | null>;
   cpu?: { usage_percent: number } | null;
   memory?: { total: number; used: number } | null;
   disk?: SnapshotResult['disk'] | null;
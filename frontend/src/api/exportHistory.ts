@@ -0,0 +1,49 @@
+// 历史导出（Rust 侧 export.rs）：先经 pickExportPath() 让用户在保存对话框中选定文件，再导出到该路径，期间通过 export_progress 事件报告进度
+
+export type ExportParams = {
+  // pickExportPath() 返回的路径，每个只能导出一次
+  path: string;
+  format?: 'csv' | 'ndjson';
+  source?: 'service' | 'local';
+  from_ts: number;
+  to_ts: number;
+  modules?: string[];
+  step_ms?: number | null;
+  agg?: 'raw' | '10s' | '1m';
+  // 点分路径，如 'disk.totals.read_bytes_per_sec'
+  columns?: string[];
+  chunk_ms?: number;
+};
+
+export type ExportProgress = {
+  export_id: number;
+  path: string;
+  rows: number;
+  bytes: number;
+  progress: number;
+  done: boolean;
+  error: string | null;
+};
+
+export async function exportHistory(params: ExportParams, onProgress?: (p: ExportProgress) => void): Promise<ExportProgress> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  let unlisten: (() => void) | null = null;
+  if (onProgress) {
+    const { listen } = await import('@tauri-apps/api/event');
+    // 按 path 区分并发导出（export_id 在命令返回前未知）
+    unlisten = await listen<ExportProgress>('export_progress', (evt) => {
+      if (evt.payload?.path === params.path) onProgress(evt.payload);
+    });
+  }
+  try {
+    return await invoke<ExportProgress>('export_history', { params });
+  } finally {
+    if (unlisten) unlisten();
+  }
+}
+
+// 弹出保存对话框；用户取消时返回 null
+export async function pickExportPath(defaultName?: string, format: 'csv' | 'ndjson' = 'csv'): Promise<string | null> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<string | null>('pick_export_path', { defaultName, format });
+}
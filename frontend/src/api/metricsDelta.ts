@@ -0,0 +1,64 @@
+// metrics 增量模式（Rust 侧 delta.rs）：关键帧 + JSON Merge Patch（RFC 7396）
+// onMetricsDelta 负责开启当前窗口的增量模式并还原整帧，回调收到的与 onMetrics 相同
+
+export type DeltaMessage = { kind: 'key' | 'delta'; id: number; base_id: number | null; data: any };
+
+const isObject = (v: any) => v !== null && typeof v === 'object' && !Array.isArray(v);
+
+export function applyMergePatch(target: any, patch: any): any {
+  if (!isObject(patch)) return patch;
+  const out: any = isObject(target) ? { ...target } : {};
+  for (const [k, v] of Object.entries(patch)) {
+    if (v === null) delete out[k];
+    else out[k] = applyMergePatch(out[k], v);
+  }
+  return out;
+}
+
+/** 还原器：断链（base_id 不连续）时返回 null，调用方需请求关键帧 */
+export function createDeltaDecoder() {
+  let frame: any = null;
+  let lastId: number | null = null;
+  return (msg: DeltaMessage): any | null => {
+    if (msg.kind === 'key') {
+      frame = msg.data;
+    } else if (frame !== null && msg.base_id === lastId) {
+      frame = applyMergePatch(frame, msg.data);
+    } else {
+      return null;
+    }
+    lastId = msg.id;
+    return frame;
+  };
+}
+
+export function onMetricsDelta(listener: (payload: any) => void, opts?: { keyframeIntervalMs?: number }) {
+  let unlisten: (() => void) | null = null;
+  let disposed = false;
+  const decode = createDeltaDecoder();
+  (async () => {
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      // metrics_delta 按窗口投递，全局监听会收到其他窗口的增量而断链
+      const { listenWindow } = await import('./windowEvents');
+      const fn = await listenWindow('metrics_delta', (evt: any) => {
+        const frame = decode(evt?.payload as DeltaMessage);
+        if (frame === null) {
+          invoke('metrics_request_keyframe').catch(() => { /* ignore */ });
+          return;
+        }
+        listener(frame);
+      });
+      if (disposed) { fn(); return; }
+      unlisten = fn;
+      await invoke('metrics_delta_enable', { enable: true, keyframeIntervalMs: opts?.keyframeIntervalMs });
+    } catch { /* 非 Tauri 环境忽略 */ }
+  })();
+  return () => {
+    disposed = true;
+    if (unlisten) unlisten();
+    import('@tauri-apps/api/core')
+      .then(({ invoke }) => invoke('metrics_delta_enable', { enable: false }))
+      .catch(() => { /* ignore */ });
+  };
+}
@@ -0,0 +1,42 @@
+// 基于 Tauri Channel 的 metrics 专用推送（Rust 侧 stream.rs）
+// 每个调用方一条独立、有序的数据流；dropped > 0 表示积压期间被丢弃的帧数
+// 每条消息处理后以 stream_ack 确认；未确认数达到 maxInFlight 时 Rust 侧暂停发送，长时间无确认则结束订阅
+
+export type StreamMessage = { seq: number; dropped: number; frame: any };
+
+export function subscribeStream(
+  listener: (frame: any, msg: StreamMessage) => void,
+  opts?: { modules?: string[]; maxQueue?: number; maxInFlight?: number }
+) {
+  let streamId: number | null = null;
+  let disposed = false;
+  let pendingAck: number | null = null;
+  (async () => {
+    try {
+      const { invoke, Channel } = await import('@tauri-apps/api/core');
+      const channel = new Channel<StreamMessage>();
+      channel.onmessage = (msg) => {
+        if (disposed) return;
+        try { listener(msg.frame, msg); } finally {
+          // 订阅 id 返回前到达的消息先记下，拿到 id 后补确认，否则在途窗口无法腾出
+          if (streamId !== null) invoke('stream_ack', { streamId, seq: msg.seq }).catch(() => { /* ignore */ });
+          else pendingAck = msg.seq;
+        }
+      };
+      const id = await invoke<number>('subscribe_stream', {
+        channel, modules: opts?.modules, maxQueue: opts?.maxQueue, maxInFlight: opts?.maxInFlight
+      });
+      if (disposed) { invoke('unsubscribe_stream', { streamId: id }).catch(() => { /* ignore */ }); return; }
+      streamId = id;
+      if (pendingAck !== null) invoke('stream_ack', { streamId: id, seq: pendingAck }).catch(() => { /* ignore */ });
+    } catch { /* 非 Tauri 环境忽略 */ }
+  })();
+  return () => {
+    disposed = true;
+    if (streamId === null) return;
+    const id = streamId;
+    import('@tauri-apps/api/core')
+      .then(({ invoke }) => invoke('unsubscribe_stream', { streamId: id }))
+      .catch(() => { /* ignore */ });
+  };
+}
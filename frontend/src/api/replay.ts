@@ -0,0 +1,36 @@
+// 离线回放（Rust 侧 replay.rs）：打开导出的 NDJSON 或抓包文件，metrics 事件、snapshot 与 query_history 改由文件提供
+
+export type ReplayStatus = {
+  active: boolean;
+  path: string;
+  playing: boolean;
+  speed: number;
+  position_ts: number;
+  from_ts: number;
+  to_ts: number;
+  index: number;
+  frames: number;
+};
+
+async function call<T>(cmd: string, args?: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<T>(cmd, args);
+}
+
+export const replay = {
+  open: (path: string) => call<ReplayStatus>('replay_open', { path }),
+  close: () => call<boolean>('replay_close'),
+  play: () => call<ReplayStatus>('replay_play'),
+  pause: () => call<ReplayStatus>('replay_pause'),
+  seek: (ts: number) => call<ReplayStatus>('replay_seek', { ts }),
+  setSpeed: (speed: number) => call<ReplayStatus>('replay_set_speed', { speed }),
+  status: () => call<ReplayStatus | null>('replay_status'),
+  onState(listener: (s: ReplayStatus | { active: false }) => void) {
+    let unlisten: (() => void) | null = null;
+    import('@tauri-apps/api/event')
+      .then(({ listen }) => listen('replay_state', (evt: any) => listener(evt?.payload)))
+      .then((fn) => { unlisten = fn; })
+      .catch(() => { /* 非 Tauri 环境忽略 */ });
+    return () => { if (unlisten) unlisten(); };
+  },
+};
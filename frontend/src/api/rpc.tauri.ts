@@ -44,8 +44,9 @@ export const tauriRpc = {
   onMetrics(listener: (payload: any) => void) {
     let unlisten: (() => void) | null = null;
     // 动态引入事件 API，避免在纯 Web 环境编译/运行报错
-    import('@tauri-apps/api/event')
-      .then(({ listen }) => listen('metrics', (evt: any) => {
+    // 按窗口监听：全局监听无法被增量模式（metrics_delta_enable）排除
+    import('./windowEvents')
+      .then(({ listenWindow }) => listenWindow('metrics', (evt: any) => {
         // 标记已收到 metrics，停止 ensureEventBridge 的重试/强制订阅
         try { const w: any = typeof window !== 'undefined' ? window : {}; w.__METRICS_READY = true; } catch {}
         listener(evt?.payload);
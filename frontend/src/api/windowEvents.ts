@@ -0,0 +1,8 @@
+// 窗口作用域的事件监听
+// 全局 listen() 注册的目标为 EventTarget::Any，Rust 侧 emit_filter/emit_to 无法将其排除；
+// 按窗口投递的事件（metrics 在增量模式下跳过该窗口、metrics_delta 只发给开启的窗口）必须经此监听
+
+export async function listenWindow<T = any>(event: string, handler: (evt: { payload: T }) => void): Promise<() => void> {
+  const { getCurrentWebviewWindow } = await import('@tauri-apps/api/webviewWindow');
+  return getCurrentWebviewWindow().listen<T>(event, handler);
+}
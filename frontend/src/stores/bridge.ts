@@ -1,5 +1,10 @@
 import { defineStore } from 'pinia';
 
+// bridge_stats 轮询句柄；store 重复 init 时不叠加定时器与监听
+let statsTimer: ReturnType<typeof setInterval> | null = null;
+let unlistens: Array<() => void> = [];
+let initialized = false;
+
 export type BridgeState = {
   status: 'idle' | 'connecting' | 'connected' | 'disconnected' | 'error';
   rx: number;
@@ -12,6 +17,8 @@ export const useBridgeStore = defineStore('bridge', {
   state: (): BridgeState => ({ status: 'idle', rx: 0, err: 0, lastEvent: '', lastAt: 0 }),
   actions: {
     init() {
+      if (initialized) return;
+      initialized = true;
       const w: any = typeof window !== 'undefined' ? window : {};
       // 初始状态读取
       try { if (w.__BRIDGE_STATUS__) this.status = w.__BRIDGE_STATUS__; } catch {}
@@ -19,12 +26,29 @@ export const useBridgeStore = defineStore('bridge', {
       (async () => {
         try {
           const { listen } = await import('@tauri-apps/api/event');
-          await listen('bridge_handshake', () => { this.status = 'connected'; this.lastEvent = 'handshake'; this.lastAt = Date.now(); });
-          await listen('bridge_disconnected', () => { this.status = 'disconnected'; this.lastEvent = 'disconnected'; this.lastAt = Date.now(); });
-          await listen('bridge_error', () => { this.status = 'error'; this.err++; this.lastEvent = 'error'; this.lastAt = Date.now(); });
-          await listen('bridge_rx', () => { this.rx++; this.lastEvent = 'rx'; this.lastAt = Date.now(); });
+          unlistens.push(await listen('bridge_handshake', () => { this.status = 'connected'; this.lastEvent = 'handshake'; this.lastAt = Date.now(); }));
+          unlistens.push(await listen('bridge_disconnected', () => { this.status = 'disconnected'; this.lastEvent = 'disconnected'; this.lastAt = Date.now(); }));
+          unlistens.push(await listen('bridge_error', () => { this.status = 'error'; this.err++; this.lastEvent = 'error'; this.lastAt = Date.now(); }));
+        } catch { /* 非 Tauri 环境 */ }
+        // 收帧计数取自 Rust 侧 bridge_stats（逐帧 bridge_rx 事件默认关闭；只含事件桥，不含 rpc_call 短连接）
+        try {
+          const { invoke } = await import('@tauri-apps/api/core');
+          if (!initialized) return; // dispose 先于动态导入完成
+          if (statsTimer) clearInterval(statsTimer);
+          statsTimer = setInterval(async () => {
+            try {
+              const s: any = await invoke('bridge_stats');
+              if (s && s.frames_in_total !== this.rx) { this.rx = s.frames_in_total; this.lastEvent = 'rx'; this.lastAt = Date.now(); }
+            } catch { /* 桥未就绪，忽略 */ }
+          }, 2000);
         } catch { /* 非 Tauri 环境 */ }
       })();
+    },
+    dispose() {
+      if (statsTimer) { clearInterval(statsTimer); statsTimer = null; }
+      for (const un of unlistens) { try { un(); } catch {} }
+      unlistens = [];
+      initialized = false;
     }
   }
 });